@@ -0,0 +1,73 @@
+use std::time::{Duration, Instant};
+
+use tan::{
+    api::eval_string,
+    context::{Context, ContextBuilder},
+};
+
+// #insight
+// Compares the multi-method dispatch with and without the dispatch cache, on
+// arithmetic-heavy code. Run with `cargo bench --bench dispatch`.
+
+const INPUT: &str = r#"
+(let square (Func x (* x x)))
+(let sum-of-squares (Func n
+    (let i 0)
+    (let sum 0)
+    (let total 0.0)
+    (while (< i n)
+        (<- sum (+ sum (square i)))
+        (<- total (+ total (* 0.5 (int->float i))))
+        (<- i (+ i 1))
+    )
+    [sum total]
+))
+(sum-of-squares 20000)
+"#;
+
+const RUNS: usize = 5;
+
+fn context(dispatch_cache: bool) -> Context {
+    let context = ContextBuilder::new()
+        .root_path(env!("CARGO_MANIFEST_DIR"))
+        .dispatch_cache(dispatch_cache)
+        .try_build()
+        .expect("the context should be created");
+
+    // The operators are overloaded, the methods are resolved dynamically.
+    context.register("+", |a: i64, b: i64| a + b);
+    context.register("+", |a: f64, b: f64| a + b);
+    context.register("*", |a: i64, b: i64| a * b);
+    context.register("*", |a: f64, b: f64| a * b);
+    context.register("<", |a: i64, b: i64| a < b);
+    context.register("<", |a: f64, b: f64| a < b);
+    context.register("int->float", |n: i64| n as f64);
+
+    context
+}
+
+/// Returns the best time of the runs.
+fn bench(dispatch_cache: bool) -> Duration {
+    let mut best = Duration::MAX;
+
+    for _ in 0..RUNS {
+        let mut context = context(dispatch_cache);
+        let start = Instant::now();
+        eval_string(INPUT, &mut context).expect("the benchmark should not fail");
+        best = best.min(start.elapsed());
+    }
+
+    best
+}
+
+fn main() {
+    let uncached = bench(false);
+    let cached = bench(true);
+
+    println!("dispatch, uncached: {uncached:?}");
+    println!("dispatch, cached:   {cached:?}");
+    println!(
+        "speedup: {:.2}x",
+        uncached.as_secs_f64() / cached.as_secs_f64()
+    );
+}
@@ -0,0 +1,63 @@
+use std::time::{Duration, Instant};
+
+use tan::{
+    api::eval_string,
+    context::{Context, ContextBuilder, Engine},
+};
+
+// #insight
+// Compares the tree-walking evaluator with the VM, on a loop-heavy function
+// with `let` locals. Run with `cargo bench --bench engines`.
+
+const INPUT: &str = r#"
+(let square (Func x (* x x)))
+(let sum-of-squares (Func n
+    (let i 0)
+    (let sum 0)
+    (while (< i n)
+        (<- sum (+ sum (square i)))
+        (<- i (+ i 1))
+    )
+    sum
+))
+(sum-of-squares 50000)
+"#;
+
+const RUNS: usize = 10;
+
+fn context(engine: Engine) -> Context {
+    let context = ContextBuilder::new()
+        .root_path(env!("CARGO_MANIFEST_DIR"))
+        .engine(engine)
+        .try_build()
+        .expect("the context should be created");
+
+    context.register("+", |a: i64, b: i64| a + b);
+    context.register("*", |a: i64, b: i64| a * b);
+    context.register("<", |a: i64, b: i64| a < b);
+
+    context
+}
+
+/// Returns the time of a run.
+fn run(engine: Engine) -> Duration {
+    let mut context = context(engine);
+    let start = Instant::now();
+    eval_string(INPUT, &mut context).expect("the benchmark should not fail");
+    start.elapsed()
+}
+
+fn main() {
+    // #insight The runs are interleaved, the best time of each engine is kept.
+    let mut eval = Duration::MAX;
+    let mut vm = Duration::MAX;
+
+    for _ in 0..RUNS {
+        eval = eval.min(run(Engine::Eval));
+        vm = vm.min(run(Engine::Vm));
+    }
+
+    println!("sum-of-squares, eval: {eval:?}");
+    println!("sum-of-squares, vm:   {vm:?}");
+    println!("speedup: {:.2}x", eval.as_secs_f64() / vm.as_secs_f64());
+}
@@ -4,7 +4,7 @@ use std::path::Path;
 
 use crate::{
     check::check,
-    context::Context,
+    context::{Context, Engine},
     error::Error,
     eval::eval,
     expr::Expr,
@@ -14,7 +14,9 @@ use crate::{
     parser::Parser,
     prune::prune,
     range::Position,
+    resolve_locals::resolve_locals,
     util::fs::get_full_extension,
+    vm::Vm,
 };
 
 pub const TAN_FILE_EXTENSION: &str = "tan";
@@ -139,8 +141,6 @@ pub fn compile(expr: Expr, context: &mut Context) -> Result<Expr, Vec<Error>> {
     // #todo pass a dummy scope here? no need to polute the dyn-time environment with macro stuff.
     let expr = macro_expand(expr, context);
 
-    // #todo bug, macro_expand strips let annotation!
-
     // #todo temp hack until macro_expand returns multiple errors.
     let Ok(expr) = expr else {
         return Err(vec![expr.unwrap_err()]);
@@ -168,6 +168,10 @@ pub fn compile(expr: Expr, context: &mut Context) -> Result<Expr, Vec<Error>> {
     // #todo should run after resolve?
     let expr = optimize(expr);
 
+    // Lexical addressing pass, after macro expansion.
+
+    let expr = resolve_locals(expr);
+
     // Resolve pass (typechecking, definitions, etc)
 
     // #todo should we push a new env?
@@ -224,9 +228,13 @@ pub fn eval_string(input: impl AsRef<str>, context: &mut Context) -> Result<Expr
     let exprs = compile_string(input, context)?;
 
     let mut last_value = Expr::None;
+    let mut vm = Vm::new();
 
     for expr in exprs {
-        let value = eval(&expr, context);
+        let value = context.check_budget().and_then(|_| match context.engine {
+            Engine::Eval => eval(&expr, context),
+            Engine::Vm => vm.eval(&expr, context),
+        });
 
         let Ok(value) = value else {
             return Err(vec![value.unwrap_err()]);
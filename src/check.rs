@@ -1,4 +1,7 @@
-use crate::{error::Error, expr::Expr};
+use crate::{
+    error::Error,
+    expr::{check_key, Expr},
+};
 
 // #todo Not the best name, too general, confusing with the upcoming `unchecked` concept.
 // #todo Interesting name: vet! (Golang)
@@ -15,19 +18,32 @@ pub fn check_fn(expr: Expr) -> Result<Expr, Error> {
                 // #todo This may become Expr::typ
                 if let Some(s) = terms[0].as_symbol() {
                     // #todo it's weird that we are special-handling "Map" here.
-                    if s == "Map" {
+                    if s == "Map" || s == "PersistentMap" {
                         // Check that the Map constructor has an even number of arguments.
                         // #insight Should be odd, including the op.
                         if terms.len() % 2 == 0 {
                             // #todo Investigate why expr has no range here!
                             return Err(Error::invalid_arguments(
-                                "missing argument in Map constructor",
+                                &format!("missing argument in {s} constructor"),
                                 terms[0].range(),
                             ));
                         }
+                        // #insight
+                        // The literal keys are not evaluated, invalid keys
+                        // (e.g. NaN) are reported before they reach a hashed
+                        // collection.
+                        for key in terms[1..].iter().step_by(2) {
+                            check_key(key, terms[0].range())?;
+                        }
                         // #todo Check that '_' inference is correct.
                         return Ok(expr);
                     }
+                    if s == "PersistentSet" {
+                        for item in &terms[1..] {
+                            check_key(item, terms[0].range())?;
+                        }
+                        return Ok(expr);
+                    }
                 }
             }
             // #insight No annotations stripped.
@@ -3,7 +3,20 @@
 use std::{collections::HashMap, sync::Arc};
 
 use crate::{
-    eval::util::canonicalize_path, expr::Expr, module::Module, scope::Scope,
+    error::Error,
+    eval::{dispatch_cache::DispatchCache, util::canonicalize_path, TailCall},
+    expr::Expr,
+    foreign::{
+        register_context_foreign_func, register_foreign_func, register_scoped_foreign_func,
+        IntoContextForeignFunc, IntoForeignFunc,
+    },
+    library::setup_library,
+    module::{
+        loader::{FileSystemModuleLoader, ModuleLoader},
+        Module,
+    },
+    scope::Scope,
+    symbol::{Symbol, ToSymbol},
     util::standard_names::PROFILE,
 };
 
@@ -20,6 +33,16 @@ use crate::{
 
 const ROOT_PATH_ENV_VAR: &str = "TAN_ROOT";
 
+/// The execution engine of a context.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Engine {
+    /// The tree-walking evaluator.
+    #[default]
+    Eval,
+    /// The bytecode compiler and stack VM, see the `vm` module.
+    Vm,
+}
+
 // #insight the Clone is used for the http-server
 // #todo consider removing the Clone, it will give more flexibility.
 
@@ -32,6 +55,8 @@ pub struct Context {
     pub root_path: String,
     // #todo consider the name `module_map`
     pub module_registry: HashMap<String, Arc<Module>>,
+    /// Resolves and loads the modules imported with `use`.
+    pub module_loader: Arc<dyn ModuleLoader>,
     // #insight named just scope instead of static_scope, to match module.scope.
     /// The static scope.
     pub scope: Arc<Scope>,
@@ -40,6 +65,29 @@ pub struct Context {
     // #todo find better name, e.g. prelude_scope?
     // #todo what about `global_scope`? nah...
     pub top_scope: Arc<Scope>,
+    // #insight Useful for CLI tools, embedders should keep the default.
+    /// If true, a Tan panic aborts the host process, instead of unwinding to
+    /// the embedder as an error.
+    pub abort_on_panic: bool,
+    /// The engine used to execute the compiled expressions.
+    pub engine: Engine,
+    /// The per call-site cache of resolved methods.
+    pub dispatch_cache: DispatchCache,
+    // #insight The budget bounds runaway scripts, e.g. when evaluating
+    // untrusted code. Exhausting the budget is sticky, every further step
+    // fails, so the error cannot be swallowed by `try`. A step is a function
+    // invocation (Tan or foreign) or a loop iteration, both engines consume
+    // the same steps.
+    /// The maximum number of evaluation steps, unbounded if `None`.
+    pub step_budget: Option<u64>,
+    /// The number of evaluation steps consumed so far.
+    pub(crate) steps: u64,
+    // #insight Set just before evaluating an expression in tail position, and
+    // consumed (reset) by the evaluator, see `take_tail_position`.
+    pub(crate) is_tail_position: bool,
+    // #insight Set by a call in tail position, and consumed by the trampoline
+    // of the enclosing invocation, see `take_tail_call`.
+    pub(crate) tail_call: Option<TailCall>,
 }
 
 impl Default for Context {
@@ -50,24 +98,24 @@ impl Default for Context {
 
 impl Context {
     // #todo consider removing new and just use default?
+    /// Creates a context rooted at the `TAN_ROOT` env variable.
+    ///
+    /// Panics if `TAN_ROOT` is not set, use `ContextBuilder` for fallible
+    /// construction.
     pub fn new() -> Self {
-        // #todo move this somewhere else.
-        // #todo how to handle missing TAN_ROOT variable?
         // #todo expose as special tan variable? at least in 'dev' profile?
-        let root_path = std::env::var(ROOT_PATH_ENV_VAR)
-            .unwrap_or_else(|_| panic!("env variable `{ROOT_PATH_ENV_VAR}` should be set"));
-
-        let root_path = canonicalize_path(root_path);
-
-        let top_scope = Arc::new(Scope::default());
+        ContextBuilder::new()
+            .try_build()
+            .unwrap_or_else(|_| panic!("env variable `{ROOT_PATH_ENV_VAR}` should be set"))
+    }
 
-        Self {
-            root_path,
-            module_registry: HashMap::new(),
-            scope: top_scope.clone(),
-            dynamic_scope: Arc::new(Scope::default()),
-            top_scope: top_scope.clone(),
-        }
+    /// Creates a context that loads modules with the given loader. The
+    /// `TAN_ROOT` env variable is not required.
+    pub fn with_module_loader(module_loader: Arc<dyn ModuleLoader>) -> Self {
+        ContextBuilder::new()
+            .module_loader(module_loader)
+            .try_build()
+            .expect("context with module loader should not fail")
     }
 
     // #todo Use it for all prelude insertions.
@@ -108,7 +156,7 @@ impl Context {
     #[inline]
     pub fn insert(
         &self,
-        name: impl Into<String>,
+        name: impl Into<Symbol>,
         value: impl Into<Arc<Expr>>,
         is_dynamically_scoped: bool,
     ) -> Option<Arc<Expr>> {
@@ -120,8 +168,7 @@ impl Context {
     }
 
     #[inline]
-    pub fn get(&self, name: impl AsRef<str>, is_dynamically_scoped: bool) -> Option<Arc<Expr>> {
-        let name = name.as_ref();
+    pub fn get(&self, name: impl ToSymbol, is_dynamically_scoped: bool) -> Option<Arc<Expr>> {
         if is_dynamically_scoped {
             self.dynamic_scope.get(name)
         } else {
@@ -141,7 +188,268 @@ impl Context {
         false
     }
 
-    pub fn contains_name(&self, name: impl AsRef<str>) -> bool {
-        self.scope.contains_name_recursive(name.as_ref())
+    pub fn contains_name(&self, name: impl ToSymbol) -> bool {
+        self.scope.contains_name_recursive(name)
+    }
+
+    /// Registers a Rust function in the prelude, the method signature is
+    /// computed from the argument types, e.g. `add$$Int$$Int`.
+    pub fn register<Args>(
+        &self,
+        name: impl AsRef<str>,
+        func: impl IntoForeignFunc<Args> + 'static,
+    ) -> Option<Arc<Expr>> {
+        register_foreign_func(&self.top_scope, name, func)
+    }
+
+    /// Registers a Rust function, that may borrow from the environment, in the
+    /// prelude, for the duration of `body`. The function is revoked, and the
+    /// shadowed bindings are restored, when `body` returns.
+    pub fn scoped_register<'env, Args, R>(
+        &mut self,
+        name: impl AsRef<str>,
+        func: impl IntoForeignFunc<Args> + 'env,
+        body: impl FnOnce(&mut Context) -> R,
+    ) -> R {
+        // #insight The registration is dropped before 'env ends, also on unwind.
+        let _registration = register_scoped_foreign_func(&self.top_scope, name, func);
+        body(self)
+    }
+
+    /// Registers a Rust function that accepts the Context, as the first
+    /// argument, in the prelude.
+    pub fn register_with_context<Args>(
+        &self,
+        name: impl AsRef<str>,
+        func: impl IntoContextForeignFunc<Args>,
+    ) -> Option<Arc<Expr>> {
+        register_context_foreign_func(&self.top_scope, name, func)
+    }
+
+    /// Returns true if the expression about to be evaluated is in tail
+    /// position, resets the flag.
+    pub(crate) fn take_tail_position(&mut self) -> bool {
+        std::mem::take(&mut self.is_tail_position)
+    }
+
+    /// Returns the pending call in tail position, if any.
+    pub(crate) fn take_tail_call(&mut self) -> Option<TailCall> {
+        self.tail_call.take()
+    }
+
+    /// Returns the number of evaluation steps consumed so far, e.g. for
+    /// billing.
+    pub fn consumed_steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Resets the consumed steps, e.g. to reuse the context with a fresh
+    /// budget.
+    pub fn reset_consumed_steps(&mut self) {
+        self.steps = 0;
+    }
+
+    /// Fails with `BudgetExhausted` if the step budget is already exceeded,
+    /// e.g. before evaluating the next top-level expression.
+    pub(crate) fn check_budget(&self) -> Result<(), Error> {
+        match self.step_budget {
+            Some(budget) if self.steps > budget => Err(Error::budget_exhausted(budget, None)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Consumes an evaluation step, fails with `BudgetExhausted` if the step
+    /// budget is exceeded.
+    // #insight
+    // The literals have no range, the error is anchored to the enclosing
+    // ranged form while it propagates, e.g. the loop form, or the region of
+    // the current op in the VM.
+    #[inline]
+    pub(crate) fn consume_step(&mut self, expr: Option<&Expr>) -> Result<(), Error> {
+        self.steps += 1;
+        match self.step_budget {
+            Some(budget) if self.steps > budget => Err(Error::budget_exhausted(
+                budget,
+                expr.and_then(|expr| expr.range()),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+// #insight The builder allows embedders to create a Context without touching
+// process-global env variables.
+
+/// A builder for `Context`.
+#[derive(Debug, Default)]
+pub struct ContextBuilder {
+    root_path: Option<String>,
+    local_root_path: Option<Option<String>>,
+    module_loader: Option<Arc<dyn ModuleLoader>>,
+    prelude: Option<Arc<Scope>>,
+    profile: Option<String>,
+    abort_on_panic: bool,
+    engine: Option<Engine>,
+    dispatch_cache: Option<bool>,
+    step_budget: Option<u64>,
+}
+
+impl ContextBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the root path, if not set the `TAN_ROOT` env variable is used.
+    pub fn root_path(mut self, root_path: impl Into<String>) -> Self {
+        self.root_path = Some(root_path.into());
+        self
+    }
+
+    /// Overrides the local root path (default: `.local-tan-root`), pass
+    /// `None` to disable the local root.
+    pub fn local_root_path(mut self, local_root_path: Option<String>) -> Self {
+        self.local_root_path = Some(local_root_path);
+        self
+    }
+
+    /// Sets a custom module loader, the root path is ignored.
+    pub fn module_loader(mut self, module_loader: Arc<dyn ModuleLoader>) -> Self {
+        self.module_loader = Some(module_loader);
+        self
+    }
+
+    /// Sets the initial prelude, the bindings of the scope are imported into
+    /// the top scope.
+    pub fn prelude(mut self, prelude: Arc<Scope>) -> Self {
+        self.prelude = Some(prelude);
+        self
+    }
+
+    /// Sets the execution profile, e.g. `test`.
+    pub fn profile(mut self, profile: impl Into<String>) -> Self {
+        self.profile = Some(profile.into());
+        self
+    }
+
+    pub fn abort_on_panic(mut self, abort_on_panic: bool) -> Self {
+        self.abort_on_panic = abort_on_panic;
+        self
+    }
+
+    /// Sets the execution engine (default: `Engine::Eval`).
+    pub fn engine(mut self, engine: Engine) -> Self {
+        self.engine = Some(engine);
+        self
+    }
+
+    /// Enables the dispatch cache (default: true), disabling it is useful
+    /// for benchmarking.
+    pub fn dispatch_cache(mut self, is_enabled: bool) -> Self {
+        self.dispatch_cache = Some(is_enabled);
+        self
+    }
+
+    /// Sets the maximum number of evaluation steps (default: unbounded).
+    pub fn step_budget(mut self, step_budget: u64) -> Self {
+        self.step_budget = Some(step_budget);
+        self
+    }
+
+    pub fn try_build(self) -> Result<Context, Error> {
+        let (root_path, module_loader) = if let Some(module_loader) = self.module_loader {
+            (self.root_path.unwrap_or_default(), module_loader)
+        } else {
+            let root_path = if let Some(root_path) = self.root_path {
+                root_path
+            } else {
+                // #todo how to handle missing TAN_ROOT variable?
+                let Ok(root_path) = std::env::var(ROOT_PATH_ENV_VAR) else {
+                    return Err(Error::invalid_arguments(
+                        &format!(
+                            "missing root path, env variable `{ROOT_PATH_ENV_VAR}` is not set"
+                        ),
+                        None,
+                    ));
+                };
+                root_path
+            };
+
+            let root_path = canonicalize_path(root_path);
+
+            let mut module_loader = FileSystemModuleLoader::new(&root_path);
+            if let Some(local_root_path) = self.local_root_path {
+                module_loader = module_loader.with_local_root_path(local_root_path);
+            }
+
+            let module_loader: Arc<dyn ModuleLoader> = Arc::new(module_loader);
+
+            (root_path, module_loader)
+        };
+
+        let top_scope = Arc::new(Scope::default());
+
+        let context = Context {
+            root_path,
+            module_registry: HashMap::new(),
+            module_loader,
+            scope: top_scope.clone(),
+            dynamic_scope: Arc::new(Scope::default()),
+            top_scope: top_scope.clone(),
+            abort_on_panic: self.abort_on_panic,
+            engine: self.engine.unwrap_or_default(),
+            dispatch_cache: DispatchCache::new(self.dispatch_cache.unwrap_or(true)),
+            step_budget: self.step_budget,
+            steps: 0,
+            is_tail_position: false,
+            tail_call: None,
+        };
+
+        setup_library(&context.top_scope);
+
+        if let Some(prelude) = self.prelude {
+            context.import_scope_into_prelude(prelude);
+        }
+
+        if let Some(profile) = self.profile {
+            context.top_scope.insert(PROFILE, Expr::string(profile));
+        }
+
+        Ok(context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{api::eval_string, expr::Expr, scope::Scope};
+
+    use super::ContextBuilder;
+
+    #[test]
+    fn context_builder_uses_the_explicit_root_path() {
+        let context = ContextBuilder::new()
+            .root_path("/some/tan/root")
+            .profile("test")
+            .try_build()
+            .unwrap();
+
+        assert_eq!(context.root_path, "/some/tan/root");
+        assert!(context.is_test_profile());
+    }
+
+    #[test]
+    fn context_builder_imports_the_prelude() {
+        let prelude = Arc::new(Scope::default());
+        prelude.insert("answer", Expr::Int(42));
+
+        let mut context = ContextBuilder::new()
+            .root_path("/some/tan/root")
+            .prelude(prelude)
+            .try_build()
+            .unwrap();
+
+        let value = eval_string("answer", &mut context).unwrap();
+        assert_matches::assert_matches!(value.unpack(), Expr::Int(42));
     }
 }
@@ -0,0 +1,275 @@
+// Deserialization of Tan values to Rust values, via serde.
+
+// #insight
+// The deserializer accepts both evaluated values (e.g. Expr::Map) and the
+// 'normalized' parsed expressions (e.g. `(Map :key value)`), so that data
+// files can be loaded without evaluation.
+
+// #todo Support borrowed deserialization (zero-copy).
+// #todo Support Set.
+
+use std::{fmt, str::FromStr};
+
+use indexmap::IndexMap;
+
+use rust_decimal::Decimal;
+use serde::de::{
+    self,
+    value::{MapAccessDeserializer, MapDeserializer, SeqDeserializer},
+    DeserializeOwned, IntoDeserializer, Visitor,
+};
+
+use crate::{
+    api::parse_string,
+    error::Error,
+    expr::{format_value, Expr},
+    util::fmt::{format_duration, format_instant, format_ratio},
+};
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::general(&msg.to_string())
+    }
+}
+
+/// Deserializes a Rust value from Tan source text.
+pub fn from_str<T: DeserializeOwned>(input: impl AsRef<str>) -> Result<T, Vec<Error>> {
+    let expr = parse_string(input)?;
+    from_expr(expr).map_err(|error| vec![error])
+}
+
+/// Deserializes a Rust value from a Tan expression.
+pub fn from_expr<T: DeserializeOwned>(expr: Expr) -> Result<T, Error> {
+    let value = to_data_value(expr)?;
+    T::deserialize(Deserializer::new(value))
+}
+
+/// Converts a parsed data expression to a value, e.g. `(Map :key value)` is
+/// converted to an `Expr::Map`.
+fn to_data_value(expr: Expr) -> Result<Expr, Error> {
+    // #insight Annotations, e.g. ranges or `#Date`, are ignored.
+    let expr = expr.unpack_consuming();
+
+    match expr {
+        Expr::List(mut items) => {
+            if items.is_empty() {
+                return Ok(Expr::None);
+            }
+
+            let range = items[0].range();
+
+            let Some(head) = items[0].as_symbol().map(str::to_owned) else {
+                return Err(Error::invalid_arguments(
+                    &format!("unsupported data expression `{}`", Expr::List(items)),
+                    range,
+                ));
+            };
+
+            match head.as_str() {
+                "Array" => {
+                    let items = items
+                        .drain(1..)
+                        .map(to_data_value)
+                        .collect::<Result<Vec<_>, _>>()?;
+                    Ok(Expr::array(items))
+                }
+                "Map" => {
+                    if items.len() % 2 == 0 {
+                        return Err(Error::invalid_arguments(
+                            "malformed Map, missing value",
+                            range,
+                        ));
+                    }
+                    let mut map = IndexMap::new();
+                    let mut items = items.drain(1..);
+                    while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                        map.insert(to_data_value(key)?, to_data_value(value)?);
+                    }
+                    Ok(Expr::map(map))
+                }
+                "Char" => {
+                    let c = items.get(1).and_then(|s| s.as_string()).and_then(|s| {
+                        let mut chars = s.chars();
+                        let c = chars.next();
+                        if chars.next().is_some() {
+                            None
+                        } else {
+                            c
+                        }
+                    });
+                    let Some(c) = c else {
+                        return Err(Error::invalid_arguments("malformed Char", range));
+                    };
+                    Ok(Expr::Char(c))
+                }
+                "Dec" => {
+                    let n = items.get(1).map(format_value).unwrap_or_default();
+                    let Ok(n) = Decimal::from_str(&n) else {
+                        return Err(Error::invalid_arguments("malformed Dec", range));
+                    };
+                    Ok(Expr::Dec(n))
+                }
+                _ => Err(Error::invalid_arguments(
+                    &format!("unsupported data expression `{}`", Expr::List(items)),
+                    range,
+                )),
+            }
+        }
+        Expr::Array(items) => {
+            let items = items
+                .read()
+                .expect("poisoned lock")
+                .iter()
+                .cloned()
+                .map(to_data_value)
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Expr::array(items))
+        }
+        Expr::Map(map) => {
+            let map = map
+                .read()
+                .expect("poisoned lock")
+                .iter()
+                .map(|(k, v)| Ok((k.clone(), to_data_value(v.clone())?)))
+                .collect::<Result<IndexMap<_, _>, Error>>()?;
+            Ok(Expr::map(map))
+        }
+        expr => Ok(expr),
+    }
+}
+
+/// A serde deserializer for a Tan data value.
+pub struct Deserializer {
+    value: Expr,
+}
+
+impl Deserializer {
+    fn new(value: Expr) -> Self {
+        Self { value }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Deserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Expr::None => visitor.visit_unit(),
+            Expr::Bool(b) => visitor.visit_bool(b),
+            Expr::U8(n) => visitor.visit_u8(n),
+            Expr::Int(n) => visitor.visit_i64(n),
+            Expr::Float(n) => visitor.visit_f64(n),
+            // #insight Decimals are passed as strings to avoid loss of precision.
+            Expr::Dec(n) => visitor.visit_string(n.to_string()),
+            #[cfg(feature = "bigint")]
+            Expr::BigInt(n) => visitor.visit_string(n.to_string()),
+            Expr::Ratio(n) => visitor.visit_string(format_ratio(&n)),
+            // #insight Temporal values are passed as ISO 8601 strings.
+            Expr::Date(d) => visitor.visit_string(d.to_string()),
+            Expr::Instant(t) => visitor.visit_string(format_instant(&t)),
+            Expr::Duration(d) => visitor.visit_string(format_duration(&d)),
+            Expr::Char(c) => visitor.visit_char(c),
+            Expr::String(s) => visitor.visit_string(s),
+            Expr::KeySymbol(s) | Expr::Symbol(s) => visitor.visit_str(s.as_str()),
+            Expr::Array(items) => {
+                let items = items.read().expect("poisoned lock").clone();
+                let mut seq = SeqDeserializer::new(items.into_iter().map(Deserializer::new));
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Expr::Map(map) => {
+                let map = map.read().expect("poisoned lock").clone();
+                let mut map = MapDeserializer::new(
+                    map.into_iter()
+                        .map(|(k, v)| (Deserializer::new(k), Deserializer::new(v))),
+                );
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            Expr::Vector(items) => {
+                let mut seq = SeqDeserializer::new(items.into_iter().map(Deserializer::new));
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Expr::PersistentMap(map) => {
+                let mut map =
+                    MapDeserializer::new(map.iter().map(|(k, v)| {
+                        (Deserializer::new(k.clone()), Deserializer::new(v.clone()))
+                    }));
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            value => Err(Error::invalid_arguments(
+                &format!("unsupported data value `{value}`"),
+                value.range(),
+            )),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Expr::None => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // #insight Unit variants are encoded as KeySymbols or Strings, other
+    // variants as single-entry Maps, e.g. `{:Circle 1.0}`.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.value {
+            Expr::String(s) => visitor.visit_enum(s.into_deserializer()),
+            Expr::KeySymbol(s) | Expr::Symbol(s) => {
+                visitor.visit_enum(s.as_str().into_deserializer())
+            }
+            Expr::Map(map) => {
+                let map = map.read().expect("poisoned lock").clone();
+                if map.len() != 1 {
+                    return Err(Error::invalid_arguments(
+                        "an enum variant should be encoded as a Map with a single entry",
+                        None,
+                    ));
+                }
+                let map = MapDeserializer::new(
+                    map.into_iter()
+                        .map(|(k, v)| (Deserializer::new(k), Deserializer::new(v))),
+                );
+                visitor.visit_enum(MapAccessDeserializer::new(map))
+            }
+            value => Err(Error::invalid_arguments(
+                &format!("invalid enum value `{value}`"),
+                value.range(),
+            )),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
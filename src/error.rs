@@ -1,7 +1,12 @@
 use std::fmt;
 
+use indexmap::IndexMap;
+
 use crate::{
-    context::Context, eval::util::get_current_file_path, expr::Expr, range::Range,
+    context::Context,
+    eval::util::get_current_file_path,
+    expr::{annotate_type, format_value, range_to_expr, Expr},
+    range::Range,
     util::constants::INPUT_PSEUDO_FILE_PATH,
 };
 
@@ -57,8 +62,11 @@ pub enum ErrorVariant {
     UnexpectedEnd,
     MalformedInt,
     MalformedFloat,
+    MalformedRatio,
+    MalformedTemporal,
     MalformedEscapeCode,
     UnterminatedString,
+    UnterminatedChar,
     UnterminatedAnnotation,
 
     // Syntactic (parse) errors
@@ -73,6 +81,8 @@ pub enum ErrorVariant {
     UndefinedSymbol(String), // #todo maybe pass the whole Symbol expression?
     UndefinedFunction(String, String), // #todo maybe pass the whole Symbol expression?
     InvalidArguments,
+    /// A value could not be converted, (expected type, found type).
+    InvalidConversion(String, String),
     NotInvocable, // #todo maybe the non-invocable Annotated<Expr> should be the param?
     // #todo better name needed.
     // #todo is this a run-time error?
@@ -82,6 +92,7 @@ pub enum ErrorVariant {
     Io(std::io::Error),
     PoisonedLock,    // #todo find a better name!
     General(String), // #todo find a better name!
+    BudgetExhausted,
 
     // Panic
     Panic(String),
@@ -95,11 +106,12 @@ pub enum ErrorVariant {
     // #todo the Error is abused, maybe should use Exprs instead?
     // #todo consider using named return value(s) instead of the return keyword? (assignment is implicit return?)
     // #todo add custom reporting if used outside of a function (not catched in eval_func).
+    // #insight The values are boxed, to keep the size of Error small.
     // Returned by ..return
-    ReturnCF(Expr),
+    ReturnCF(Box<Expr>),
     // #todo add custom reporting if used outside of a loop (not catched in eval_for)
     // Signals a break statement in a loop.
-    BreakCF(Expr),
+    BreakCF(Box<Expr>),
     // #todo add custom reporting if used outside of a loop (not catched in eval_for)
     // Signals a continue statement in a loop.
     ContinueCF,
@@ -112,8 +124,11 @@ impl fmt::Display for ErrorVariant {
             ErrorVariant::UnexpectedEnd => "unexpected end of input".to_owned(),
             ErrorVariant::MalformedInt => "malformed integer number".to_owned(),
             ErrorVariant::MalformedFloat => "malformed float number".to_owned(),
+            ErrorVariant::MalformedRatio => "malformed ratio number".to_owned(),
+            ErrorVariant::MalformedTemporal => "malformed temporal value".to_owned(),
             ErrorVariant::MalformedEscapeCode => "malformed escape code".to_owned(),
             ErrorVariant::UnterminatedString => "unterminated string".to_owned(),
+            ErrorVariant::UnterminatedChar => "unterminated char".to_owned(),
             ErrorVariant::UnterminatedAnnotation => "unterminated annotation".to_owned(),
             ErrorVariant::InvalidQuote => "invalid quote".to_owned(),
             ErrorVariant::UnexpectedToken => "unexpected token".to_owned(),
@@ -128,9 +143,13 @@ impl fmt::Display for ErrorVariant {
             ErrorVariant::Io(io_err) => format!("i/o error: {io_err}"),
             ErrorVariant::FailedUse(url, _) => format!("failed use `{url}`"),
             ErrorVariant::InvalidArguments => "invalid arguments".to_owned(),
+            ErrorVariant::InvalidConversion(expected, found) => {
+                format!("invalid conversion, expected `{expected}`, found `{found}`")
+            }
             ErrorVariant::PoisonedLock => "poisoned lock".to_owned(),
             ErrorVariant::NotInvocable => "not invocable".to_owned(),
             ErrorVariant::General(text) => text.clone(),
+            ErrorVariant::BudgetExhausted => "step budget exhausted".to_owned(),
             ErrorVariant::Panic(_) => "panic".to_owned(),
             ErrorVariant::ReturnCF(_) => "return".to_owned(),
             ErrorVariant::ContinueCF => "continue".to_owned(),
@@ -149,6 +168,50 @@ impl ErrorVariant {
         // #todo implement me!
         0
     }
+
+    // #insight Used to expose the variant to Tan code, e.g. in (try ...)
+    /// Returns the name of the variant.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorVariant::UnexpectedEnd => "UnexpectedEnd",
+            ErrorVariant::MalformedInt => "MalformedInt",
+            ErrorVariant::MalformedFloat => "MalformedFloat",
+            ErrorVariant::MalformedRatio => "MalformedRatio",
+            ErrorVariant::MalformedTemporal => "MalformedTemporal",
+            ErrorVariant::MalformedEscapeCode => "MalformedEscapeCode",
+            ErrorVariant::UnterminatedString => "UnterminatedString",
+            ErrorVariant::UnterminatedChar => "UnterminatedChar",
+            ErrorVariant::UnterminatedAnnotation => "UnterminatedAnnotation",
+            ErrorVariant::InvalidQuote => "InvalidQuote",
+            ErrorVariant::UnexpectedToken => "UnexpectedToken",
+            ErrorVariant::UnterminatedList => "UnterminatedList",
+            ErrorVariant::MalformedAnnotation => "MalformedAnnotation",
+            ErrorVariant::MalformedRange => "MalformedRange",
+            ErrorVariant::MalformedStringTemplate => "MalformedStringTemplate",
+            ErrorVariant::UndefinedSymbol(..) => "UndefinedSymbol",
+            ErrorVariant::UndefinedFunction(..) => "UndefinedFunction",
+            ErrorVariant::InvalidArguments => "InvalidArguments",
+            ErrorVariant::InvalidConversion(..) => "InvalidConversion",
+            ErrorVariant::NotInvocable => "NotInvocable",
+            ErrorVariant::FailedUse(..) => "FailedUse",
+            ErrorVariant::Io(..) => "Io",
+            ErrorVariant::PoisonedLock => "PoisonedLock",
+            ErrorVariant::General(..) => "General",
+            ErrorVariant::BudgetExhausted => "BudgetExhausted",
+            ErrorVariant::Panic(..) => "Panic",
+            ErrorVariant::ReturnCF(..) => "ReturnCF",
+            ErrorVariant::BreakCF(..) => "BreakCF",
+            ErrorVariant::ContinueCF => "ContinueCF",
+        }
+    }
+
+    /// Returns true if the variant is a control-flow 'pseudo' error.
+    pub fn is_control_flow(&self) -> bool {
+        matches!(
+            self,
+            ErrorVariant::ReturnCF(..) | ErrorVariant::BreakCF(..) | ErrorVariant::ContinueCF
+        )
+    }
 }
 
 // #insight
@@ -183,6 +246,18 @@ impl ErrorNote {
     }
 }
 
+// #todo Consider keeping the callee range also.
+/// A frame of the Tan call-stack, captured while an error unwinds.
+#[derive(Debug, Clone)]
+pub struct ErrorFrame {
+    /// The name of the invoked function, `<anonymous>` if the function is not named.
+    pub name: String,
+    /// The file path of the call-site.
+    pub file_path: String,
+    /// The range of the call-site, within the source.
+    pub range: Option<Range>,
+}
+
 // #insight We keep the file url (instead of the module url) for more precise error reporting.
 
 // #todo keep source expression instead of file_path/range.
@@ -200,14 +275,42 @@ pub struct Error {
     /// at a second stage.
     pub file_path: String,
     pub notes: Vec<ErrorNote>,
+    // #insight
+    // The stack is boxed, most errors (e.g. the control-flow errors) have no
+    // frames, keep the size of Error (and every Result) small.
+    /// The Tan call-stack, innermost frame first.
+    stack: Option<Box<[ErrorFrame]>>,
 }
 
 impl std::error::Error for Error {}
 
+impl fmt::Display for ErrorFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(range) = &self.range {
+            write!(
+                f,
+                "{} ({}:{}:{})",
+                self.name,
+                self.file_path,
+                range.start.line + 1,
+                range.start.col + 1
+            )
+        } else {
+            write!(f, "{} ({})", self.name, self.file_path)
+        }
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         // #todo write more information!
-        write!(f, "{}", self.variant)
+        write!(f, "{}", self.variant)?;
+
+        for frame in self.stack() {
+            write!(f, "\n  at {frame}")?;
+        }
+
+        Ok(())
     }
 }
 
@@ -217,6 +320,7 @@ impl Error {
             variant,
             file_path: INPUT_PSEUDO_FILE_PATH.to_owned(),
             notes: Vec::new(),
+            stack: None,
         }
     }
 
@@ -226,6 +330,21 @@ impl Error {
         error
     }
 
+    /// Creates an error for a value that could not be converted to the
+    /// expected type.
+    pub fn invalid_conversion(expected: &str, value: &Expr) -> Self {
+        let found = format_value(value.type_of());
+        let mut error = Self::new(ErrorVariant::InvalidConversion(
+            expected.to_owned(),
+            found.clone(),
+        ));
+        error.push_note(
+            &format!("expected `{expected}`, found `{found}`"),
+            value.range(),
+        );
+        error
+    }
+
     pub fn poisoned_lock(note: &str, range: Option<Range>) -> Self {
         let mut error = Self::new(ErrorVariant::PoisonedLock);
         error.push_note(note, range);
@@ -258,6 +377,12 @@ impl Error {
         error
     }
 
+    pub fn budget_exhausted(budget: u64, range: Option<Range>) -> Self {
+        let mut error = Self::new(ErrorVariant::BudgetExhausted);
+        error.push_note(&format!("exceeded the budget of {budget} steps"), range);
+        error
+    }
+
     pub fn io(io_error: std::io::Error, note: &str, range: Option<Range>) -> Self {
         let mut error = Self::new(ErrorVariant::Io(io_error));
         error.push_note(note, range);
@@ -274,7 +399,7 @@ impl Error {
         let mut notes = Vec::new();
         for e in &source_errors {
             // #todo should also include source range!!
-            notes.push(ErrorNote::new(&e.to_string(), e.range().cloned()));
+            notes.push(ErrorNote::new(&e.variant.to_string(), e.range().cloned()));
         }
         let mut error = Self::new(ErrorVariant::FailedUse(url.to_owned(), source_errors));
         error.notes = notes;
@@ -288,11 +413,11 @@ impl Error {
     }
 
     pub fn return_cf(value: Expr) -> Self {
-        Self::new(ErrorVariant::ReturnCF(value))
+        Self::new(ErrorVariant::ReturnCF(Box::new(value)))
     }
 
     pub fn break_cf(value: Expr) -> Self {
-        Self::new(ErrorVariant::BreakCF(value))
+        Self::new(ErrorVariant::BreakCF(Box::new(value)))
     }
 
     pub fn continue_cf() -> Self {
@@ -312,6 +437,7 @@ impl Error {
             variant: crate::error::ErrorVariant::Panic(text.to_string()),
             file_path: get_current_file_path(context),
             notes: vec![],
+            stack: None,
         };
 
         error.push_note(text, None);
@@ -343,6 +469,27 @@ impl Error {
         self.notes.push(ErrorNote::new(note, range));
     }
 
+    /// Returns the Tan call-stack, innermost frame first.
+    pub fn stack(&self) -> &[ErrorFrame] {
+        self.stack.as_deref().unwrap_or_default()
+    }
+
+    pub fn push_frame(&mut self, name: &str, file_path: &str, range: Option<Range>) {
+        self.push_stack_frame(ErrorFrame {
+            name: name.to_owned(),
+            file_path: file_path.to_owned(),
+            range,
+        });
+    }
+
+    pub fn push_stack_frame(&mut self, frame: ErrorFrame) {
+        // #insight The frames are pushed only while the error unwinds, the
+        // reallocation is cheap compared to the unwinding.
+        let mut stack = self.stack.take().map(Vec::from).unwrap_or_default();
+        stack.push(frame);
+        self.stack = Some(stack.into_boxed_slice());
+    }
+
     pub fn range(&self) -> Option<&Range> {
         let note = self.notes.first();
         if let Some(note) = note {
@@ -354,6 +501,45 @@ impl Error {
     pub fn is_panic(&self) -> bool {
         matches!(self.variant, ErrorVariant::Panic(..))
     }
+
+    // #todo Consider a dedicated Expr variant for errors, Expr::Error is too weak.
+    // #insight The Map is annotated with the `Error` type, works with (when ...)
+    /// Converts the error to a first-class expression, used by (try ...).
+    pub fn to_expr(&self) -> Expr {
+        let notes: Vec<Expr> = self
+            .notes
+            .iter()
+            .map(|note| Expr::string(&note.text))
+            .collect();
+
+        let range = if let Some(range) = self.range() {
+            range_to_expr(range)
+        } else {
+            Expr::None
+        };
+
+        let stack: Vec<Expr> = self
+            .stack()
+            .iter()
+            .map(|frame| Expr::string(frame.to_string()))
+            .collect();
+
+        let mut map = IndexMap::new();
+        map.insert(
+            Expr::key_symbol("variant"),
+            Expr::string(self.variant.name()),
+        );
+        map.insert(
+            Expr::key_symbol("message"),
+            Expr::string(self.variant.to_string()),
+        );
+        map.insert(Expr::key_symbol("notes"), Expr::array(notes));
+        map.insert(Expr::key_symbol("file-path"), Expr::string(&self.file_path));
+        map.insert(Expr::key_symbol("range"), range);
+        map.insert(Expr::key_symbol("stack"), Expr::array(stack));
+
+        annotate_type(Expr::map(map), "Error")
+    }
 }
 
 impl From<std::io::Error> for Error {
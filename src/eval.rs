@@ -2,6 +2,7 @@
 
 // #todo Move these external eval functions into library, e.g. library/lang?
 
+pub mod dispatch_cache;
 mod eval_assertions;
 mod eval_assign;
 mod eval_cond;
@@ -18,6 +19,7 @@ mod eval_let_ds;
 mod eval_panic;
 mod eval_pipe;
 mod eval_scope_update;
+mod eval_try;
 mod eval_unless;
 mod eval_use;
 mod eval_when;
@@ -25,22 +27,31 @@ mod eval_while;
 pub mod iterator;
 pub mod util;
 
-use std::{collections::HashMap, sync::Arc};
+use std::{borrow::Cow, sync::Arc};
+
+use imbl::Vector;
+use indexmap::IndexMap;
 
 use eval_assertions::eval_assert_error;
 use eval_else::eval_else;
 use eval_is_defined::eval_is_defined;
 use eval_pipe::eval_pipe;
+use eval_try::{eval_catch, eval_try};
 use eval_unless::eval_unless;
 use eval_when::eval_when;
 
 use crate::{
     context::Context,
-    error::{Error, ErrorVariant},
-    expr::{expr_clone, format_value, Expr, ForeignFnRef},
+    error::{Error, ErrorFrame, ErrorVariant},
+    expr::{
+        check_key, expr_clone,
+        expr_persistent::{PersistentMap, PersistentSet},
+        Expr, ForeignFnRef,
+    },
     range::Range,
     resolver::resolve_op_method,
-    scope::Scope,
+    scope::{param_name, Scope},
+    symbol::Symbol,
     util::{
         is_dynamically_scoped, is_ellipsis, is_reserved_symbol,
         method::compute_signature_from_annotations, standard_names::CURRENT_FILE_PATH,
@@ -64,7 +75,7 @@ use self::{
     eval_scope_update::eval_scope_update,
     eval_use::eval_use,
     eval_while::eval_while,
-    util::{anchor_error, get_current_file_path},
+    util::{anchor_error, get_current_file_path, push_call_frame},
 };
 
 // #insight Not a pure evaluator, performs side-effects.
@@ -97,15 +108,17 @@ pub fn eval_args(args: &[Expr], context: &mut Context) -> Result<Vec<Expr>, Erro
 // #insight Used in *_destructure_bind functions.
 // #todo Add unit test.
 pub fn insert_symbol_binding(
-    sym: &str,
+    sym: impl Into<Symbol>,
     range: &Option<Range>,
     value: Expr,
     context: &mut Context,
 ) -> Result<(), Error> {
+    let sym = sym.into();
+
     // #insight reserved words are not polymorphic, so we can check before signature, rething about this.
     // #todo Also is_reserved_symbol is slow, optimize.
     // #todo Do we really want this? Maybe convert to a lint?
-    if is_reserved_symbol(sym) {
+    if is_reserved_symbol(&sym) {
         return Err(Error::invalid_arguments(
             &format!("cannot shadow the reserved symbol `{sym}`"),
             range.clone(),
@@ -144,6 +157,27 @@ pub fn insert_symbol_binding(
     Ok(())
 }
 
+/// Updates the binding of the name, in the scope that defines it. A local
+/// resolved to a bound frame slot is updated in place.
+pub fn update_binding(name: &Expr, value: Expr, context: &mut Context) {
+    let name = match name.unpack() {
+        Expr::LocalSymbol(sym, depth, index) => {
+            if context.scope.get_slot(*depth, *index).is_some() {
+                context.scope.set_slot(*depth, *index, sym, value);
+                return;
+            }
+            sym.as_str()
+        }
+        name => match name.as_stringable() {
+            Some(name) => name,
+            None => return,
+        },
+    };
+
+    // #todo should we check that the symbol actually exists?
+    context.scope.update(name, value);
+}
+
 // #todo find a better name.
 pub fn insert_binding(name: &Expr, value: Expr, context: &mut Context) -> Result<(), Error> {
     // #todo Consider special op/syntax for destructuring? e.g. ~[a b], `~` operator.
@@ -160,6 +194,14 @@ pub fn insert_binding(name: &Expr, value: Expr, context: &mut Context) -> Result
             // #todo Report error if sym == _ or ...
             insert_symbol_binding(sym, &name.range(), value, context)?;
         }
+        // #insight A `let` local resolved to a frame slot, see `resolve_locals`.
+        Expr::LocalSymbol(sym, depth, index) => {
+            let value = Arc::new(value);
+            if !context.scope.set_slot(*depth, *index, sym, value.clone()) {
+                let value = Arc::unwrap_or_clone(value);
+                insert_symbol_binding(sym, &name.range(), value, context)?;
+            }
+        }
         // #todo Try to find a destructure function!
         Expr::List(names) => {
             // list destructuring.
@@ -301,8 +343,6 @@ pub fn insert_binding(name: &Expr, value: Expr, context: &mut Context) -> Result
             let items = try_lock_read(items, name.range())?;
 
             for (key, name) in items.iter() {
-                // #todo could use as_stringable here.
-                // #todo could use the real expression here.
                 let Some(sym) = name.as_symbol() else {
                     return Err(Error::invalid_arguments(
                         "malformed destructuring bind, map pattern should contain symbols",
@@ -311,7 +351,17 @@ pub fn insert_binding(name: &Expr, value: Expr, context: &mut Context) -> Result
                 };
 
                 // (let {:name _ :age _} user)
-                let sym = if sym == "_" { key } else { sym };
+                let sym = if sym == "_" {
+                    let Some(key) = key.as_stringable() else {
+                        return Err(Error::invalid_arguments(
+                            "malformed destructuring bind, cannot infer the name from the key",
+                            name.range(),
+                        ));
+                    };
+                    key
+                } else {
+                    sym
+                };
 
                 if !values.contains_key(key) {
                     return Err(Error::invalid_arguments(
@@ -344,16 +394,8 @@ pub fn insert_binding(name: &Expr, value: Expr, context: &mut Context) -> Result
 pub fn invoke(invocable: &Expr, args: Vec<Expr>, context: &mut Context) -> Result<Expr, Error> {
     // #todo Support more invocable expressions, e.g. indexing!
     let result = match invocable.unpack() {
-        Expr::Func(..) => invoke_func(invocable, args, context),
-        Expr::ForeignFunc(fn_ref) => {
-            // #todo Consider having 3 ForeignFunc variants to avoid an extra check?
-            match fn_ref {
-                ForeignFnRef::NoContext(func) => func(&args),
-                ForeignFnRef::Context(func) => func(&args, context),
-                ForeignFnRef::MutContext(func) => func(&args, context),
-            }
-            // foreign_function(&args, context)
-        }
+        Expr::Func(..) => invoke_func_trampoline(invocable, args, context),
+        Expr::ForeignFunc(fn_ref) => return invoke_foreign_func(fn_ref, &args, context),
         _ => {
             // #todo return NonInvocable error!
             Err(Error::invalid_arguments(
@@ -363,12 +405,38 @@ pub fn invoke(invocable: &Expr, args: Vec<Expr>, context: &mut Context) -> Resul
         }
     };
 
+    check_panic(result, context)
+}
+
+/// Invokes the foreign function with the (evaluated) arguments, the
+/// arguments are borrowed.
+pub fn invoke_foreign_func(
+    fn_ref: &ForeignFnRef,
+    args: &[Expr],
+    context: &mut Context,
+) -> Result<Expr, Error> {
+    context.consume_step(None)?;
+
+    // #todo Consider having 3 ForeignFunc variants to avoid an extra check?
+    let result = match fn_ref {
+        ForeignFnRef::NoContext(func) => func(args),
+        ForeignFnRef::Context(func) => func(args, context),
+        ForeignFnRef::MutContext(func) => func(args, context),
+        ForeignFnRef::Shared(func) => func(args, context),
+    };
+
+    check_panic(result, context)
+}
+
+/// Aborts on a panic error, if requested, see `Context.abort_on_panic`.
+fn check_panic(result: Result<Expr, Error>, context: &Context) -> Result<Expr, Error> {
     match result {
         Err(ref error) => {
             if let ErrorVariant::Panic(msg) = &error.variant {
-                // #todo #hack this is a temp solution, maybe the Repl/Runner should install a custom panic handler?
-                // #todo maybe put a flag in Context to stop further evaluation and unwind the stack?
-                panic!("{}", msg);
+                // #insight By default the panic unwinds to the embedder as an error.
+                if context.abort_on_panic {
+                    panic!("{}", msg);
+                }
             }
             result
         }
@@ -377,65 +445,145 @@ pub fn invoke(invocable: &Expr, args: Vec<Expr>, context: &mut Context) -> Resul
 }
 
 // #todo rename to eval_func?
-// #todo use this function in eval, later.
 // #todo pass &[Expr] instead of Vec<Expr>
-// #todo rethink this and the non-inner function above.
+/// Invokes the function with the (evaluated) arguments, e.g. from a foreign
+/// higher-order function. If the invocation fails, a call-stack frame is
+/// pushed for the function, the call-site is not known.
 pub fn invoke_func(func: &Expr, args: Vec<Expr>, context: &mut Context) -> Result<Expr, Error> {
-    // #insight args are intentionally not evaluated!
+    let result = invoke_func_trampoline(func, args, context);
+    push_call_frame(result, func, func, context)
+}
 
-    let Expr::Func(params, body, func_scope, file_path) = func.unpack() else {
-        // #todo what to do here?
-        return Err(Error::invalid_arguments("should be a Func", func.range()));
-    };
+// #todo rethink this and the non-inner function above.
+/// Invokes the function, the caller pushes the call-stack frame of the
+/// call-site.
+fn invoke_func_trampoline(
+    func: &Expr,
+    args: Vec<Expr>,
+    context: &mut Context,
+) -> Result<Expr, Error> {
+    // #insight args are intentionally not evaluated!
 
     // #todo should set the current-module somehow?
 
-    // #todo ultra-hack to kill shared ref to `env`.
-    let params = params.clone();
-
     // #insight
     // actually we implement static (lexical) scoping here, as we base the new
     // scope on the lexical function scope.
 
     // #todo avoid the func_scope.clone()
     let prev_scope = context.scope.clone();
-    // #insight notice we use func_scope here!
-    // #insight #IMPORTANT make sure the scope is restored before all exit points of this function!!!
-    // #todo need a push_scope helper on context that uses Drop to emulate defer?
-    // #todo e.g. it could return a prev_scope ScopeGuard!
-    // #insight notice we use func_scope here!
-    // let prev_scope = std::mem::replace(&mut context.scope, func_scope.clone());
-    context.scope = Arc::new(Scope::new(func_scope.clone()));
-    // #todo consider args.into_iter();
 
+    // #insight
+    // Calls in tail position are not invoked recursively, they are recorded
+    // in the context (see `TailCall`) and invoked in this loop (trampoline),
+    // reusing the current native stack frame.
+
+    let mut func = func.clone();
+    let mut args = args;
+    // The call-site of the last tail call, and the function that made it.
+    let mut tail_site = None;
+
+    loop {
+        let result = invoke_func_body(&func, args, context);
+
+        if let Some(tail_call) = context.take_tail_call() {
+            if result.is_ok() {
+                let caller = std::mem::replace(&mut func, tail_call.func);
+                args = tail_call.args;
+                tail_site = Some((tail_call.name, tail_call.range, caller));
+                continue;
+            }
+        }
+
+        // #insight #IMPORTANT make sure the scope is restored before all exit points of this function!!!
+        context.scope = prev_scope;
+
+        return match (result, tail_site) {
+            (Err(mut error), Some((name, range, caller))) if !error.variant.is_control_flow() => {
+                // #insight The frame is created only if the error escapes.
+                let file_path = match caller.unpack() {
+                    Expr::Func(.., file_path) => file_path.clone(),
+                    _ => get_current_file_path(context),
+                };
+                error.push_stack_frame(TailCall::frame(name, range, file_path));
+                Err(error)
+            }
+            (result, _) => result,
+        };
+    }
+}
+
+// #insight
+// A dedicated record, instead of a control-flow 'error', avoids allocating an
+// Error on every iteration of the trampoline. The forms that pass the tail
+// position (do, if, cond, when, unless, ...) return the placeholder value of
+// the call unchanged.
+
+/// A call in tail position, deferred to the trampoline of the enclosing
+/// invocation.
+#[derive(Clone, Debug)]
+pub(crate) struct TailCall {
+    /// The invoked function.
+    pub func: Expr,
+    /// The evaluated arguments.
+    pub args: Vec<Expr>,
+    /// The name of the operator, if symbolic.
+    pub name: Option<Symbol>,
+    /// The range of the call-site.
+    pub range: Option<Range>,
+}
+
+impl TailCall {
+    /// Returns the call-stack frame of the call-site.
+    pub fn frame(name: Option<Symbol>, range: Option<Range>, file_path: String) -> ErrorFrame {
+        ErrorFrame {
+            name: name.map_or_else(|| "<anonymous>".to_owned(), String::from),
+            file_path,
+            range,
+        }
+    }
+}
+
+/// Binds the arguments to the function parameters, in a new scope nested in
+/// the function scope.
+pub fn bind_func_args(
+    params: &Arc<Vec<Expr>>,
+    func_scope: &Arc<Scope>,
+    file_path: &str,
+    args: Vec<Expr>,
+    context: &mut Context,
+) -> Result<(), Error> {
+    // #insight The invocation consumes a step, the error is anchored to the
+    // call-site while it propagates.
+    context.consume_step(None)?;
+
+    // #insight The arguments are bound to slots, by position. The `let`
+    // locals are bound to the following slots, see `resolve_locals`.
+    let mut slots = Vec::with_capacity(params.len());
     let mut args = args.into_iter();
 
-    for param in params {
-        let Some(param_name) = param.as_symbol() else {
+    for param in params.iter() {
+        // #insight The rest parameter is bound without the ellipsis.
+        let Some(name) = param_name(param) else {
             let mut error = Error::invalid_arguments("parameter is not a symbol", param.range());
             if !error.has_file_path() {
-                error.file_path.clone_from(file_path);
+                error.file_path = file_path.to_string();
             }
-            // #insight Restore the scope (and current_file_path) after creating the error.
-            context.scope = prev_scope;
             return Err(error);
         };
 
         // #todo consider other syntax, e.g. `&rest` like Clojure.
 
         // check for 'rest' parameter.
-        if is_ellipsis(param_name) {
+        if param.as_symbol().is_some_and(is_ellipsis) {
             let rest_args = Expr::array(args.collect::<Vec<Expr>>());
-            // remove the ellipsis prefix from the parameter name.
-            let param_name = &param_name[3..];
-            context.scope.insert(param_name, rest_args);
+            slots.push(Some((name, Arc::new(rest_args))));
             break;
         }
 
         // #todo consider making missing parameters an error!
         // #todo or maybe just a warning?
         // let Some(arg) = args.next() else {
-        // #todo correctly restore the scope!
         //     return Err(Error::invalid_arguments(
         //         &format!("no argument for parameter `{param}`"),
         //         param.range(),
@@ -443,21 +591,39 @@ pub fn invoke_func(func: &Expr, args: Vec<Expr>, context: &mut Context) -> Resul
         // };
 
         if let Some(arg) = args.next() {
-            context.scope.insert(param_name, arg);
+            slots.push(Some((name, Arc::new(arg))));
         } else {
             break;
         }
     }
 
+    // #insight notice we use func_scope here!
+    // #insight the scope is restored by the caller.
+    context.scope = Arc::new(Scope::with_slots(func_scope.clone(), slots));
+
+    Ok(())
+}
+
+/// Evaluates the body of the function, in a new scope. The last expression
+/// of the body is evaluated in tail position.
+fn invoke_func_body(func: &Expr, args: Vec<Expr>, context: &mut Context) -> Result<Expr, Error> {
+    let Expr::Func(params, body, func_scope, file_path) = func.unpack() else {
+        // #todo what to do here?
+        return Err(Error::invalid_arguments("should be a Func", func.range()));
+    };
+
+    bind_func_args(params, func_scope, file_path, args, context)?;
+
     // #todo this code is the same as in the (do ..) block, extract.
 
     // #todo do should be 'monadic', propagate Eff (effect) wrapper.
     let mut value = Expr::None;
 
-    for expr in body {
-        // #todo what happens on `return` statement! should exit this loop and not evaluate the rest!
+    for (i, expr) in body.iter().enumerate() {
         // #todo should inspect the error and add the file_path?
 
+        context.is_tail_position = i == body.len() - 1;
+
         match eval(expr, context) {
             Ok(v) => value = v,
             Err(mut error) => {
@@ -465,7 +631,7 @@ pub fn invoke_func(func: &Expr, args: Vec<Expr>, context: &mut Context) -> Resul
                     ErrorVariant::ReturnCF(v) => {
                         // A return 'statement' encountered, stop evaluating more
                         // expressions and return the value.
-                        value = v;
+                        value = *v;
                         break;
                     }
                     _ => {
@@ -476,7 +642,6 @@ pub fn invoke_func(func: &Expr, args: Vec<Expr>, context: &mut Context) -> Resul
                         if !error.has_file_path() {
                             error.file_path.clone_from(file_path);
                         }
-                        context.scope = prev_scope;
                         return Err(error);
                     }
                 }
@@ -484,8 +649,6 @@ pub fn invoke_func(func: &Expr, args: Vec<Expr>, context: &mut Context) -> Resul
         }
     }
 
-    // #todo what happens to this if an error is thrown??!!
-    context.scope = prev_scope;
     Ok(value)
 }
 
@@ -503,6 +666,27 @@ pub fn eval_symbol(expr: &Expr, context: &mut Context) -> Result<Expr, Error> {
         return Ok(expr.clone());
     }
 
+    lookup_symbol(symbol, expr, context)
+}
+
+/// Evaluates a parameter reference resolved to a frame slot, see
+/// `resolve_locals`.
+pub fn eval_local_symbol(expr: &Expr, context: &mut Context) -> Result<Expr, Error> {
+    let Expr::LocalSymbol(symbol, depth, index) = expr.unpack() else {
+        unreachable!()
+    };
+
+    if let Some(value) = context.scope.get_slot(*depth, *index) {
+        return Ok(expr_clone(&value));
+    }
+
+    // #insight
+    // The slot is not bound if the argument is missing, the symbol is looked
+    // up by name in the enclosing scopes, like before the resolution.
+    lookup_symbol(symbol, expr, context)
+}
+
+fn lookup_symbol(symbol: &Symbol, expr: &Expr, context: &mut Context) -> Result<Expr, Error> {
     // #todo #IMPORTANT now that we don't use the method annotation, we don't support Tan functions with types?
 
     // #todo handle 'PathSymbol'
@@ -564,16 +748,491 @@ pub fn eval_type(expr: &Expr) -> Result<Expr, Error> {
     Ok(expr.clone())
 }
 
+/// Returns true if the expression evaluates to itself, e.g. a number or a
+/// string literal. Used to skip the evaluation of constant persistent literals.
+pub(crate) fn is_constant(expr: &Expr) -> bool {
+    match expr.unpack() {
+        Expr::None
+        | Expr::Bool(_)
+        | Expr::U8(_)
+        | Expr::Int(_)
+        | Expr::Float(_)
+        | Expr::Dec(_)
+        | Expr::Ratio(_)
+        | Expr::KeySymbol(_)
+        | Expr::Char(_)
+        | Expr::String(_)
+        | Expr::Date(_)
+        | Expr::Instant(_)
+        | Expr::Duration(_) => true,
+        #[cfg(feature = "bigint")]
+        Expr::BigInt(_) => true,
+        Expr::Vector(items) => items.iter().all(is_constant),
+        Expr::PersistentMap(map) => map.values().all(is_constant),
+        Expr::PersistentSet(set) => set.iter().all(is_constant),
+        _ => false,
+    }
+}
+
+/// Resolves the invocable of the operator `op`, bound to `value` in the
+/// current scope. The arguments are already evaluated, they are used to
+/// resolve the method signature.
+pub fn resolve_invocable(
+    op: &Expr,
+    name: &Symbol,
+    value: &Arc<Expr>,
+    args: &[Expr],
+    context: &mut Context,
+) -> Result<Expr, Error> {
+    if !matches!(value.unpack(), Expr::Func(..) | Expr::ForeignFunc(..)) {
+        // #insight The lookup yields other invocables, e.g. Map, Array
+        // (Indexable), Type, etc.
+        return eval(op, context);
+    }
+
+    if let Some(head) = context.dispatch_cache.get(op, name, value, args, context) {
+        return Ok(head);
+    }
+
+    let head = match value.unpack() {
+        Expr::Func(params, ..) => {
+            // #todo Extract utility function to invoke a function.
+            // #todo Ultra-hack to kill shared ref to `env`.
+            let params = params.clone();
+
+            let prev_scope = context.scope.clone();
+            context.scope = Arc::new(Scope::new(prev_scope.clone()));
+
+            for (param, arg) in params.iter().zip(args.iter()) {
+                let Expr::Symbol(param) = param.unpack() else {
+                    return Err(Error::invalid_arguments(
+                        "parameter is not a symbol",
+                        param.range(),
+                    ));
+                };
+
+                context.scope.insert(param, arg.clone());
+            }
+
+            let head = resolve_op_method(op, name, args, context)?;
+
+            context.scope = prev_scope;
+
+            head
+        }
+        _ => resolve_op_method(op, name, args, context)?,
+    };
+
+    if let Some(signature) = context.dispatch_cache.signature(args, context) {
+        context
+            .dispatch_cache
+            .insert(op, name, value, signature, head.clone(), &context.scope);
+    }
+
+    Ok(head)
+}
+
+/// Evaluates the invocation of `head`, the resolved invocable of the operator
+/// `op`, at the call-site `expr`. The args are evaluated, unless the operator
+/// is a special form.
+pub fn eval_invocation(
+    expr: &Expr,
+    op: &Expr,
+    head: &Expr,
+    args: Cow<'_, [Expr]>,
+    is_tail: bool,
+    context: &mut Context,
+) -> Result<Expr, Error> {
+    // Evaluate the whole list expression with the resolved head/op.
+
+    // #todo Use op instead of head here.
+    // #todo Move special forms to prelude, as Expr::Macro or Expr::Special
+
+    match head.unpack() {
+        Expr::Func(..) => {
+            if is_tail {
+                // #insight The call is invoked by the enclosing trampoline,
+                // the returned value is a placeholder.
+                context.tail_call = Some(TailCall {
+                    func: head.clone(),
+                    args: args.into_owned(),
+                    name: op.as_symbolic(),
+                    range: expr.range().or_else(|| op.range()),
+                });
+                return Ok(Expr::None);
+            }
+            // #todo #fails library::html::tests::html_from_expr_usage
+            // #insight The args are already evaluated here!
+            // #todo call invoke_func directly?
+            let result = anchor_error(invoke(head, args.into_owned(), context), expr);
+            push_call_frame(result, op, expr, context)
+        }
+        Expr::ForeignFunc(fn_ref) => {
+            // #todo do NOT pre-evaluate args for ForeignFunc, allow to implement 'macros'.
+            // Foreign Functions do NOT change the environment, hmm...
+            // #todo use RefCell / interior mutability instead, to allow for changing the environment (with Mutation Effect)
+
+            // #insight The args are already evaluated here, and borrowed.
+            let result = anchor_error(invoke_foreign_func(fn_ref, &args, context), expr);
+            push_call_frame(result, op, expr, context)
+        }
+        // Treat array as invocable.
+        Expr::Array(arr) => {
+            // #todo What about dynamic type here?
+            // Evaluate the arguments before calling the function.
+            let args = eval_args(&args, context)?;
+
+            // #todo optimize this!
+            // #todo error checking, one arg, etc.
+            let index = &args[0];
+            // #todo we need UInt, USize, Nat type
+            let Some(index) = index.as_int() else {
+                return Err(Error::invalid_arguments(
+                    &format!(
+                        "invalid array index, expecting Int, found `{}` ({})",
+                        index,
+                        index.dyn_type(context)
+                    ),
+                    index.range(),
+                ));
+            };
+            let index = index as usize;
+
+            let arr = try_lock_read(arr, expr.range())?;
+
+            if let Some(value) = arr.get(index) {
+                // #todo replace the clone with the custom expr::copy/ref
+                Ok(value.clone())
+            } else {
+                // #todo introduce Maybe { Some, None }
+                Ok(Expr::None)
+            }
+        }
+        // Treat vector as invocable.
+        Expr::Vector(items) => {
+            let args = eval_args(&args, context)?;
+
+            let index = &args[0];
+            let Some(index) = index.as_int() else {
+                return Err(Error::invalid_arguments(
+                    &format!(
+                        "invalid vector index, expecting Int, found `{}` ({})",
+                        index,
+                        index.dyn_type(context)
+                    ),
+                    index.range(),
+                ));
+            };
+
+            // #todo introduce Maybe { Some, None }
+            Ok(items.get(index as usize).cloned().unwrap_or_default())
+        }
+        // Treat persistent map as invocable.
+        Expr::PersistentMap(map) => {
+            let args = eval_args(&args, context)?;
+
+            // #todo Introduce Maybe { Some, None }
+            Ok(map.get(args[0].unpack()).cloned().unwrap_or_default())
+        }
+        // Treat map as invocable.
+        Expr::Map(map) => {
+            // Evaluate the arguments before calling the function.
+            let args = eval_args(&args, context)?;
+
+            // #todo optimize this!
+            // #todo error checking, one arg, stringable, etc.
+
+            let key = args[0].unpack();
+
+            let map = try_lock_read(map, expr.range())?;
+
+            if let Some(value) = map.get(key) {
+                Ok(value.clone())
+            } else {
+                // #todo Introduce Maybe { Some, None }
+                Ok(Expr::None)
+            }
+        }
+        // Treat buffer as invocable.
+        Expr::Buffer(_length, buf) => {
+            // #todo What about dynamic type here?
+            // Evaluate the arguments before calling the function.
+            let args = eval_args(&args, context)?;
+
+            // #todo optimize this!
+            // #todo error checking, one arg, etc.
+            let index = &args[0];
+            // #todo we need UInt, USize, Nat type
+            let Some(index) = index.as_int() else {
+                return Err(Error::invalid_arguments(
+                    "invalid buffer index, expecting Int",
+                    index.range(),
+                ));
+            };
+            let index = index as usize;
+
+            let buf = try_lock_read(buf, expr.range())?;
+
+            if let Some(value) = buf.get(index) {
+                // #todo replace the clone with the custom expr::copy/ref
+                Ok(Expr::U8(*value))
+            } else {
+                // #todo introduce Maybe { Some, None }
+                Ok(Expr::None)
+            }
+        }
+        // #todo move all 'type-constructors' to external files.
+        Expr::Type(s) => match s.as_str() {
+            "List" => {
+                let args = eval_args(&args, context)?;
+                Ok(Expr::List(args))
+            }
+            "Trait" => {
+                // Example:
+                // (let Semigroup (Trait T
+                //   #(Func [T T] T) combine
+                // ))
+                //
+                // #insight Just accepts the syntax for the moment.
+                // #todo Implement me!
+                // #todo We need a Trait Expr?
+                Ok(Expr::None)
+            }
+            "Func" => {
+                let Some(params) = args.first() else {
+                    return Err(Error::invalid_arguments(
+                        "malformed func definition, missing function parameters",
+                        expr.range(),
+                    ));
+                };
+
+                let body = &args[1..];
+
+                // #todo move handling of Expr::None to as_list?
+
+                // #todo Remove the clones!
+                // #todo should check both for list and array (i.e. as_iterable)
+                let params = if let Some(params) = params.as_array() {
+                    params.clone()
+                } else if params.is_symbol() {
+                    // Also allow a single parameter without the array.
+                    // #todo For the moment we just convert it to an array here, we should leave it as is though.
+                    vec![params.clone()]
+                } else if params.is_none() {
+                    // #insight None == One == Unit
+                    Vec::new()
+                } else {
+                    println!("=== {params:?}");
+                    return Err(Error::invalid_arguments(
+                        "malformed func parameters definition",
+                        params.range(),
+                    ));
+                };
+
+                // #insight captures the static (lexical scope)
+
+                let func_file_path = get_current_file_path(context);
+                let func_scope = context.scope.clone();
+                func_scope.insert(CURRENT_FILE_PATH, Expr::string(&func_file_path));
+
+                // #todo optimize
+                Ok(Expr::Func(
+                    Arc::new(params),
+                    Arc::new(body.into()),
+                    func_scope,
+                    func_file_path, // #todo is this really needed here?
+                ))
+            }
+            // #todo lookup constructor function
+            _ => Err(Error::not_invocable(
+                &format!("not invocable constructor `{head}`, the type is `{s}`"),
+                head.range(),
+            )),
+        },
+        // #todo add handling of 'high-level', compound expressions here.
+        // #todo Expr::If
+        // #todo Expr::Let
+        // #todo Expr::Do
+        // #todo Expr::..
+        Expr::Symbol(s) => {
+            match s.as_str() {
+                "eval" => {
+                    // #todo also support eval-all/eval-many? (auto wrap with do?)
+                    let Some(expr) = args.first() else {
+                        return Err(Error::invalid_arguments(
+                            "missing expression to be evaluated",
+                            expr.range(),
+                        ));
+                    };
+
+                    // #todo consider naming this `form`?
+                    let expr = eval(expr, context)?;
+
+                    eval(&expr, context)
+                }
+                "return" => {
+                    let value = args.first().unwrap_or(&Expr::None);
+                    let value = eval(value, context)?;
+                    Err(Error::return_cf(value))
+                }
+                // #todo is there a way to avoid having continue in the language?
+                // #todo consider a different name?
+                // #todo consider continue without parentheses?
+                // #todo maybe should return some kind of Nothing/Never/Zero value?
+                "continue" => Err(Error::continue_cf()),
+                // #todo is there a way to avoid having break in the language?
+                // #todo consider break without parentheses?
+                // #todo maybe should return some kind of Nothing/Never/Zero value?
+                "break" => {
+                    let value = args.first().unwrap_or(&Expr::None);
+                    let value = eval(value, context)?;
+                    Err(Error::break_cf(value))
+                }
+                "quot" => {
+                    // #insight not obvious how to move to static/comptime phase.
+                    // #todo doesn't quote all exprs, e.g. the if expression.
+                    // #todo optimize with custom exprs, e.g Expr::Quot, Expr::QuasiQuot, etc.
+
+                    let Some(value) = args.first() else {
+                        return Err(Error::invalid_arguments(
+                            "missing quote target",
+                            expr.range(),
+                        ));
+                    };
+
+                    // #todo transform_mut is not the correct traversal, it's depth first it should be breadth first.
+                    // #todo expr.quote() is a temp hack.
+                    Ok(value.clone().quot(context))
+                }
+                // special term
+                // #todo the low-level handling of special forms should use the above high-level cases.
+                // #todo use the `optimize`/`raise` function, here to prepare high-level expression for evaluation, to avoid duplication.
+                "do" => {
+                    context.is_tail_position = is_tail;
+                    anchor_error(eval_do(&args, context), expr)
+                }
+                // #insight `head` seems to have range info, that `expr` lacks.
+                // #todo add range info to expr (no unpack) and use it instead!!!
+                "panic!" => anchor_error(eval_panic(&args, context), head),
+                "for" => anchor_error(eval_for(&args, context), expr),
+                // #todo consider the name `for*` or something similar?
+                "for->list" => anchor_error(eval_for_list(&args, context), expr),
+                "while" => anchor_error(eval_while(&args, context), expr),
+                "if" => {
+                    context.is_tail_position = is_tail;
+                    anchor_error(eval_if(&args, context), expr)
+                }
+                // #todo #temp Implement with macro.
+                "unless" => {
+                    context.is_tail_position = is_tail;
+                    anchor_error(eval_unless(&args, context), expr)
+                }
+                // #todo #fix else has no range here, wtf!
+                "else" => anchor_error(eval_else(&args, context), expr),
+                "cond" => {
+                    context.is_tail_position = is_tail;
+                    anchor_error(eval_cond(&args, context), expr)
+                }
+                "try" => anchor_error(eval_try(&args, context), expr),
+                "catch" => anchor_error(eval_catch(&args, context), expr),
+                "when" => {
+                    context.is_tail_position = is_tail;
+                    anchor_error(eval_when(&args, context), expr)
+                }
+                "|>" => anchor_error(eval_pipe(&args, context), expr),
+                // #todo #temp temporary solution.
+                "assert" => anchor_error(eval_assert(op, &args, context), expr),
+                "assert-eq" => anchor_error(eval_assert_eq(op, &args, context), expr),
+                "assert-error" => anchor_error(eval_assert_error(op, &args, context), expr),
+                "is-defined?" => anchor_error(eval_is_defined(&args, context), expr),
+                // #todo for-each or overload for?
+                "for-each" => anchor_error(eval_for_each(&args, context), expr),
+                "assign" => anchor_error(eval_assign(&args, context), expr),
+                // #insight operator alias for assign
+                "<-" => anchor_error(eval_assign(&args, context), expr),
+                // #todo, investigate, find a better name.
+                "scope-update" => anchor_error(eval_scope_update(&args, context), expr),
+                // #insight `op` seems to have range info, that `expr` lacks.
+                // #todo add range info to expr (no unpack) and use it instead!!!
+                "use" => anchor_error(eval_use(&args, context), expr),
+                "def" => anchor_error(eval_def(head, &args, context), expr),
+                "let-ds" => anchor_error(eval_let_ds(&args, context), expr),
+                "let" => anchor_error(eval_let(head, &args, context), expr),
+                "and" => {
+                    // #insight `and` _is_ short-circuiting and cannot be implemented with a function
+                    // #todo what about binary and?
+                    // #todo consider operator form? `&&` or `*`
+                    // #todo optimize case with 2 arguments.
+                    // #todo make a macro
+                    // #todo should these 'special forms' get added in scope/env?
+
+                    for arg in args.iter() {
+                        let value = eval(arg, context)?;
+                        let Some(predicate) = value.as_bool() else {
+                            return Err(Error::invalid_arguments(
+                                "`and` argument should be boolean",
+                                expr.range(),
+                            ));
+                        };
+
+                        if !predicate {
+                            return Ok(Expr::Bool(false));
+                        }
+                    }
+
+                    Ok(Expr::Bool(true))
+                }
+                "or" => {
+                    // #insight `or` is short-circuiting so it cannot be implemented as a function
+                    // #todo what about binary or?
+                    // #todo consider operator form? `||` or `+`
+                    // #todo make a macro.
+
+                    for arg in args.iter() {
+                        let value = eval(arg, context)?;
+                        let Some(predicate) = value.as_bool() else {
+                            return Err(Error::invalid_arguments(
+                                "`or` argument should be boolean",
+                                expr.range(),
+                            ));
+                        };
+
+                        if predicate {
+                            return Ok(Expr::Bool(true));
+                        }
+                    }
+
+                    Ok(Expr::Bool(false))
+                }
+                _ => Err(Error::not_invocable(
+                    &format!("symbol `{head}`"),
+                    head.range(),
+                )),
+            }
+        }
+        _ => Err(Error::not_invocable(
+            // #todo add a more descriptive error!
+            &format!("expression `{head}`"),
+            head.range(),
+        )),
+    }
+}
+
 // #todo needs better conversion to Expr::Annotated
 
 /// Evaluates via expression rewriting. The expression `expr` evaluates to
 /// a fixed point. In essence this is a 'tree-walk' interpreter.
 pub fn eval(expr: &Expr, context: &mut Context) -> Result<Expr, Error> {
+    // #insight The flag is reset here, only the tail expressions are
+    // evaluated in tail position.
+    let is_tail = context.take_tail_position();
+
     let result = match expr.unpack() {
         // #todo are you sure?
         // Expr::Annotated(..) => eval(expr.unpack(), env),
         // #todo should pass `symbol_expr` to eval_symbol.
         _symbol_expr @ Expr::Symbol(..) => eval_symbol(expr, context),
+        Expr::LocalSymbol(..) => eval_local_symbol(expr, context),
         Expr::KeySymbol(..) => eval_key_symbol(expr),
         Expr::Type(..) => eval_type(expr),
         // #todo if is unquotable!!
@@ -589,8 +1248,10 @@ pub fn eval(expr: &Expr, context: &mut Context) -> Result<Expr, Error> {
             };
 
             if predicate {
+                context.is_tail_position = is_tail;
                 eval(true_clause, context)
             } else if let Some(false_clause) = false_clause {
+                context.is_tail_position = is_tail;
                 eval(false_clause, context)
             } else {
                 // #todo what should we return if there is no false-clause? Zero/Never?
@@ -613,9 +1274,8 @@ pub fn eval(expr: &Expr, context: &mut Context) -> Result<Expr, Error> {
 
             // The unwrap here is safe.
             let op = list.first().unwrap();
-            // let args = &list[1..];
-            // #todo Try to avoid Vec::from()
-            let mut args = Vec::from(&list[1..]);
+            // #insight The args are cloned only when evaluated.
+            let mut args = Cow::Borrowed(&list[1..]);
 
             // #todo Could check special forms before the eval
 
@@ -624,55 +1284,16 @@ pub fn eval(expr: &Expr, context: &mut Context) -> Result<Expr, Error> {
             // Resolve and evaluate the head, try to find dynamic signature.
 
             let head = if let Some(name) = op.as_symbolic() {
-                if !is_reserved_symbol(name) {
+                if !is_reserved_symbol(&name) {
                     // #todo super nasty hack!!!!
 
                     // #insight The un-mangled operator name is still needed.
                     // #todo Add module.insert_op helper that automatically creates it!
                     // #todo we don't support dynamic scoping in this position, reconsider
                     if let Some(value) = context.scope.get(name) {
-                        match value.unpack() {
-                            Expr::Func(params, ..) => {
-                                // 'Cache' the evaluated args, to avoid double evaluation.
-                                args = eval_args(&args, context)?;
-
-                                // #todo Extract utility function to invoke a function.
-                                // #todo Ultra-hack to kill shared ref to `env`.
-                                let params = params.clone();
-
-                                let prev_scope = context.scope.clone();
-                                context.scope = Arc::new(Scope::new(prev_scope.clone()));
-
-                                for (param, arg) in params.iter().zip(&args) {
-                                    let Some(param) = param.as_symbol() else {
-                                        return Err(Error::invalid_arguments(
-                                            "parameter is not a symbol",
-                                            param.range(),
-                                        ));
-                                    };
-
-                                    context.scope.insert(param, arg.clone());
-                                }
-
-                                // #todo Optimize the resolve_op_method.
-                                let head = resolve_op_method(op, name, &args, context)?;
-
-                                context.scope = prev_scope;
-
-                                head
-                            }
-                            Expr::ForeignFunc(_) => {
-                                args = eval_args(&args, context)?;
-                                // #todo Optimize the resolve_op_method.
-                                resolve_op_method(op, name, &args, context)?
-                            }
-                            _ => {
-                                // #insight The lookup yields other invocables,
-                                // e.g. Map, Array (Indexable), Type, etc.
-                                args = eval_args(&args, context)?;
-                                eval(op, context)?
-                            }
-                        }
+                        // 'Cache' the evaluated args, to avoid double evaluation.
+                        args = Cow::Owned(eval_args(&args, context)?);
+                        resolve_invocable(op, &name, &value, &args, context)?
                     } else {
                         // #todo What is this case?
                         // #insight No need to eval_args here!
@@ -688,325 +1309,7 @@ pub fn eval(expr: &Expr, context: &mut Context) -> Result<Expr, Error> {
                 eval(op, context)?
             };
 
-            // Evaluate the whole list expression with the resolved head/op.
-
-            // #todo Use op instead of head here.
-            // #todo Move special forms to prelude, as Expr::Macro or Expr::Special
-
-            match head.unpack() {
-                Expr::Func(..) => {
-                    // #todo #fails library::html::tests::html_from_expr_usage
-                    // #insight The args are already evaluated here!
-                    // #todo call invoke_func directly?
-                    anchor_error(invoke(&head, args, context), expr)
-                }
-                Expr::ForeignFunc(..) => {
-                    // #todo do NOT pre-evaluate args for ForeignFunc, allow to implement 'macros'.
-                    // Foreign Functions do NOT change the environment, hmm...
-                    // #todo use RefCell / interior mutability instead, to allow for changing the environment (with Mutation Effect)
-
-                    // #todo call directly?
-                    // #insight The args are already evaluated here!
-                    anchor_error(invoke(&head, args, context), expr)
-                }
-                // Treat array as invocable.
-                Expr::Array(arr) => {
-                    // #todo What about dynamic type here?
-                    // Evaluate the arguments before calling the function.
-                    let args = eval_args(&args, context)?;
-
-                    // #todo optimize this!
-                    // #todo error checking, one arg, etc.
-                    let index = &args[0];
-                    // #todo we need UInt, USize, Nat type
-                    let Some(index) = index.as_int() else {
-                        return Err(Error::invalid_arguments(
-                            &format!(
-                                "invalid array index, expecting Int, found `{}` ({})",
-                                index,
-                                index.dyn_type(context)
-                            ),
-                            index.range(),
-                        ));
-                    };
-                    let index = index as usize;
-
-                    let arr = try_lock_read(arr, expr.range())?;
-
-                    if let Some(value) = arr.get(index) {
-                        // #todo replace the clone with the custom expr::copy/ref
-                        Ok(value.clone())
-                    } else {
-                        // #todo introduce Maybe { Some, None }
-                        Ok(Expr::None)
-                    }
-                }
-                // Treat map as invocable.
-                Expr::Map(map) => {
-                    // Evaluate the arguments before calling the function.
-                    let args = eval_args(&args, context)?;
-
-                    // #todo optimize this!
-                    // #todo error checking, one arg, stringable, etc.
-
-                    // #insight no need to unpack, format_value sees-through.
-                    let key = format_value(&args[0]);
-
-                    let map = try_lock_read(map, expr.range())?;
-
-                    if let Some(value) = map.get(&key) {
-                        Ok(value.clone())
-                    } else {
-                        // #todo Introduce Maybe { Some, None }
-                        Ok(Expr::None)
-                    }
-                }
-                // Treat buffer as invocable.
-                Expr::Buffer(_length, buf) => {
-                    // #todo What about dynamic type here?
-                    // Evaluate the arguments before calling the function.
-                    let args = eval_args(&args, context)?;
-
-                    // #todo optimize this!
-                    // #todo error checking, one arg, etc.
-                    let index = &args[0];
-                    // #todo we need UInt, USize, Nat type
-                    let Some(index) = index.as_int() else {
-                        return Err(Error::invalid_arguments(
-                            "invalid buffer index, expecting Int",
-                            index.range(),
-                        ));
-                    };
-                    let index = index as usize;
-
-                    let buf = try_lock_read(buf, expr.range())?;
-
-                    if let Some(value) = buf.get(index) {
-                        // #todo replace the clone with the custom expr::copy/ref
-                        Ok(Expr::U8(*value))
-                    } else {
-                        // #todo introduce Maybe { Some, None }
-                        Ok(Expr::None)
-                    }
-                }
-                // #todo move all 'type-constructors' to external files.
-                Expr::Type(s) => match s.as_str() {
-                    "List" => {
-                        let args = eval_args(&args, context)?;
-                        Ok(Expr::List(args))
-                    }
-                    "Trait" => {
-                        // Example:
-                        // (let Semigroup (Trait T
-                        //   #(Func [T T] T) combine
-                        // ))
-                        //
-                        // #insight Just accepts the syntax for the moment.
-                        // #todo Implement me!
-                        // #todo We need a Trait Expr?
-                        Ok(Expr::None)
-                    }
-                    "Func" => {
-                        let Some(params) = args.first() else {
-                            return Err(Error::invalid_arguments(
-                                "malformed func definition, missing function parameters",
-                                expr.range(),
-                            ));
-                        };
-
-                        let body = &args[1..];
-
-                        // #todo move handling of Expr::None to as_list?
-
-                        // #todo Remove the clones!
-                        // #todo should check both for list and array (i.e. as_iterable)
-                        let params = if let Some(params) = params.as_array() {
-                            params.clone()
-                        } else if params.is_symbol() {
-                            // Also allow a single parameter without the array.
-                            // #todo For the moment we just convert it to an array here, we should leave it as is though.
-                            vec![params.clone()]
-                        } else if params.is_none() {
-                            // #insight None == One == Unit
-                            Vec::new()
-                        } else {
-                            println!("=== {params:?}");
-                            return Err(Error::invalid_arguments(
-                                "malformed func parameters definition",
-                                params.range(),
-                            ));
-                        };
-
-                        // #insight captures the static (lexical scope)
-
-                        let func_file_path = get_current_file_path(context);
-                        let func_scope = context.scope.clone();
-                        func_scope.insert(CURRENT_FILE_PATH, Expr::string(&func_file_path));
-
-                        // #todo optimize
-                        Ok(Expr::Func(
-                            params,
-                            body.into(),
-                            func_scope,
-                            func_file_path, // #todo is this really needed here?
-                        ))
-                    }
-                    // #todo lookup constructor function
-                    _ => Err(Error::not_invocable(
-                        &format!("not invocable constructor `{head}`, the type is `{s}`"),
-                        head.range(),
-                    )),
-                },
-                // #todo add handling of 'high-level', compound expressions here.
-                // #todo Expr::If
-                // #todo Expr::Let
-                // #todo Expr::Do
-                // #todo Expr::..
-                Expr::Symbol(s) => {
-                    match s.as_str() {
-                        "eval" => {
-                            // #todo also support eval-all/eval-many? (auto wrap with do?)
-                            let Some(expr) = args.first() else {
-                                return Err(Error::invalid_arguments(
-                                    "missing expression to be evaluated",
-                                    expr.range(),
-                                ));
-                            };
-
-                            // #todo consider naming this `form`?
-                            let expr = eval(expr, context)?;
-
-                            eval(&expr, context)
-                        }
-                        "return" => {
-                            let value = args.first().unwrap_or(&Expr::None);
-                            let value = eval(value, context)?;
-                            Err(Error::return_cf(value))
-                        }
-                        // #todo is there a way to avoid having continue in the language?
-                        // #todo consider a different name?
-                        // #todo consider continue without parentheses?
-                        // #todo maybe should return some kind of Nothing/Never/Zero value?
-                        "continue" => Err(Error::continue_cf()),
-                        // #todo is there a way to avoid having break in the language?
-                        // #todo consider break without parentheses?
-                        // #todo maybe should return some kind of Nothing/Never/Zero value?
-                        "break" => {
-                            let value = args.first().unwrap_or(&Expr::None);
-                            let value = eval(value, context)?;
-                            Err(Error::break_cf(value))
-                        }
-                        "quot" => {
-                            // #insight not obvious how to move to static/comptime phase.
-                            // #todo doesn't quote all exprs, e.g. the if expression.
-                            // #todo optimize with custom exprs, e.g Expr::Quot, Expr::QuasiQuot, etc.
-
-                            let Some(value) = args.first() else {
-                                return Err(Error::invalid_arguments(
-                                    "missing quote target",
-                                    expr.range(),
-                                ));
-                            };
-
-                            // #todo transform_mut is not the correct traversal, it's depth first it should be breadth first.
-                            // #todo expr.quote() is a temp hack.
-                            Ok(value.clone().quot(context))
-                        }
-                        // special term
-                        // #todo the low-level handling of special forms should use the above high-level cases.
-                        // #todo use the `optimize`/`raise` function, here to prepare high-level expression for evaluation, to avoid duplication.
-                        "do" => anchor_error(eval_do(&args, context), expr),
-                        // #insight `head` seems to have range info, that `expr` lacks.
-                        // #todo add range info to expr (no unpack) and use it instead!!!
-                        "panic!" => anchor_error(eval_panic(&args, context), &head),
-                        "for" => anchor_error(eval_for(&args, context), expr),
-                        // #todo consider the name `for*` or something similar?
-                        "for->list" => anchor_error(eval_for_list(&args, context), expr),
-                        "while" => anchor_error(eval_while(&args, context), expr),
-                        "if" => anchor_error(eval_if(&args, context), expr),
-                        // #todo #temp Implement with macro.
-                        "unless" => anchor_error(eval_unless(&args, context), expr),
-                        // #todo #fix else has no range here, wtf!
-                        "else" => anchor_error(eval_else(&args, context), expr),
-                        "cond" => anchor_error(eval_cond(&args, context), expr),
-                        "when" => anchor_error(eval_when(&args, context), expr),
-                        "|>" => anchor_error(eval_pipe(&args, context), expr),
-                        // #todo #temp temporary solution.
-                        "assert" => anchor_error(eval_assert(op, &args, context), expr),
-                        "assert-eq" => anchor_error(eval_assert_eq(op, &args, context), expr),
-                        "assert-error" => anchor_error(eval_assert_error(op, &args, context), expr),
-                        "is-defined?" => anchor_error(eval_is_defined(&args, context), expr),
-                        // #todo for-each or overload for?
-                        "for-each" => anchor_error(eval_for_each(&args, context), expr),
-                        "assign" => anchor_error(eval_assign(&args, context), expr),
-                        // #insight operator alias for assign
-                        "<-" => anchor_error(eval_assign(&args, context), expr),
-                        // #todo, investigate, find a better name.
-                        "scope-update" => anchor_error(eval_scope_update(&args, context), expr),
-                        // #insight `op` seems to have range info, that `expr` lacks.
-                        // #todo add range info to expr (no unpack) and use it instead!!!
-                        "use" => anchor_error(eval_use(&args, context), expr),
-                        "def" => anchor_error(eval_def(&head, &args, context), expr),
-                        "let-ds" => anchor_error(eval_let_ds(&args, context), expr),
-                        "let" => anchor_error(eval_let(&head, &args, context), expr),
-                        "and" => {
-                            // #insight `and` _is_ short-circuiting and cannot be implemented with a function
-                            // #todo what about binary and?
-                            // #todo consider operator form? `&&` or `*`
-                            // #todo optimize case with 2 arguments.
-                            // #todo make a macro
-                            // #todo should these 'special forms' get added in scope/env?
-
-                            for arg in args {
-                                let value = eval(&arg, context)?;
-                                let Some(predicate) = value.as_bool() else {
-                                    return Err(Error::invalid_arguments(
-                                        "`and` argument should be boolean",
-                                        expr.range(),
-                                    ));
-                                };
-
-                                if !predicate {
-                                    return Ok(Expr::Bool(false));
-                                }
-                            }
-
-                            Ok(Expr::Bool(true))
-                        }
-                        "or" => {
-                            // #insight `or` is short-circuiting so it cannot be implemented as a function
-                            // #todo what about binary or?
-                            // #todo consider operator form? `||` or `+`
-                            // #todo make a macro.
-
-                            for arg in args {
-                                let value = eval(&arg, context)?;
-                                let Some(predicate) = value.as_bool() else {
-                                    return Err(Error::invalid_arguments(
-                                        "`or` argument should be boolean",
-                                        expr.range(),
-                                    ));
-                                };
-
-                                if predicate {
-                                    return Ok(Expr::Bool(true));
-                                }
-                            }
-
-                            Ok(Expr::Bool(false))
-                        }
-                        _ => Err(Error::not_invocable(
-                            &format!("symbol `{head}`"),
-                            head.range(),
-                        )),
-                    }
-                }
-                _ => Err(Error::not_invocable(
-                    // #todo add a more descriptive error!
-                    &format!("expression `{head}`"),
-                    head.range(),
-                )),
-            }
+            eval_invocation(expr, op, &head, args, is_tail, context)
         }
         Expr::Array(items) => {
             // #insight [...] => (Array ...) => it's like a function.
@@ -1023,14 +1326,55 @@ pub fn eval(expr: &Expr, context: &mut Context) -> Result<Expr, Error> {
             // #insight [...] => (Map ...) => it's like a function.
             // #todo nasty code, improve.
             // #todo can this get pre-evaluated statically in some cases?
-            let mut evaled_map = HashMap::new();
+            let mut evaled_map = IndexMap::new();
             // #todo use pairs or items instead of map?
             let map = try_lock_read(map, expr.range())?;
             for (k, v) in map.iter() {
+                check_key(k, expr.range())?;
                 evaled_map.insert(k.clone(), eval(v, context)?);
             }
             Ok(Expr::map(evaled_map))
         }
+        Expr::Vector(items) => {
+            // #insight Constant literals are returned as-is, the clone is O(1).
+            if items.iter().all(is_constant) {
+                return Ok(expr.unpack().clone());
+            }
+            let mut evaled_items = Vector::new();
+            for item in items.iter() {
+                evaled_items.push_back(eval(item, context)?);
+            }
+            Ok(Expr::Vector(evaled_items))
+        }
+        Expr::PersistentMap(map) => {
+            // #insight evaluates the values, like Map.
+            for k in map.keys() {
+                check_key(k, expr.range())?;
+            }
+            if map.values().all(is_constant) {
+                return Ok(expr.unpack().clone());
+            }
+            let mut evaled_map = PersistentMap::new();
+            for (k, v) in map.iter() {
+                evaled_map.insert(k.clone(), eval(v, context)?);
+            }
+            Ok(Expr::PersistentMap(evaled_map))
+        }
+        Expr::PersistentSet(set) => {
+            for item in set.iter() {
+                check_key(item, expr.range())?;
+            }
+            if set.iter().all(is_constant) {
+                return Ok(expr.unpack().clone());
+            }
+            let mut evaled_set = PersistentSet::new();
+            for item in set.iter() {
+                let item = eval(item, context)?;
+                check_key(&item, expr.range())?;
+                evaled_set.insert(item);
+            }
+            Ok(Expr::PersistentSet(evaled_set))
+        }
         _ => {
             // #todo hm, maybe need to report an error here? or even select the desired behavior? -> NO ERROR
             // #todo can we avoid the clone?
@@ -0,0 +1,264 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Weak},
+};
+
+use crate::{context::Context, expr::Expr, scope::Scope, symbol::Symbol};
+
+// #insight
+// The dispatch cache is an inline (per call-site) cache of the method
+// resolution, see `resolve_invocable`. A call-site remembers the method
+// resolved for the last signature (the types of the arguments), the lookup
+// of the mangled names is skipped while the types don't change.
+
+// #insight
+// A cache entry is valid while:
+// - the operator is bound to the same callee, a rebinding of the name yields
+//   a new value
+// - the scopes that bind methods (mangled names), visible from the call-site,
+//   are the same, and no method binding is inserted, updated or removed in
+//   them, this is tracked by a per-scope version
+// The call-site is identified by the address of the operator expression, the
+// callee is retained by the entry, so the address cannot be reused by another
+// callee.
+
+// #insight
+// The same call-site is reached from different scopes, e.g. the invocations of
+// a function, or functions that share the body but capture different scopes.
+// The scopes without method bindings are skipped, so the fresh invocation
+// scopes don't defeat the cache.
+
+// #todo Support polymorphic call-sites, keep more than one signature per entry.
+// #todo Evict the entries of dropped call-sites, instead of clearing.
+
+/// The maximum number of cached call-sites, the cache is cleared when full.
+const CAPACITY: usize = 4096;
+
+#[derive(Clone, Debug)]
+struct Entry {
+    name: Symbol,
+    callee: Arc<Expr>,
+    signature: Box<[Symbol]>,
+    /// The scopes that bind methods, with the versions of the bindings.
+    // #insight The Weak reference keeps the address from being reused.
+    method_scopes: Box<[(Weak<Scope>, u64)]>,
+    head: Expr,
+}
+
+/// A per call-site cache of resolved methods.
+#[derive(Clone, Debug)]
+pub struct DispatchCache {
+    is_enabled: bool,
+    entries: HashMap<usize, Entry>,
+}
+
+impl Default for DispatchCache {
+    fn default() -> Self {
+        Self::new(true)
+    }
+}
+
+impl DispatchCache {
+    pub fn new(is_enabled: bool) -> Self {
+        Self {
+            is_enabled,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Returns the number of cached call-sites.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the cached method for the call-site `op`.
+    pub fn get(
+        &self,
+        op: &Expr,
+        name: &Symbol,
+        callee: &Arc<Expr>,
+        args: &[Expr],
+        context: &Context,
+    ) -> Option<Expr> {
+        if !self.is_enabled {
+            return None;
+        }
+
+        let entry = self.entries.get(&call_site(op))?;
+
+        if entry.name != *name
+            || !Arc::ptr_eq(&entry.callee, callee)
+            || entry.signature.len() != args.len()
+        {
+            return None;
+        }
+
+        let mut scopes = method_scopes(&context.scope);
+        for (entry_scope, version) in entry.method_scopes.iter() {
+            let scope = scopes.next()?;
+            if !std::ptr::eq(entry_scope.as_ptr(), Arc::as_ptr(scope))
+                || *version != scope.method_version()
+            {
+                return None;
+            }
+        }
+        if scopes.next().is_some() {
+            return None;
+        }
+
+        for (arg, typ) in args.iter().zip(entry.signature.iter()) {
+            if arg_type(arg, context)? != *typ {
+                return None;
+            }
+        }
+
+        Some(entry.head.clone())
+    }
+
+    /// Returns the signature used to cache the method, `None` if the
+    /// invocation cannot be cached.
+    pub fn signature(&self, args: &[Expr], context: &Context) -> Option<Box<[Symbol]>> {
+        if !self.is_enabled {
+            return None;
+        }
+
+        args.iter().map(|arg| arg_type(arg, context)).collect()
+    }
+
+    /// Caches the method resolved for the call-site `op`, in the given scope.
+    pub fn insert(
+        &mut self,
+        op: &Expr,
+        name: &Symbol,
+        callee: &Arc<Expr>,
+        signature: Box<[Symbol]>,
+        head: Expr,
+        scope: &Arc<Scope>,
+    ) {
+        if self.entries.len() >= CAPACITY {
+            self.entries.clear();
+        }
+
+        self.entries.insert(
+            call_site(op),
+            Entry {
+                name: *name,
+                callee: callee.clone(),
+                signature,
+                method_scopes: method_scopes(scope)
+                    .map(|scope| (Arc::downgrade(scope), scope.method_version()))
+                    .collect(),
+                head,
+            },
+        );
+    }
+}
+
+fn call_site(op: &Expr) -> usize {
+    op as *const Expr as usize
+}
+
+/// Returns the scopes that bind methods, from the innermost scope.
+fn method_scopes(scope: &Arc<Scope>) -> impl Iterator<Item = &Arc<Scope>> {
+    std::iter::successors(Some(scope), |scope| scope.parent.as_ref())
+        .filter(|scope| scope.method_version() > 0)
+}
+
+/// Returns the dynamic type of the argument, `None` if the type depends on the
+/// scope, e.g. for Symbols.
+fn arg_type(arg: &Expr, context: &Context) -> Option<Symbol> {
+    if matches!(arg.unpack(), Expr::Symbol(..)) && arg.annotation("type").is_none() {
+        return None;
+    }
+
+    match arg.dyn_type(context).unpack() {
+        Expr::Type(typ) => Some(*typ),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        api::eval_string,
+        context::{Context, ContextBuilder},
+        expr::Expr,
+        scope::Scope,
+    };
+
+    fn test_context() -> Context {
+        ContextBuilder::new()
+            .root_path("/some/tan/root")
+            .try_build()
+            .unwrap()
+    }
+
+    #[test]
+    fn dispatch_cache_is_invalidated_on_redefinition() {
+        let mut context = test_context();
+        context.register("g", |a: i64| a + 1);
+
+        let input = "(let f (Func x (g x)))";
+        eval_string(input, &mut context).unwrap();
+
+        let value = eval_string("(f 1)", &mut context).unwrap();
+        assert_eq!(value.unpack(), &Expr::Int(2));
+        assert!(!context.dispatch_cache.is_empty());
+
+        // The cached method is used for the same signature.
+        let value = eval_string("(f 2)", &mut context).unwrap();
+        assert_eq!(value.unpack(), &Expr::Int(3));
+
+        // Redefining the method invalidates the cache.
+        context.register("g", |a: i64| a + 100);
+        let value = eval_string("(f 1)", &mut context).unwrap();
+        assert_eq!(value.unpack(), &Expr::Int(101));
+
+        // Rebinding the operator is detected.
+        let input = "(let h (Func x 1)) (let k (Func x (h x)))";
+        eval_string(input, &mut context).unwrap();
+        let value = eval_string("(k 1)", &mut context).unwrap();
+        assert_eq!(value.unpack(), &Expr::Int(1));
+        eval_string("(let h (Func x 2))", &mut context).unwrap();
+        let value = eval_string("(k 1)", &mut context).unwrap();
+        assert_eq!(value.unpack(), &Expr::Int(2));
+    }
+
+    #[test]
+    fn dispatch_cache_distinguishes_the_scopes_of_closures() {
+        let mut context = test_context();
+
+        let input = r#"
+        (let g (Func x 0))
+        (let a (Func x (g x)))
+        "#;
+        eval_string(input, &mut context).unwrap();
+
+        // `b` shares the body, i.e. the call-site `(g x)`, and the callee `g`
+        // with `a`, but captures a scope that shadows the `g` method.
+        let a = context.scope.get("a").unwrap();
+        let Expr::Func(params, body, scope, file_path) = a.unpack() else {
+            panic!("`a` should be a Func");
+        };
+        let b_scope = Arc::new(Scope::new(scope.clone()));
+        let b = Expr::Func(
+            params.clone(),
+            body.clone(),
+            b_scope.clone(),
+            file_path.clone(),
+        );
+        context.scope.insert("b", b);
+
+        scope.insert("g$$Int", eval_string("(Func x 1)", &mut context).unwrap());
+        b_scope.insert("g$$Int", eval_string("(Func x 2)", &mut context).unwrap());
+
+        let value = eval_string("[(a 5) (b 5) (a 5)]", &mut context).unwrap();
+        assert_eq!(value.to_string(), "[1 2 1]");
+    }
+}
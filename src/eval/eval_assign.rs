@@ -1,6 +1,6 @@
 use crate::{context::Context, error::Error, expr::Expr};
 
-use super::eval;
+use super::{eval, update_binding};
 
 // #insight
 // This is not the same as let, it also traverses the scope stack to find
@@ -39,17 +39,16 @@ pub fn eval_assign(args: &[Expr], context: &mut Context) -> Result<Expr, Error>
         return Err(Error::invalid_arguments("malformed `assign`", None));
     };
 
-    let Some(name) = name.as_stringable() else {
+    if name.as_stringable().is_none() && !matches!(name.unpack(), Expr::LocalSymbol(..)) {
         return Err(Error::invalid_arguments(
             "requires a symbol as the first argument",
             name.range(),
         ));
-    };
+    }
 
     let value = eval(value, context)?;
 
-    // #todo should we check that the symbol actually exists?
-    context.scope.update(name, value.clone());
+    update_binding(name, value, context);
 
     // #todo what should this return? One/Unit (i.e. nothing useful) or the actual value?
     Ok(Expr::None)
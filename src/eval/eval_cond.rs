@@ -12,6 +12,8 @@ use super::eval;
 //   else (...)
 // )
 pub fn eval_cond(args: &[Expr], context: &mut Context) -> Result<Expr, Error> {
+    let is_tail = context.take_tail_position();
+
     let mut i = 0;
 
     loop {
@@ -35,6 +37,7 @@ pub fn eval_cond(args: &[Expr], context: &mut Context) -> Result<Expr, Error> {
         if let Expr::Symbol(sym) = predicate.unpack() {
             // #todo Very confusing, don't allow else like this, use '_'.
             if sym == "_" || sym == "else" {
+                context.is_tail_position = is_tail;
                 break eval(clause, context);
             }
         }
@@ -49,6 +52,7 @@ pub fn eval_cond(args: &[Expr], context: &mut Context) -> Result<Expr, Error> {
         };
 
         if predicate {
+            context.is_tail_position = is_tail;
             break eval(clause, context);
         }
 
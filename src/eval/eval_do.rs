@@ -7,6 +7,8 @@ use super::eval;
 // #todo #WARNING will probably deprecate and only leave `let`.
 
 pub fn eval_do(args: &[Expr], context: &mut Context) -> Result<Expr, Error> {
+    let is_tail = context.take_tail_position();
+
     // #todo do should be 'monadic', propagate Eff (effect) wrapper.
     let mut value = Expr::None;
 
@@ -17,7 +19,9 @@ pub fn eval_do(args: &[Expr], context: &mut Context) -> Result<Expr, Error> {
     let prev_scope = context.scope.clone();
     context.scope = Arc::new(Scope::new(prev_scope.clone()));
 
-    for expr in args {
+    for (i, expr) in args.iter().enumerate() {
+        // #insight The last expression inherits the tail position.
+        context.is_tail_position = is_tail && i == args.len() - 1;
         value = eval(expr, context)?;
     }
 
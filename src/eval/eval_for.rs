@@ -129,6 +129,7 @@ pub fn eval_for(args: &[Expr], context: &mut Context) -> Result<Expr, Error> {
     //     insert_binding(var, value, context)?;
     'outer_loop: while insert_next_bindings(&bindings, context)? {
         // insert_binding(var, value, context)?;
+        context.consume_step(Some(binding))?;
         'inner_loop: for expr in body {
             match eval(expr, context) {
                 Err(Error {
@@ -20,7 +20,7 @@ pub fn eval_for_each(args: &[Expr], context: &mut Context) -> Result<Expr, Error
         ));
     };
 
-    let Some(sym) = var.as_symbol() else {
+    let Expr::Symbol(sym) = var.unpack() else {
         return Err(Error::invalid_arguments(
             "`for-each` requires a symbol as the second argument",
             var.range(),
@@ -48,7 +48,7 @@ pub fn eval_for_list(args: &[Expr], context: &mut Context) -> Result<Expr, Error
         ));
     };
 
-    let Some(var) = var.as_symbol() else {
+    let Expr::Symbol(var) = var.unpack() else {
         // #todo proper error!
         return Err(Error::invalid_arguments(
             "invalid for->list binding, malformed variable",
@@ -13,6 +13,8 @@ use super::{eval, eval_do::eval_do};
 
 // #todo Somehow mark that this is lazy evaluation.
 pub fn eval_if(args: &[Expr], context: &mut Context) -> Result<Expr, Error> {
+    let is_tail = context.take_tail_position();
+
     // #insight If is not comp-time.
     // #insight Cannot use unpack_bool_arg, this has lazy evaluation.
     // #todo Is the name `predicate` relevant here?
@@ -65,9 +67,11 @@ pub fn eval_if(args: &[Expr], context: &mut Context) -> Result<Expr, Error> {
 
     let value = if predicate {
         // #todo Extract common code between this, do, for, etc.
+        context.is_tail_position = is_tail;
         eval_do(body, context)?
     } else if let Some(else_clause) = else_clause {
         // #insight Note that (else ...) is like (do ...)!
+        context.is_tail_position = is_tail;
         eval_do(&else_clause[1..], context)?
     } else {
         // #todo What should be the return value?
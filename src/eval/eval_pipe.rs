@@ -5,7 +5,7 @@ use crate::{
     util::args::unpack_arg,
 };
 
-use super::{eval, invoke};
+use super::{eval, invoke, util::push_call_frame};
 
 // #insight Function pipes are related to function composition.
 
@@ -48,11 +48,11 @@ pub fn eval_pipe(args: &[Expr], context: &mut Context) -> Result<Expr, Error> {
 
     // #insight Cannot use fold due to error propagation.
 
-    for func in funcs.iter() {
+    for func_expr in funcs.iter() {
         // #todo Can we remove this clone somehow?
-        let func = eval(func, context)?;
+        let func = eval(func_expr, context)?;
         let args = vec![expr_clone(&value)];
-        value = invoke(&func, args, context)?;
+        value = push_call_frame(invoke(&func, args, context), func_expr, func_expr, context)?;
     }
 
     Ok(value)
@@ -30,6 +30,12 @@ pub fn eval_scope_update(args: &[Expr], context: &mut Context) -> Result<Expr, E
     };
 
     for (name, value) in map.iter() {
+        let Some(name) = name.as_stringable() else {
+            return Err(Error::invalid_arguments(
+                &format!("malformed `scope-update`, invalid binding name `{name}`"),
+                name.range(),
+            ));
+        };
         // #todo remove clone.
         context.scope.insert(name, expr_clone(value));
     }
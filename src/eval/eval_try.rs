@@ -0,0 +1,111 @@
+use std::sync::Arc;
+
+use crate::{
+    context::Context,
+    error::{Error, ErrorVariant},
+    expr::Expr,
+    scope::Scope,
+};
+
+use super::eval_do::eval_do;
+
+// #todo Consider a (finally ...) clause.
+// #todo Consider matching on the error variant, e.g. (catch (UndefinedSymbol err) ...)
+
+// #insight
+// Panics and control-flow 'pseudo' errors (return, break, continue) are not
+// caught, they are propagated upstream.
+
+// #insight
+// Unlike (do ...), (if ...) and (cond ...), (try ...) does not pass the tail
+// position to its body, the errors of a call must be caught before the
+// enclosing invocation returns. Calls in a try body are not eliminated, deep
+// recursion through (try ...) grows the native stack.
+
+// (try
+//   (use "/missing/module")
+//   (catch err
+//     (writeln (err :message))
+//   )
+// )
+pub fn eval_try(args: &[Expr], context: &mut Context) -> Result<Expr, Error> {
+    // #todo Make (catch ...) raise error if not in the last position of try.
+
+    let catch_clause = if let Some(last_clause) = args.last() {
+        if let Some(last_clause) = last_clause.as_list() {
+            if let Some(op) = last_clause.first().and_then(|op| op.as_symbol()) {
+                if op == "catch" {
+                    Some(last_clause)
+                } else {
+                    None
+                }
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+
+    let Some(catch_clause) = catch_clause else {
+        return Err(Error::invalid_arguments(
+            "malformed try expression, missing (catch ...) clause",
+            None,
+        ));
+    };
+
+    let Some(error_name) = catch_clause.get(1) else {
+        return Err(Error::invalid_arguments(
+            "malformed catch clause, missing error name",
+            catch_clause.first().and_then(|op| op.range()),
+        ));
+    };
+
+    let Some(error_name) = error_name.as_symbol() else {
+        return Err(Error::invalid_arguments(
+            "malformed catch clause, the error name should be a Symbol",
+            error_name.range(),
+        ));
+    };
+
+    // Remove the catch_clause from the body.
+    let body = &args[..(args.len() - 1)];
+
+    let prev_scope = context.scope.clone();
+
+    match eval_do(body, context) {
+        Ok(value) => Ok(value),
+        // #insight The exhausted budget is not caught, like a panic, so both
+        // engines stop at the same point.
+        Err(error)
+            if error.variant.is_control_flow()
+                || error.is_panic()
+                || matches!(error.variant, ErrorVariant::BudgetExhausted) =>
+        {
+            Err(error)
+        }
+        Err(error) => {
+            // #insight The failed body may not restore the scope, so we do it here.
+            context.scope = Arc::new(Scope::new(prev_scope.clone()));
+
+            if error_name != "_" {
+                context.scope.insert(error_name, error.to_expr());
+            }
+
+            let value = eval_do(&catch_clause[2..], context);
+
+            context.scope = prev_scope;
+
+            value
+        }
+    }
+}
+
+pub fn eval_catch(_args: &[Expr], _context: &mut Context) -> Result<Expr, Error> {
+    Err(Error::invalid_arguments(
+        "`catch` can only be used as the last clause of a `try` block",
+        None,
+    ))
+}
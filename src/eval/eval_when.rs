@@ -21,6 +21,8 @@ use super::eval;
 //   _ (...)
 // )
 pub fn eval_when(args: &[Expr], context: &mut Context) -> Result<Expr, Error> {
+    let is_tail = context.take_tail_position();
+
     // #todo can use returns instead of breaks.
 
     let value = unpack_arg(args, 0, "value")?;
@@ -53,11 +55,13 @@ pub fn eval_when(args: &[Expr], context: &mut Context) -> Result<Expr, Error> {
         match pattern {
             Expr::Symbol(sym) => {
                 if sym == "_" {
+                    context.is_tail_position = is_tail;
                     break eval(clause, context);
                 }
             }
             Expr::Type(type_name) => {
                 if has_dyn_type(&value, type_name, context) {
+                    context.is_tail_position = is_tail;
                     break eval(clause, context);
                 }
             }
@@ -76,6 +80,7 @@ pub fn eval_when(args: &[Expr], context: &mut Context) -> Result<Expr, Error> {
                                         context.scope.insert(name, Expr::Int(*n));
                                     }
                                 } // #todo raise error.
+                                context.is_tail_position = is_tail;
                                 break eval(clause, context);
                             }
                         }
@@ -91,6 +96,7 @@ pub fn eval_when(args: &[Expr], context: &mut Context) -> Result<Expr, Error> {
                                         context.scope.insert(name, Expr::string(reason));
                                     }
                                 } // #todo raise error.
+                                context.is_tail_position = is_tail;
                                 break eval(clause, context);
                             }
                         }
@@ -30,6 +30,8 @@ pub fn eval_while(args: &[Expr], context: &mut Context) -> Result<Expr, Error> {
     let body = &args[1..];
 
     loop {
+        context.consume_step(Some(predicate))?;
+
         let predicate = eval(predicate, context)?;
 
         // let Some(predicate) = predicate.as_bool() else {
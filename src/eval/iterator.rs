@@ -13,8 +13,10 @@ use std::{
     sync::{Arc, RwLock, RwLockReadGuard},
 };
 
+use imbl::Vector;
+
 use crate::{
-    expr::{expr_clone, Expr},
+    expr::{expr_clone, expr_persistent::PersistentMap, Expr},
     util::{expect_lock_read, expect_lock_write},
 };
 
@@ -166,6 +168,21 @@ impl ExprIterator for ArrayIteratorRc2 {
     }
 }
 
+// #insight The vector is cloned in O(1), no lock is needed.
+pub struct VectorIterator {
+    current: usize,
+    items: Vector<Expr>,
+    pub step: usize,
+}
+
+impl ExprIterator for VectorIterator {
+    fn next(&mut self) -> Option<Expr> {
+        let value = self.items.get(self.current).cloned();
+        self.current += self.step;
+        value
+    }
+}
+
 pub struct MapIterator {
     current: usize,
     items: Vec<Expr>,
@@ -206,6 +223,20 @@ impl ExprIterator for SetIterator {
     }
 }
 
+// #insight The entries of a persistent map are immutable `[key value]` vectors.
+fn persistent_map_iterator(map: &PersistentMap) -> MapIterator {
+    let items = map
+        .iter()
+        .map(|(k, v)| Expr::vector(vec![k.clone(), v.clone()]))
+        .collect();
+
+    MapIterator {
+        current: 0,
+        items,
+        step: 1,
+    }
+}
+
 // #todo find better name.
 // #todo consider using Box<dyn ExprIterator> instead, at least have a custom helper that returns Box.
 pub fn try_iterator_from<'a>(expr: &'a Expr) -> Option<Rc<RefCell<dyn ExprIterator + 'a>>> {
@@ -259,12 +290,10 @@ pub fn try_iterator_from<'a>(expr: &'a Expr) -> Option<Rc<RefCell<dyn ExprIterat
                 panic!("invalid state in for-map");
             };
 
-            // #todo why does map return k as String?
             // #todo wow, this is incredibly inefficient.
-            // #todo #hack temp fix we add the a `:` prefix to generate keys
             let items: Vec<_> = items
                 .iter()
-                .map(|(k, v)| Expr::array(vec![Expr::KeySymbol(k.clone()), expr_clone(v)]))
+                .map(|(k, v)| Expr::array(vec![k.clone(), expr_clone(v)]))
                 .collect();
 
             Some(Rc::new(RefCell::new(MapIterator {
@@ -273,6 +302,17 @@ pub fn try_iterator_from<'a>(expr: &'a Expr) -> Option<Rc<RefCell<dyn ExprIterat
                 step: 1,
             })))
         }
+        Expr::Vector(items) => Some(Rc::new(RefCell::new(VectorIterator {
+            current: 0,
+            items: items.clone(),
+            step: 1,
+        }))),
+        Expr::PersistentMap(map) => Some(Rc::new(RefCell::new(persistent_map_iterator(map)))),
+        Expr::PersistentSet(set) => Some(Rc::new(RefCell::new(SetIterator {
+            current: 0,
+            items: set.iter().cloned().collect(),
+            step: 1,
+        }))),
         Expr::Set(_) => {
             // example usage: #todo
             // #todo somehow reuse map_get_entries
@@ -342,12 +382,10 @@ pub fn try_iterator_from_consuming(expr: Expr) -> Option<Rc<RefCell<dyn ExprIter
 
             let items = expect_lock_write(&items);
 
-            // #todo why does map return k as String?
             // #todo wow, this is incredibly inefficient.
-            // #todo #hack temp fix we add the a `:` prefix to generate keys
             let items: Vec<_> = items
                 .iter()
-                .map(|(k, v)| Expr::array(vec![Expr::KeySymbol(k.clone()), expr_clone(v)]))
+                .map(|(k, v)| Expr::array(vec![k.clone(), expr_clone(v)]))
                 .collect();
 
             Some(Rc::new(RefCell::new(MapIterator {
@@ -356,6 +394,17 @@ pub fn try_iterator_from_consuming(expr: Expr) -> Option<Rc<RefCell<dyn ExprIter
                 step: 1,
             })))
         }
+        Expr::Vector(items) => Some(Rc::new(RefCell::new(VectorIterator {
+            current: 0,
+            items,
+            step: 1,
+        }))),
+        Expr::PersistentMap(map) => Some(Rc::new(RefCell::new(persistent_map_iterator(&map)))),
+        Expr::PersistentSet(set) => Some(Rc::new(RefCell::new(SetIterator {
+            current: 0,
+            items: set.iter().cloned().collect(),
+            step: 1,
+        }))),
         Expr::Set(items) => {
             // example usage: #todo
             // #todo somehow reuse map_get_entries
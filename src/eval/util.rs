@@ -6,12 +6,15 @@ use std::{
 
 use crate::{
     api::{compile_string, has_tan_extension, has_tan_extension_strict, strip_tan_extension},
-    context::Context,
+    context::{Context, Engine},
     error::Error,
     expr::Expr,
     module::Module,
+    range::Range,
     scope::Scope,
+    symbol::Symbol,
     util::standard_names::{CURRENT_FILE_PATH, CURRENT_MODULE_PATH},
+    vm::Vm,
 };
 
 use super::eval;
@@ -23,22 +26,44 @@ use super::eval;
 /// If the result is an error, add a range from the 'anchor' expression.
 pub fn anchor_error(result: Result<Expr, Error>, expr: &Expr) -> Result<Expr, Error> {
     if let Err(mut error) = result {
-        // #todo consider anchoring all notes!
-        // #todo notes in error is a hack, needs refactoring.
-
-        // #todo #important #fix It seems that expr some times don't have range here, WHY?
-
-        if let Some(note) = error.notes.first_mut() {
-            if note.range.is_none() {
-                note.range = expr.range()
-            }
-        };
+        anchor_error_to_range(&mut error, expr.range());
         Err(error)
     } else {
         result
     }
 }
 
+/// Adds the range to the error, if the error is not already anchored.
+pub fn anchor_error_to_range(error: &mut Error, range: Option<Range>) {
+    // #todo consider anchoring all notes!
+    // #todo notes in error is a hack, needs refactoring.
+    if let Some(note) = error.notes.first_mut() {
+        if note.range.is_none() {
+            note.range = range
+        }
+    };
+}
+
+/// If the result is an error, push a call-stack frame for the invocation of
+/// `op`, at the call-site `expr`. Control-flow errors are passed through.
+pub fn push_call_frame(
+    result: Result<Expr, Error>,
+    op: &Expr,
+    expr: &Expr,
+    context: &Context,
+) -> Result<Expr, Error> {
+    match result {
+        Err(mut error) if !error.variant.is_control_flow() => {
+            let name = op.as_symbolic();
+            let name = name.as_deref().unwrap_or("<anonymous>");
+            let range = expr.range().or_else(|| op.range());
+            error.push_frame(name, &get_current_file_path(context), range);
+            Err(error)
+        }
+        _ => result,
+    }
+}
+
 // #todo split read_module, eval(_module)
 // #todo not sure that dir as module is a good idea.
 
@@ -48,54 +73,38 @@ pub fn anchor_error(result: Result<Expr, Error>, expr: &Expr) -> Result<Expr, Er
 //     todo!()
 // }
 
-fn get_current_module_path(context: &Context) -> String {
-    // #todo Pass what's needed from context.
-    let Some(current_module_path) = context.scope.get(CURRENT_MODULE_PATH) else {
-        // #todo!
-        panic!("missing current-module-path");
-    };
+/// Resolves a module path to a canonical module path (id), using the module
+/// loader of the context.
+pub fn resolve_module_path(path: impl Into<String>, context: &Context) -> Result<String, Error> {
+    let path = path.into();
 
-    let Some(current_module_path) = current_module_path.as_string() else {
-        // #todo!
-        panic!("invalid current-module-path");
-    };
+    // #todo Pass what's needed from context.
+    let current_module_path = context.scope.get(CURRENT_MODULE_PATH);
+    let current_module_path = current_module_path.as_ref().and_then(|p| p.as_string());
 
-    // #todo Can we return &str and keep the borrow-checker happy?
-    current_module_path.to_string()
+    context.module_loader.resolve(&path, current_module_path)
 }
 
-pub fn resolve_module_path(path: impl Into<String>, context: &Context) -> std::io::Result<String> {
-    let mut path = path.into();
+// #todo add unit test for `../` paths.
+/// Resolves a relative (`./`, `../`) module path against the current module path.
+pub fn resolve_relative_module_path(
+    path: &str,
+    current_module_path: Option<&str>,
+) -> Result<String, Error> {
+    let Some(current_module_path) = current_module_path else {
+        return Err(Error::invalid_arguments(
+            &format!("cannot resolve relative module path `{path}`, missing current module path"),
+            None,
+        ));
+    };
 
-    if path.starts_with("./") {
-        path = format!(
-            "{}{}",
-            get_current_module_path(context),
-            path.strip_prefix('.').unwrap(),
-        );
-    } else if path.starts_with("../") {
-        path = format!("{}/{path}", get_current_module_path(context));
-    } else if path.starts_with("file://") {
-        // #insight used by tan-run.
-        path = path[7..].to_string();
-    } else {
-        // #todo Add integration test for local-tan-root.
-        // #todo #perf #IMPORTANT somehow cache this!
-        // #todo Also check `./.patch-tan-root`, first.
-        let local_path = resolve_non_relative_module_path(&path, ".local-tan-root");
-        if Path::new(&local_path).exists() {
-            // #todo Add some tracing here!
-            // println!("Using local dependency");
-            path = local_path;
-        } else {
-            path = resolve_non_relative_module_path(&path, &context.root_path);
+    if let Some(path) = path.strip_prefix('.') {
+        if path.starts_with('/') {
+            return Ok(format!("{current_module_path}{path}"));
         }
     }
 
-    // #todo Check here if the path actually exists!
-    // std::fs::metadata(&path)?;
-
-    Ok(canonicalize_path(path))
+    Ok(format!("{current_module_path}/{path}"))
 }
 
 // #insight The input 'path' is a module path/url, not a file-system path.
@@ -106,7 +115,7 @@ pub fn resolve_module_path(path: impl Into<String>, context: &Context) -> std::i
 // #todo write unit tests!
 // #todo find another name, there is confusion with path_buf::canonicalize.
 // #todo remove the _module_ from name, used also for files and dyn-libs.
-fn resolve_non_relative_module_path(path: impl Into<String>, root_path: &str) -> String {
+pub fn resolve_non_relative_module_path(path: impl Into<String>, root_path: &str) -> String {
     let path = path.into();
 
     // #todo what is a good coding convention for 'system' variables?
@@ -235,14 +244,14 @@ pub fn eval_file(path: &str, context: &mut Context) -> Result<Expr, Vec<Error>>
     //     .top_scope
     //     .insert(CURRENT_FILE_PATH, Expr::string(path));
 
-    let prev_current_file_path = get_current_file_path(context);
-    set_current_file_path(context, path);
-
-    let input = std::fs::read_to_string(path);
+    let input = context.module_loader.read_source(path);
     let Ok(input) = input else {
-        return Err(vec![input.unwrap_err().into()]);
+        return Err(vec![input.unwrap_err()]);
     };
 
+    let prev_current_file_path = get_current_file_path(context);
+    set_current_file_path(context, path);
+
     let result = compile_string(input, context);
 
     let Ok(exprs) = result else {
@@ -261,11 +270,16 @@ pub fn eval_file(path: &str, context: &mut Context) -> Result<Expr, Vec<Error>>
 
     let mut value = Expr::None;
     let mut errors = Vec::new();
+    let mut vm = Vm::new();
 
     for expr in exprs {
-        // if let Err(mut error) = eval(&expr, context) {
-        match eval(&expr, context) {
-            Ok(expr) => value = expr,
+        let result = context.check_budget().and_then(|_| match context.engine {
+            Engine::Eval => eval(&expr, context),
+            Engine::Vm => vm.eval(&expr, context),
+        });
+
+        match result {
+            Ok(value_expr) => value = value_expr,
             Err(mut error) => {
                 // #todo add a unit test to check that the file_path is added here!
                 // #todo just make error.file_path optional and avoid this hack here!!!
@@ -273,8 +287,17 @@ pub fn eval_file(path: &str, context: &mut Context) -> Result<Expr, Vec<Error>>
                     error.file_path = path.to_string();
                 }
 
+                error.push_frame("<top-level>", path, expr.range());
+
+                // #insight A panic stops the evaluation of the file.
+                let is_panic = error.is_panic();
+
                 // #todo better error here!
                 errors.push(error);
+
+                if is_panic {
+                    break;
+                }
             }
         }
     }
@@ -309,7 +332,7 @@ pub fn eval_module(path: &str, context: &mut Context, force: bool) -> Result<Exp
     let result = resolve_module_path(path, context);
 
     let Ok(module_path) = result else {
-        return Err(vec![result.unwrap_err().into()]);
+        return Err(vec![result.unwrap_err()]);
     };
 
     // #todo is this really needed?
@@ -350,7 +373,9 @@ pub fn eval_module(path: &str, context: &mut Context, force: bool) -> Result<Exp
     // Lookup into the module_registry first.
 
     // #todo optimize this lookup, no need for contains_key!
-    let module = if context.module_registry.contains_key(&module_path) {
+    let is_registered = context.module_registry.contains_key(&module_path);
+
+    let module = if is_registered {
         let module = context.module_registry.get(&module_path).unwrap().clone();
         if !force {
             // #insight if not in force mode, just returned the evaluated (cached) module.
@@ -372,6 +397,9 @@ pub fn eval_module(path: &str, context: &mut Context, force: bool) -> Result<Exp
         .module_registry
         .insert(module_path.clone(), module.clone());
 
+    // #insight The call-stack frame file path is the file that uses the module.
+    let caller_file_path = get_current_file_path(context);
+
     // #todo avoid the module.scope.clone()
     let prev_scope = context.scope.clone();
     context.scope = module.scope.clone();
@@ -400,13 +428,19 @@ pub fn eval_module(path: &str, context: &mut Context, force: bool) -> Result<Exp
     }
 
     let strict = !context.is_test_profile();
-    let file_paths = compute_module_file_paths(&module_path, strict);
+    let file_paths = context
+        .module_loader
+        .source_unit_paths(&module_path, strict);
 
     let Ok(file_paths) = file_paths else {
         // #todo argh! we need something like defer here!
         // #todo could use defer_lite crate and the defer! macro!!
         context.scope = prev_scope;
-        return Err(vec![file_paths.unwrap_err().into()]);
+        if !is_registered {
+            // #insight Unregister the failed module, to allow recovery, e.g. in (try ...)
+            context.module_registry.remove(&module_path);
+        }
+        return Err(vec![file_paths.unwrap_err()]);
     };
 
     // #todo return Expr::Module, add module metadata: name, path, exports, etc.
@@ -415,9 +449,20 @@ pub fn eval_module(path: &str, context: &mut Context, force: bool) -> Result<Exp
     // #todo prohibit recursive module_evals.
 
     for file_path in &file_paths {
-        if let Err(errors) = eval_file(file_path, context) {
+        if let Err(mut errors) = eval_file(file_path, context) {
+            for error in &mut errors {
+                error.push_frame(
+                    &format!("<module {}>", module.stem),
+                    &caller_file_path,
+                    None,
+                );
+            }
+
             // #todo Investigate if errors are muted here!
             context.scope = prev_scope;
+            if !is_registered {
+                context.module_registry.remove(&module_path);
+            }
             return Err(errors);
         }
     }
@@ -436,7 +481,7 @@ pub fn eval_module(path: &str, context: &mut Context, force: bool) -> Result<Exp
 pub fn get_bindings_with_prefix(
     scope: &Scope,
     prefix: impl AsRef<str>,
-) -> Vec<(String, Arc<Expr>)> {
+) -> Vec<(Symbol, Arc<Expr>)> {
     let name = prefix.as_ref();
     let prefix = format!("{name}$$");
 
@@ -445,8 +490,8 @@ pub fn get_bindings_with_prefix(
     let mut matched_bindings = Vec::new();
 
     for key in scope_bindings.keys() {
-        if key == name || key.starts_with(&prefix) {
-            matched_bindings.push((key.clone(), scope_bindings.get(key).unwrap().clone()));
+        if *key == name || key.starts_with(&prefix) {
+            matched_bindings.push((*key, scope_bindings.get(key).unwrap().clone()));
         }
     }
 
@@ -504,6 +549,4 @@ mod tests {
         let paths = compute_module_file_paths("tests/fixtures/dummy-module", false).unwrap();
         assert_eq!(paths.len(), 3);
     }
-
-    // #todo add unit test for `../` paths.
 }
@@ -1,24 +1,37 @@
 pub mod expr_iter;
+pub mod expr_persistent;
 pub mod expr_transform;
 
 use std::{
     any::Any,
+    cmp::Ordering,
     collections::{HashMap, HashSet},
     fmt,
     hash::{Hash, Hasher},
     sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
 };
 
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeDelta};
+use imbl::Vector;
+use indexmap::{IndexMap, IndexSet};
+#[cfg(feature = "bigint")]
+use num_bigint::BigInt;
+use num_rational::Rational64;
 use rust_decimal::Decimal;
 
 use crate::{
     context::Context,
     error::Error,
+    expr::expr_persistent::{PersistentMap, PersistentSet},
     lexer::comment::CommentKind,
     module::Module,
     range::{Position, Range},
     scope::Scope,
-    util::{expect_lock_read, expect_lock_write, fmt::format_float},
+    symbol::{sym, Symbol},
+    util::{
+        expect_lock_read, expect_lock_write,
+        fmt::{format_duration, format_float, format_instant, format_ratio},
+    },
 };
 
 // #todo Introduce separate Sync, Send, Sync+Send versions of Expr?
@@ -81,11 +94,12 @@ pub enum ForeignFnRef {
     NoContext(&'static FnNoContext),
     Context(&'static FnContext),
     MutContext(&'static FnMutContext),
+    // #insight Used for closures that capture state, see `foreign::register_foreign_func`.
+    Shared(Arc<FnMutContext>),
 }
 
 // #todo Use normal structs instead of tuple-structs?
 
-// #todo Add Expr::Date
 // #todo Add Expr::Panic (catched by the runtime, should support unwind)
 
 // #insight Maybe.None == Nil == Unit
@@ -129,16 +143,31 @@ pub enum Expr {
     // #todo consider `Byte`, `UInt8`?
     U8(u8),
     Int(i64),
+    /// An arbitrary-precision integer, e.g. `123n`.
+    #[cfg(feature = "bigint")]
+    BigInt(BigInt),
     Float(f64),
     Dec(Decimal),
-    Symbol(String),    // #todo consider renaming to Expr::Sym
-    KeySymbol(String), // #todo consider renaming to Expr::Key
+    /// An exact rational number, always in reduced form, e.g. `1/3`.
+    Ratio(Rational64),
+    Symbol(Symbol), // #todo consider renaming to Expr::Sym
+    /// A reference to a function parameter, resolved to the slot `index` of
+    /// the enclosing invocation frame at `depth`, see `resolve_locals`.
+    /// LocalSymbol(name, depth, index)
+    LocalSymbol(Symbol, usize, usize),
+    KeySymbol(Symbol), // #todo consider renaming to Expr::Key
     Char(char),
     String(String),
+    /// A calendar date, e.g. `#Date "2024-01-15"`.
+    Date(NaiveDate),
+    /// A point in time with a UTC offset, e.g. `#Instant "2024-01-15T10:30:00Z"`.
+    Instant(DateTime<FixedOffset>),
+    /// A span of time, e.g. `#Duration "PT1H30M"`.
+    Duration(TimeDelta),
     // #todo currently a special String for types.
     // #todo consider Typ
     // #todo Make sure types are unpacked as strings, not symbols.
-    Type(String),
+    Type(Symbol),
     // #todo better name for 'generic' List, how about `Cons` or `ConsList` or `Cell`?
     // #todo add 'quoted' List -> Array!
     // #todo do we really need Vec here? Maybe Arc<[Expr]> is enough?
@@ -147,9 +176,14 @@ pub enum Expr {
     // #todo Consider Vec<u8> -> Box<[u8]> to make non-resizable.
     Buffer(usize, Arc<RwLock<Vec<u8>>>), // #insight 'reference' type
     // #todo different name?
-    // #todo support Expr as keys?
-    Map(Arc<RwLock<HashMap<String, Expr>>>),
-    Set(Arc<RwLock<HashSet<Expr>>>),
+    Map(Arc<RwLock<IndexMap<Expr, Expr>>>),
+    Set(Arc<RwLock<IndexSet<Expr>>>),
+    /// An immutable vector with structural sharing, e.g. `#Vector [1 2 3]`.
+    Vector(Vector<Expr>),
+    /// An immutable map with structural sharing, e.g. `#PersistentMap {:a 1}`.
+    PersistentMap(PersistentMap),
+    /// An immutable set with structural sharing.
+    PersistentSet(PersistentSet),
     // #todo support `start..` and `..end` ranges.
     // #todo open-ended range with step can look like this: `start../2`
     // #todo have type render as (Range Int)
@@ -159,8 +193,10 @@ pub enum Expr {
     // Range(...),
     // #todo the Func should probably store the Module environment.
     // #todo maybe should have explicit do block?
+    // #insight The params and body are shared, functions are cloned on every
+    // lookup.
     /// Func(params, body, func_scope, filename)
-    Func(Vec<Expr>, Vec<Expr>, Arc<Scope>, String),
+    Func(Arc<Vec<Expr>>, Arc<Vec<Expr>>, Arc<Scope>, String),
     // #todo add file_path to Macro
     // #todo maybe should have explicit do block?
     /// Macro(params, body)
@@ -197,19 +233,34 @@ pub enum Expr {
 impl Eq for Expr {}
 
 // #todo think some more about this.
+// #insight Annotations are ignored, consistent with Hash.
 impl PartialEq for Expr {
     fn eq(&self, other: &Self) -> bool {
+        if matches!(self, Self::Annotated(..)) || matches!(other, Self::Annotated(..)) {
+            return self.unpack() == other.unpack();
+        }
+
         match (self, other) {
             (Self::Comment(l0, l1), Self::Comment(r0, r1)) => l0 == r0 && l1 == r1,
             (Self::Bool(l0), Self::Bool(r0)) => l0 == r0,
+            (Self::U8(l0), Self::U8(r0)) => l0 == r0,
             (Self::Int(l0), Self::Int(r0)) => l0 == r0,
+            #[cfg(feature = "bigint")]
+            (Self::BigInt(l0), Self::BigInt(r0)) => l0 == r0,
             (Self::Float(l0), Self::Float(r0)) => l0 == r0,
             (Self::Dec(l0), Self::Dec(r0)) => l0 == r0,
+            (Self::Ratio(l0), Self::Ratio(r0)) => l0 == r0,
             (Self::Symbol(l0), Self::Symbol(r0)) => l0 == r0,
+            (Self::LocalSymbol(l0, l1, l2), Self::LocalSymbol(r0, r1, r2)) => {
+                l0 == r0 && l1 == r1 && l2 == r2
+            }
             (Self::KeySymbol(l0), Self::KeySymbol(r0)) => l0 == r0,
             (Self::Type(l0), Self::Type(r0)) => l0 == r0,
             (Self::Char(l0), Self::Char(r0)) => l0 == r0,
             (Self::String(l0), Self::String(r0)) => l0 == r0,
+            (Self::Date(l0), Self::Date(r0)) => l0 == r0,
+            (Self::Instant(l0), Self::Instant(r0)) => l0 == r0,
+            (Self::Duration(l0), Self::Duration(r0)) => l0 == r0,
             (Self::List(l0), Self::List(r0)) => l0 == r0,
             // #todo maybe should leave for the default discriminant case?
             // #todo equality not supported for Array, due to RwLock.
@@ -217,6 +268,9 @@ impl PartialEq for Expr {
             // (Self::Array(l0), Self::Array(r0)) => l0 == r0,
             // #todo equality not supported for Map, due to RwLock.
             // (Self::Map(l0), Self::Map(r0)) => l0 == r0,
+            (Self::Vector(l0), Self::Vector(r0)) => l0 == r0,
+            (Self::PersistentMap(l0), Self::PersistentMap(r0)) => l0 == r0,
+            (Self::PersistentSet(l0), Self::PersistentSet(r0)) => l0 == r0,
             (Self::IntRange(l0, l1, l2), Self::IntRange(r0, r1, r2)) => {
                 l0 == r0 && l1 == r1 && l2 == r2
             }
@@ -228,58 +282,106 @@ impl PartialEq for Expr {
             (Self::ForeignFunc(..), Self::ForeignFunc(..)) => false,
             (Self::Foreign(..), Self::Foreign(..)) => false,
             (Self::If(l0, l1, l2), Self::If(r0, r1, r2)) => l0 == r0 && l1 == r1 && l2 == r2,
-            // #todo #think should unpack and ignore annotations?
-            (Self::Annotated(l0, _l1), Self::Annotated(r0, _r1)) => l0.eq(r0),
             (Self::Module(..), Self::Module(..)) => false,
             _ => core::mem::discriminant(self) == core::mem::discriminant(other),
         }
     }
 }
 
+// #insight Only values of the same variant are ordered, consistent with PartialEq.
+impl PartialOrd for Expr {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self.unpack(), other.unpack()) {
+            (Self::U8(l0), Self::U8(r0)) => l0.partial_cmp(r0),
+            (Self::Int(l0), Self::Int(r0)) => l0.partial_cmp(r0),
+            #[cfg(feature = "bigint")]
+            (Self::BigInt(l0), Self::BigInt(r0)) => l0.partial_cmp(r0),
+            (Self::Float(l0), Self::Float(r0)) => l0.partial_cmp(r0),
+            (Self::Dec(l0), Self::Dec(r0)) => l0.partial_cmp(r0),
+            (Self::Ratio(l0), Self::Ratio(r0)) => l0.partial_cmp(r0),
+            (Self::Char(l0), Self::Char(r0)) => l0.partial_cmp(r0),
+            (Self::String(l0), Self::String(r0)) => l0.partial_cmp(r0),
+            (Self::Date(l0), Self::Date(r0)) => l0.partial_cmp(r0),
+            (Self::Instant(l0), Self::Instant(r0)) => l0.partial_cmp(r0),
+            (Self::Duration(l0), Self::Duration(r0)) => l0.partial_cmp(r0),
+            _ => None,
+        }
+    }
+}
+
+// #insight
+// Map keys and Set items are hashed, the hash is consistent with PartialEq:
+// the variants that are not handled explicitly only hash the discriminant.
 impl Hash for Expr {
     fn hash<H: Hasher>(&self, state: &mut H) {
+        if let Self::Annotated(inner, _) = self {
+            return inner.hash(state);
+        }
+
+        core::mem::discriminant(self).hash(state);
+
         match self {
-            Self::Int(n) => {
-                0.hash(state);
-                n.hash(state);
-            }
-            Self::String(s) => {
-                0.hash(state);
-                s.hash(state);
-            }
-            Self::Annotated(inner, _) => inner.hash(state),
-            // Expr::Zero => todo!(),
-            // Expr::One => todo!(),
-            // Expr::Comment(_, _) => todo!(),
-            // Expr::TextSeparator => todo!(),
-            // Expr::Bool(_) => todo!(),
-            // Expr::Int(_) => todo!(),
-            // Expr::Float(_) => todo!(),
-            // Expr::Dec(_) => todo!(),
-            // Expr::Symbol(_) => todo!(),
-            // Expr::KeySymbol(_) => todo!(),
-            // Expr::Char(_) => todo!(),
-            // Expr::String(_) => todo!(),
-            // Expr::Type(_) => todo!(),
-            // Expr::List(_) => todo!(),
-            // Expr::Array(_) => todo!(),
-            // Expr::Map(_) => todo!(),
-            // Expr::Set(_) => todo!(),
-            // Expr::IntRange(_, _, _) => todo!(),
-            // Expr::FloatRange(_, _, _) => todo!(),
-            // Expr::Func(_, _, _, _) => todo!(),
-            // Expr::Macro(_, _) => todo!(),
-            // Expr::ForeignFunc(_) => todo!(),
-            // Expr::ForeignStruct(_) => todo!(),
-            // Expr::Do => todo!(),
-            // Expr::Let => todo!(),
-            // Expr::If(_, _, _) => todo!(),
-            // Expr::Annotated(_, _) => todo!(),
-            // Expr::Module(_) => todo!(),
-            _ => {
-                println!("******** no hash computation: {self}");
-            }
+            Self::Bool(b) => b.hash(state),
+            Self::U8(n) => n.hash(state),
+            Self::Int(n) => n.hash(state),
+            #[cfg(feature = "bigint")]
+            Self::BigInt(n) => n.hash(state),
+            // #insight `0.0 == -0.0`, so the zero is normalized.
+            Self::Float(n) => (if *n == 0.0 { 0.0 } else { *n }).to_bits().hash(state),
+            Self::Dec(n) => n.hash(state),
+            Self::Ratio(n) => n.hash(state),
+            Self::Symbol(s) | Self::KeySymbol(s) | Self::Type(s) => s.hash(state),
+            Self::String(s) => s.hash(state),
+            Self::LocalSymbol(s, ..) => s.hash(state),
+            Self::Char(c) => c.hash(state),
+            Self::Date(d) => d.hash(state),
+            Self::Instant(t) => t.hash(state),
+            Self::Duration(d) => d.hash(state),
+            Self::List(items) => items.hash(state),
+            Self::Vector(items) => items.hash(state),
+            _ => (),
+        }
+    }
+}
+
+/// Returns true if the expression can be used as a Map key or a Set item.
+// #insight
+// The reference types are mutable and compare by discriminant, and functions,
+// foreign values and modules are not even equal to themselves, so they would
+// collapse or lose entries when used as keys. The same holds for NaN.
+pub fn is_valid_key(expr: &Expr) -> bool {
+    match expr.unpack() {
+        Expr::Float(n) => !n.is_nan(),
+        Expr::Array(..)
+        | Expr::Buffer(..)
+        | Expr::Map(..)
+        | Expr::Set(..)
+        | Expr::Func(..)
+        | Expr::ForeignFunc(..)
+        | Expr::Foreign(..)
+        | Expr::ForeignMut(..)
+        | Expr::Module(..) => false,
+        Expr::List(items) => items.iter().all(is_valid_key),
+        Expr::Vector(items) => items.iter().all(is_valid_key),
+        Expr::PersistentMap(map) => map.iter().all(|(k, v)| is_valid_key(k) && is_valid_key(v)),
+        Expr::PersistentSet(set) => set.iter().all(is_valid_key),
+        Expr::If(predicate, then, otherwise) => {
+            is_valid_key(predicate)
+                && is_valid_key(then)
+                && otherwise.as_deref().is_none_or(is_valid_key)
         }
+        _ => true,
+    }
+}
+
+pub fn check_key(key: &Expr, range: Option<Range>) -> Result<(), Error> {
+    if is_valid_key(key) {
+        Ok(())
+    } else {
+        Err(Error::invalid_arguments(
+            &format!("`{}` cannot be used as a key", key.unpack()),
+            key.range().or(range),
+        ))
     }
 }
 
@@ -297,14 +399,21 @@ impl fmt::Debug for Expr {
             Expr::TextSeparator => "<TEXT-SEPARATOR>".to_owned(),
             Expr::Bool(b) => format!("Bool({b})"),
             Expr::Symbol(s) => format!("'{s}"), // "Symbol(s)"
+            Expr::LocalSymbol(s, depth, index) => format!("'{s}@{depth}:{index}"),
             Expr::KeySymbol(s) => format!("KeySymbol({s})"),
             Expr::Type(s) => format!("Type({s})"),
             Expr::Char(c) => format!("Char({c})"),
             Expr::String(s) => format!("String(\"{s}\")"),
+            Expr::Date(d) => format!("Date({d})"),
+            Expr::Instant(t) => format!("Instant({})", format_instant(t)),
+            Expr::Duration(d) => format!("Duration({})", format_duration(d)),
             Expr::U8(num) => format!("U8({num})"),
             Expr::Int(num) => format!("Int({num})"),
+            #[cfg(feature = "bigint")]
+            Expr::BigInt(num) => format!("BigInt({num})"),
             Expr::Float(num) => format!("Float({})", format_float(*num)),
             Expr::Dec(num) => format!("Dec({num})"),
+            Expr::Ratio(num) => format!("Ratio({})", format_ratio(num)),
             Expr::Do => "do".to_owned(),
             Expr::List(terms) => {
                 format!(
@@ -320,6 +429,9 @@ impl fmt::Debug for Expr {
             Expr::Array(v) => format!("Array({:?})", v.read().expect("poisoned lock")),
             Expr::Map(d) => format!("Map({d:?})"),
             Expr::Set(d) => format!("Set({d:?})"),
+            Expr::Vector(v) => format!("Vector({v:?})"),
+            Expr::PersistentMap(d) => format!("PersistentMap({d:?})"),
+            Expr::PersistentSet(d) => format!("PersistentSet({d:?})"),
             Expr::IntRange(start, end, step) => format!("IntRange({start},{end},{step})"),
             Expr::FloatRange(start, end, step) => format!("FloatRange({start},{end},{step})"),
             Expr::Func(..) => "<FUNC>".to_owned(),
@@ -358,12 +470,19 @@ impl fmt::Display for Expr {
                 Expr::Bool(b) => b.to_string(),
                 Expr::U8(n) => n.to_string(),
                 Expr::Int(n) => n.to_string(),
+                #[cfg(feature = "bigint")]
+                Expr::BigInt(n) => format!("{n}n"),
                 Expr::Float(n) => format_float(*n),
                 Expr::Dec(n) => format!("(Dec {n})"), // #todo 'literal', e.f. 1.23d or #Dec 1.23
-                Expr::Symbol(s) => s.clone(),
+                Expr::Ratio(n) => format_ratio(n),
+                Expr::Date(d) => format!(r#"#Date "{d}""#),
+                Expr::Instant(t) => format!(r#"#Instant "{}""#, format_instant(t)),
+                Expr::Duration(d) => format!(r#"#Duration "{}""#, format_duration(d)),
+                Expr::Symbol(s) => s.to_string(),
+                Expr::LocalSymbol(s, ..) => s.to_string(),
                 Expr::KeySymbol(s) => format!(":{s}"),
-                Expr::Type(s) => s.clone(),
-                Expr::Char(c) => format!(r#"(Char "{c}")"#), // #todo no char literal?
+                Expr::Type(s) => s.to_string(),
+                Expr::Char(c) => format_char(*c),
                 Expr::String(s) => format!("\"{s}\""),
                 Expr::Error(reason) => format!(r#"(Error "{reason}")"#),
                 Expr::Do => "do".to_owned(),
@@ -397,11 +516,9 @@ impl fmt::Display for Expr {
                     format!("[{exprs}]")
                 }
                 Expr::Map(map) => {
-                    // #todo Map should support arbitrary exprs (or at lease `(Into String)` exprs)
-                    // #todo currently we convert keys to symbol, make this more subtle.
                     let exprs = expect_lock_read(map)
                         .iter()
-                        .map(|(k, v)| format!(":{k} {v}"))
+                        .map(|(k, v)| format!("{k} {v}"))
                         .collect::<Vec<String>>()
                         .join(" ");
                     format!("{{{exprs}}}")
@@ -416,6 +533,30 @@ impl fmt::Display for Expr {
                         .join(" ");
                     format!("[{exprs}]")
                 }
+                Expr::Vector(items) => {
+                    let exprs = items
+                        .iter()
+                        .map(|expr| expr.to_string())
+                        .collect::<Vec<String>>()
+                        .join(" ");
+                    format!("#Vector [{exprs}]")
+                }
+                Expr::PersistentMap(map) => {
+                    let exprs = map
+                        .iter()
+                        .map(|(k, v)| format!("{k} {v}"))
+                        .collect::<Vec<String>>()
+                        .join(" ");
+                    format!("#PersistentMap {{{exprs}}}")
+                }
+                Expr::PersistentSet(set) => {
+                    let exprs = set
+                        .iter()
+                        .map(|v| format!("{v}"))
+                        .collect::<Vec<String>>()
+                        .join(" ");
+                    format!("#PersistentSet [{exprs}]")
+                }
                 Expr::IntRange(start, end, step) => {
                     if *step == 1 {
                         format!("{start}..{end}")
@@ -458,11 +599,11 @@ impl AsRef<Expr> for Expr {
 }
 
 impl Expr {
-    pub fn symbol(s: impl Into<String>) -> Self {
+    pub fn symbol(s: impl Into<Symbol>) -> Self {
         Expr::Symbol(s.into())
     }
 
-    pub fn key_symbol(s: impl Into<String>) -> Self {
+    pub fn key_symbol(s: impl Into<Symbol>) -> Self {
         Expr::KeySymbol(s.into())
     }
 
@@ -474,7 +615,7 @@ impl Expr {
         Expr::Error(s.into())
     }
 
-    pub fn typ(s: impl Into<String>) -> Self {
+    pub fn typ(s: impl Into<Symbol>) -> Self {
         Expr::Type(s.into())
     }
 
@@ -482,14 +623,26 @@ impl Expr {
         Expr::Array(Arc::new(RwLock::new(a.into())))
     }
 
-    pub fn map(m: impl Into<HashMap<String, Expr>>) -> Self {
+    pub fn map(m: impl Into<IndexMap<Expr, Expr>>) -> Self {
         Expr::Map(Arc::new(RwLock::new(m.into())))
     }
 
-    pub fn set(s: impl Into<HashSet<Expr>>) -> Self {
+    pub fn set(s: impl Into<IndexSet<Expr>>) -> Self {
         Expr::Set(Arc::new(RwLock::new(s.into())))
     }
 
+    pub fn vector(v: impl Into<Vector<Expr>>) -> Self {
+        Expr::Vector(v.into())
+    }
+
+    pub fn persistent_map(m: impl IntoIterator<Item = (Expr, Expr)>) -> Self {
+        Expr::PersistentMap(m.into_iter().collect())
+    }
+
+    pub fn persistent_set(s: impl IntoIterator<Item = Expr>) -> Self {
+        Expr::PersistentSet(s.into_iter().collect())
+    }
+
     // #todo Consider adding `_no_context` suffix, or better remove the NoContext in the types.
     pub fn foreign_func(f: &'static FnNoContext) -> Self {
         Expr::ForeignFunc(ForeignFnRef::NoContext(f))
@@ -499,6 +652,10 @@ impl Expr {
         Expr::ForeignFunc(ForeignFnRef::MutContext(f))
     }
 
+    pub fn foreign_func_shared(f: Arc<FnMutContext>) -> Self {
+        Expr::ForeignFunc(ForeignFnRef::Shared(f))
+    }
+
     // #todo Add `foreign_struct` and `foreign_struct_mut` helpers?
 
     pub fn annotated(expr: Expr, annotations: &HashMap<String, Expr>) -> Self {
@@ -570,9 +727,9 @@ impl Expr {
         }
     }
 
-    pub fn annotation(&self, name: impl Into<String>) -> Option<&Expr> {
+    pub fn annotation(&self, name: impl AsRef<str>) -> Option<&Expr> {
         match self {
-            Expr::Annotated(_, ann) => ann.get(&name.into()),
+            Expr::Annotated(_, ann) => ann.get(name.as_ref()),
             _ => None,
         }
     }
@@ -630,6 +787,14 @@ impl Expr {
         Some(*n)
     }
 
+    #[cfg(feature = "bigint")]
+    pub fn as_big_int(&self) -> Option<&BigInt> {
+        let Expr::BigInt(n) = self.unpack() else {
+            return None;
+        };
+        Some(n)
+    }
+
     pub fn as_float(&self) -> Option<f64> {
         let Expr::Float(n) = self.unpack() else {
             return None;
@@ -651,6 +816,34 @@ impl Expr {
         Some(*n)
     }
 
+    pub fn as_ratio(&self) -> Option<Rational64> {
+        let Expr::Ratio(n) = self.unpack() else {
+            return None;
+        };
+        Some(*n)
+    }
+
+    pub fn as_date(&self) -> Option<NaiveDate> {
+        let Expr::Date(d) = self.unpack() else {
+            return None;
+        };
+        Some(*d)
+    }
+
+    pub fn as_instant(&self) -> Option<DateTime<FixedOffset>> {
+        let Expr::Instant(t) = self.unpack() else {
+            return None;
+        };
+        Some(*t)
+    }
+
+    pub fn as_duration(&self) -> Option<TimeDelta> {
+        let Expr::Duration(d) = self.unpack() else {
+            return None;
+        };
+        Some(*d)
+    }
+
     pub fn as_bool(&self) -> Option<bool> {
         let Expr::Bool(b) = self.unpack() else {
             return None;
@@ -677,10 +870,10 @@ impl Expr {
         let expr = self.unpack();
 
         match expr {
-            Expr::Symbol(s) => Some(s),
-            Expr::KeySymbol(s) => Some(s),
+            Expr::Symbol(s) => Some(s.as_str()),
+            Expr::KeySymbol(s) => Some(s.as_str()),
             Expr::String(s) => Some(s),
-            Expr::Type(s) => Some(s),
+            Expr::Type(s) => Some(s.as_str()),
             _ => None,
         }
     }
@@ -691,10 +884,10 @@ impl Expr {
         let expr = self.unpack_consuming();
 
         match expr {
-            Expr::Symbol(s) => Some(s),
-            Expr::KeySymbol(s) => Some(s),
+            Expr::Symbol(s) => Some(s.to_string()),
+            Expr::KeySymbol(s) => Some(s.to_string()),
             Expr::String(s) => Some(s),
-            Expr::Type(s) => Some(s),
+            Expr::Type(s) => Some(s.to_string()),
             _ => None,
         }
     }
@@ -721,14 +914,14 @@ impl Expr {
 
     // #todo can just make this the as_symbol impl.
     /// Tries to extract Symbol or KeySymbol.
-    pub fn as_symbolic(&self) -> Option<&str> {
+    pub fn as_symbolic(&self) -> Option<Symbol> {
         // #todo try to optimize away the unpacks.
         let expr = self.unpack();
 
         match expr {
-            Expr::Symbol(s) => Some(s),
-            Expr::KeySymbol(s) => Some(s),
-            Expr::Type(s) => Some(s),
+            Expr::Symbol(s) => Some(*s),
+            Expr::KeySymbol(s) => Some(*s),
+            Expr::Type(s) => Some(*s),
             _ => None,
         }
     }
@@ -810,34 +1003,55 @@ impl Expr {
         Some((*length, expect_lock_write(v)))
     }
 
-    pub fn as_map(&self) -> Option<RwLockReadGuard<'_, HashMap<String, Expr>>> {
+    pub fn as_map(&self) -> Option<RwLockReadGuard<'_, IndexMap<Expr, Expr>>> {
         let Expr::Map(map) = self.unpack() else {
             return None;
         };
         Some(expect_lock_read(map))
     }
 
-    pub fn as_map_mut(&self) -> Option<RwLockWriteGuard<'_, HashMap<String, Expr>>> {
+    pub fn as_map_mut(&self) -> Option<RwLockWriteGuard<'_, IndexMap<Expr, Expr>>> {
         let Expr::Map(map) = self.unpack() else {
             return None;
         };
         Some(expect_lock_write(map))
     }
 
-    pub fn as_set(&self) -> Option<RwLockReadGuard<'_, HashSet<Expr>>> {
+    pub fn as_set(&self) -> Option<RwLockReadGuard<'_, IndexSet<Expr>>> {
         let Expr::Set(set) = self.unpack() else {
             return None;
         };
         Some(expect_lock_read(set))
     }
 
-    pub fn as_set_mut(&self) -> Option<RwLockWriteGuard<'_, HashSet<Expr>>> {
+    pub fn as_set_mut(&self) -> Option<RwLockWriteGuard<'_, IndexSet<Expr>>> {
         let Expr::Set(set) = self.unpack() else {
             return None;
         };
         Some(expect_lock_write(set))
     }
 
+    pub fn as_vector(&self) -> Option<&Vector<Expr>> {
+        let Expr::Vector(v) = self.unpack() else {
+            return None;
+        };
+        Some(v)
+    }
+
+    pub fn as_persistent_map(&self) -> Option<&PersistentMap> {
+        let Expr::PersistentMap(map) = self.unpack() else {
+            return None;
+        };
+        Some(map)
+    }
+
+    pub fn as_persistent_set(&self) -> Option<&PersistentSet> {
+        let Expr::PersistentSet(set) = self.unpack() else {
+            return None;
+        };
+        Some(set)
+    }
+
     // // #todo consider #[inline]
     // pub fn as_func(&self) -> Option<i64> {
     //     let Expr::Func(params, body, scope, filename) = self.unpack() else {
@@ -861,12 +1075,30 @@ impl Expr {
 
     // #todo we need a version that returns just a string.
 
-    // #todo introduce a version that does not look-through Symbol, no Context required.
     // #todo how about return &Expr to avoid clones?
     // #todo alternatively consider key-symbol instead of String
     // #insight use string for the type to support parameterized types, e.g (Map String Any)
     // Returns the dynamic (eval-time) type of the expression.
     pub fn dyn_type(&self, context: &Context) -> Expr {
+        // #todo what about quoted Symbol?
+        if let Expr::Symbol(name) = self.unpack() {
+            if self.annotation("type").is_none() {
+                // #todo it's weird that we look through symbols.
+                if let Some(value) = context.scope.get(name) {
+                    return value.dyn_type(context);
+                } else {
+                    // #todo could use symbol here!
+                    return Expr::Type(sym!("Unknown"));
+                }
+            }
+        }
+
+        self.type_of()
+    }
+
+    /// Returns the type of the expression, does not look-through Symbols, no
+    /// Context required.
+    pub fn type_of(&self) -> Expr {
         // #todo make constant out of "type".
         if let Some(typ) = self.annotation("type") {
             // #todo why is the unpack needed?
@@ -874,44 +1106,44 @@ impl Expr {
         }
 
         match self.unpack() {
-            Expr::Never => Expr::typ("Never"), // Never, Zero
-            Expr::None => Expr::typ("None"),   // Unit, One, Nil
-            Expr::Bool(_) => Expr::typ("Bool"),
-            Expr::U8(_) => Expr::typ("U8"),
-            Expr::Int(_) => Expr::typ("Int"),
-            Expr::Float(_) => Expr::typ("Float"),
-            Expr::Dec(_) => Expr::typ("Dec"),
-            Expr::Char(_) => Expr::typ("Char"),
-            Expr::String(_) => Expr::typ("String"),
-            Expr::Type(_) => Expr::typ("Type"),
-            Expr::List(_) => Expr::typ("List"), // #todo return parameterized type
-            Expr::Array(_) => Expr::typ("Array"), // #todo return parameterized type
-            Expr::Buffer(..) => Expr::typ("Buffer"), // #todo return parameterized type
-            Expr::Map(_) => Expr::typ("Map"),   // #todo return parameterized type
-            Expr::Set(_) => Expr::typ("Set"),   // #todo return parameterized type
-            // #todo what about quoted Symbol?
-            Expr::Symbol(name) => {
-                // #todo it's weird that we look through symbols.
-                if let Some(value) = context.scope.get(name) {
-                    value.dyn_type(context)
-                } else {
-                    // #todo could use symbol here!
-                    Expr::typ("Unknown")
-                }
-            }
-            Expr::KeySymbol(..) => Expr::typ("KeySymbol"),
+            Expr::Never => Expr::Type(sym!("Never")), // Never, Zero
+            Expr::None => Expr::Type(sym!("None")),   // Unit, One, Nil
+            Expr::Bool(_) => Expr::Type(sym!("Bool")),
+            Expr::U8(_) => Expr::Type(sym!("U8")),
+            Expr::Int(_) => Expr::Type(sym!("Int")),
+            #[cfg(feature = "bigint")]
+            Expr::BigInt(_) => Expr::Type(sym!("BigInt")),
+            Expr::Float(_) => Expr::Type(sym!("Float")),
+            Expr::Dec(_) => Expr::Type(sym!("Dec")),
+            Expr::Ratio(_) => Expr::Type(sym!("Ratio")),
+            Expr::Date(_) => Expr::Type(sym!("Date")),
+            Expr::Instant(_) => Expr::Type(sym!("Instant")),
+            Expr::Duration(_) => Expr::Type(sym!("Duration")),
+            Expr::Char(_) => Expr::Type(sym!("Char")),
+            Expr::String(_) => Expr::Type(sym!("String")),
+            Expr::Type(_) => Expr::Type(sym!("Type")),
+            Expr::List(_) => Expr::Type(sym!("List")), // #todo return parameterized type
+            Expr::Array(_) => Expr::Type(sym!("Array")), // #todo return parameterized type
+            Expr::Buffer(..) => Expr::Type(sym!("Buffer")), // #todo return parameterized type
+            Expr::Map(_) => Expr::Type(sym!("Map")),   // #todo return parameterized type
+            Expr::Set(_) => Expr::Type(sym!("Set")),   // #todo return parameterized type
+            Expr::Vector(_) => Expr::Type(sym!("Vector")), // #todo return parameterized type
+            Expr::PersistentMap(_) => Expr::Type(sym!("PersistentMap")),
+            Expr::PersistentSet(_) => Expr::Type(sym!("PersistentSet")),
+            Expr::Symbol(..) | Expr::LocalSymbol(..) => Expr::Type(sym!("Symbol")),
+            Expr::KeySymbol(..) => Expr::Type(sym!("KeySymbol")),
             // #todo keep the Range type parameter as a ...parameter
-            Expr::IntRange(..) => Expr::typ("(Range Int)"),
-            Expr::FloatRange(..) => Expr::typ("(Range Float)"),
-            Expr::Func(..) => Expr::typ("Func"),
+            Expr::IntRange(..) => Expr::Type(sym!("(Range Int)")),
+            Expr::FloatRange(..) => Expr::Type(sym!("(Range Float)")),
+            Expr::Func(..) => Expr::Type(sym!("Func")),
             // #todo consider returning Func?
-            Expr::ForeignFunc(..) => Expr::typ("ForeignFunc"),
-            Expr::Error(..) => Expr::typ("Error"),
+            Expr::ForeignFunc(..) => Expr::Type(sym!("ForeignFunc")),
+            Expr::Error(..) => Expr::Type(sym!("Error")),
             // #todo add more here!
             // #todo the wildcard is very error-prone, cover all cases!
             _ => {
                 eprintln!("WARNING dyn-type unknown ---> {self:?}");
-                Expr::typ("Unknown")
+                Expr::Type(sym!("Unknown"))
             }
         }
     }
@@ -921,19 +1153,298 @@ impl Expr {
     }
 }
 
+// #insight The From/TryFrom conversions are useful for embedders, e.g. to
+// pass values to/from foreign functions.
+
+impl From<bool> for Expr {
+    fn from(item: bool) -> Self {
+        Expr::Bool(item)
+    }
+}
+
+impl From<u8> for Expr {
+    fn from(item: u8) -> Self {
+        Expr::U8(item)
+    }
+}
+
 impl From<i64> for Expr {
     fn from(item: i64) -> Self {
         Expr::Int(item)
     }
 }
 
+#[cfg(feature = "bigint")]
+impl From<BigInt> for Expr {
+    fn from(item: BigInt) -> Self {
+        Expr::BigInt(item)
+    }
+}
+
 impl From<f64> for Expr {
     fn from(item: f64) -> Self {
         Expr::Float(item)
     }
 }
 
-// #todo impl TryFrom<Expr> for f64, i64, etc.
+impl From<Decimal> for Expr {
+    fn from(item: Decimal) -> Self {
+        Expr::Dec(item)
+    }
+}
+
+impl From<Rational64> for Expr {
+    fn from(item: Rational64) -> Self {
+        Expr::Ratio(item)
+    }
+}
+
+impl From<NaiveDate> for Expr {
+    fn from(item: NaiveDate) -> Self {
+        Expr::Date(item)
+    }
+}
+
+impl From<DateTime<FixedOffset>> for Expr {
+    fn from(item: DateTime<FixedOffset>) -> Self {
+        Expr::Instant(item)
+    }
+}
+
+impl From<TimeDelta> for Expr {
+    fn from(item: TimeDelta) -> Self {
+        Expr::Duration(item)
+    }
+}
+
+impl From<char> for Expr {
+    fn from(item: char) -> Self {
+        Expr::Char(item)
+    }
+}
+
+impl From<String> for Expr {
+    fn from(item: String) -> Self {
+        Expr::String(item)
+    }
+}
+
+impl From<&str> for Expr {
+    fn from(item: &str) -> Self {
+        Expr::String(item.to_owned())
+    }
+}
+
+impl<T: Into<Expr>> From<Vec<T>> for Expr {
+    fn from(item: Vec<T>) -> Self {
+        Expr::array(item.into_iter().map(Into::into).collect::<Vec<Expr>>())
+    }
+}
+
+// #insight String keys are created at runtime, they are kept as Strings and not
+// interned, e.g. `{"name" "George"}`.
+impl<T: Into<Expr>> From<HashMap<String, T>> for Expr {
+    fn from(item: HashMap<String, T>) -> Self {
+        Expr::map(
+            item.into_iter()
+                .map(|(k, v)| (Expr::String(k), v.into()))
+                .collect::<IndexMap<Expr, Expr>>(),
+        )
+    }
+}
+
+impl<T: Into<Expr>> From<HashSet<T>> for Expr {
+    fn from(item: HashSet<T>) -> Self {
+        Expr::set(item.into_iter().map(Into::into).collect::<IndexSet<Expr>>())
+    }
+}
+
+impl<T: Into<Expr>> From<Option<T>> for Expr {
+    fn from(item: Option<T>) -> Self {
+        match item {
+            Some(value) => value.into(),
+            None => Expr::None,
+        }
+    }
+}
+
+// #todo Consider a TryFrom<&Expr> version to avoid clones.
+
+impl TryFrom<Expr> for bool {
+    type Error = Error;
+
+    fn try_from(value: Expr) -> Result<Self, Self::Error> {
+        value
+            .as_bool()
+            .ok_or_else(|| Error::invalid_conversion("Bool", &value))
+    }
+}
+
+impl TryFrom<Expr> for u8 {
+    type Error = Error;
+
+    fn try_from(value: Expr) -> Result<Self, Self::Error> {
+        value
+            .as_u8()
+            .ok_or_else(|| Error::invalid_conversion("U8", &value))
+    }
+}
+
+impl TryFrom<Expr> for i64 {
+    type Error = Error;
+
+    fn try_from(value: Expr) -> Result<Self, Self::Error> {
+        value
+            .as_int()
+            .ok_or_else(|| Error::invalid_conversion("Int", &value))
+    }
+}
+
+#[cfg(feature = "bigint")]
+impl TryFrom<Expr> for BigInt {
+    type Error = Error;
+
+    fn try_from(value: Expr) -> Result<Self, Self::Error> {
+        value
+            .as_big_int()
+            .cloned()
+            .ok_or_else(|| Error::invalid_conversion("BigInt", &value))
+    }
+}
+
+impl TryFrom<Expr> for f64 {
+    type Error = Error;
+
+    fn try_from(value: Expr) -> Result<Self, Self::Error> {
+        value
+            .as_float()
+            .ok_or_else(|| Error::invalid_conversion("Float", &value))
+    }
+}
+
+impl TryFrom<Expr> for Decimal {
+    type Error = Error;
+
+    fn try_from(value: Expr) -> Result<Self, Self::Error> {
+        value
+            .as_decimal()
+            .ok_or_else(|| Error::invalid_conversion("Dec", &value))
+    }
+}
+
+impl TryFrom<Expr> for Rational64 {
+    type Error = Error;
+
+    fn try_from(value: Expr) -> Result<Self, Self::Error> {
+        value
+            .as_ratio()
+            .ok_or_else(|| Error::invalid_conversion("Ratio", &value))
+    }
+}
+
+impl TryFrom<Expr> for NaiveDate {
+    type Error = Error;
+
+    fn try_from(value: Expr) -> Result<Self, Self::Error> {
+        value
+            .as_date()
+            .ok_or_else(|| Error::invalid_conversion("Date", &value))
+    }
+}
+
+impl TryFrom<Expr> for DateTime<FixedOffset> {
+    type Error = Error;
+
+    fn try_from(value: Expr) -> Result<Self, Self::Error> {
+        value
+            .as_instant()
+            .ok_or_else(|| Error::invalid_conversion("Instant", &value))
+    }
+}
+
+impl TryFrom<Expr> for TimeDelta {
+    type Error = Error;
+
+    fn try_from(value: Expr) -> Result<Self, Self::Error> {
+        value
+            .as_duration()
+            .ok_or_else(|| Error::invalid_conversion("Duration", &value))
+    }
+}
+
+impl TryFrom<Expr> for char {
+    type Error = Error;
+
+    fn try_from(value: Expr) -> Result<Self, Self::Error> {
+        value
+            .as_char()
+            .ok_or_else(|| Error::invalid_conversion("Char", &value))
+    }
+}
+
+impl TryFrom<Expr> for String {
+    type Error = Error;
+
+    fn try_from(value: Expr) -> Result<Self, Self::Error> {
+        match value.unpack_consuming() {
+            Expr::String(s) => Ok(s),
+            value => Err(Error::invalid_conversion("String", &value)),
+        }
+    }
+}
+
+impl<T: TryFrom<Expr, Error = Error>> TryFrom<Expr> for Vec<T> {
+    type Error = Error;
+
+    fn try_from(value: Expr) -> Result<Self, Self::Error> {
+        let Some(items) = value.as_array() else {
+            return Err(Error::invalid_conversion("Array", &value));
+        };
+        items.iter().map(|item| T::try_from(item.clone())).collect()
+    }
+}
+
+impl<T: TryFrom<Expr, Error = Error>> TryFrom<Expr> for HashMap<String, T> {
+    type Error = Error;
+
+    fn try_from(value: Expr) -> Result<Self, Self::Error> {
+        let Some(items) = value.as_map() else {
+            return Err(Error::invalid_conversion("Map", &value));
+        };
+        items
+            .iter()
+            .map(|(k, v)| {
+                let Some(key) = k.as_stringable() else {
+                    return Err(Error::invalid_conversion("String", k));
+                };
+                Ok((key.to_owned(), T::try_from(v.clone())?))
+            })
+            .collect()
+    }
+}
+
+impl<T: TryFrom<Expr, Error = Error> + Eq + Hash> TryFrom<Expr> for HashSet<T> {
+    type Error = Error;
+
+    fn try_from(value: Expr) -> Result<Self, Self::Error> {
+        let Some(items) = value.as_set() else {
+            return Err(Error::invalid_conversion("Set", &value));
+        };
+        items.iter().map(|item| T::try_from(item.clone())).collect()
+    }
+}
+
+impl<T: TryFrom<Expr, Error = Error>> TryFrom<Expr> for Option<T> {
+    type Error = Error;
+
+    fn try_from(value: Expr) -> Result<Self, Self::Error> {
+        if let Expr::None = value.unpack() {
+            Ok(None)
+        } else {
+            Ok(Some(T::try_from(value)?))
+        }
+    }
+}
 
 #[must_use]
 pub fn annotate(mut expr: Expr, name: impl Into<String>, ann_expr: Expr) -> Expr {
@@ -954,7 +1465,7 @@ pub fn annotate(mut expr: Expr, name: impl Into<String>, ann_expr: Expr) -> Expr
 // #todo use special sigil for implicit/system annotations.
 
 #[must_use]
-pub fn annotate_type(expr: Expr, type_name: impl Into<String>) -> Expr {
+pub fn annotate_type(expr: Expr, type_name: impl Into<Symbol>) -> Expr {
     // #todo String is not good, we need a symbol/key-symbol that supports spaces.
     annotate(expr, "type", Expr::Type(type_name.into()))
 }
@@ -1006,6 +1517,20 @@ pub fn format_value(expr: impl AsRef<Expr>) -> String {
     }
 }
 
+/// Formats a char as a char literal, e.g. `'a'` or `'\n'`.
+pub fn format_char(c: char) -> String {
+    match c {
+        '\\' => r"'\\'".to_owned(),
+        '\'' => r"'\''".to_owned(),
+        '\n' => r"'\n'".to_owned(),
+        '\t' => r"'\t'".to_owned(),
+        '\r' => r"'\r'".to_owned(),
+        '\0' => r"'\0'".to_owned(),
+        c if c.is_control() => format!("'\\u{{{:x}}}'", c as u32),
+        c => format!("'{c}'"),
+    }
+}
+
 // #todo consider using Arc<Expr> everywhere?
 // #todo proper name
 // #todo proper value/reference handling for all types.
@@ -1015,6 +1540,7 @@ pub fn expr_clone(expr: &Expr) -> Expr {
         // #insight treat Array and Map as a 'reference' types, Arc.clone is efficient.
         Expr::Array(items) => Expr::Array(items.clone()),
         Expr::Map(items) => Expr::Map(items.clone()),
+        // #insight The persistent collections share structure, the clone is O(1).
         _ => expr.clone(),
     }
 }
@@ -1027,26 +1553,26 @@ pub fn expr_clone(expr: &Expr) -> Expr {
 // #todo convert position to Map Expr.
 
 pub fn position_to_expr(position: &Position) -> Expr {
-    let mut map: HashMap<String, Expr> = HashMap::new();
-    map.insert("index".to_owned(), Expr::Int(position.index as i64));
-    map.insert("line".to_owned(), Expr::Int(position.line as i64));
-    map.insert("col".to_owned(), Expr::Int(position.col as i64));
+    let mut map: IndexMap<Expr, Expr> = IndexMap::new();
+    map.insert(Expr::key_symbol("index"), Expr::Int(position.index as i64));
+    map.insert(Expr::key_symbol("line"), Expr::Int(position.line as i64));
+    map.insert(Expr::key_symbol("col"), Expr::Int(position.col as i64));
     Expr::map(map)
 }
 
 pub fn expr_to_position(expr: &Expr) -> Position {
     if let Some(map) = expr.as_map() {
-        let Some(Expr::Int(index)) = map.get("index") else {
+        let Some(Expr::Int(index)) = map.get(&Expr::key_symbol("index")) else {
             // #todo fix me!
             return Position::default();
         };
 
-        let Some(Expr::Int(line)) = map.get("line") else {
+        let Some(Expr::Int(line)) = map.get(&Expr::key_symbol("line")) else {
             // #todo fix me!
             return Position::default();
         };
 
-        let Some(Expr::Int(col)) = map.get("col") else {
+        let Some(Expr::Int(col)) = map.get(&Expr::key_symbol("col")) else {
             // #todo fix me!
             return Position::default();
         };
@@ -1109,7 +1635,14 @@ pub fn is_truthy(expr: &Expr) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use crate::expr::Expr;
+    use std::{cmp::Ordering, collections::HashMap};
+
+    use assert_matches::assert_matches;
+    use chrono::NaiveDate;
+
+    use indexmap::IndexSet;
+
+    use crate::{error::ErrorVariant, expr::Expr};
 
     #[test]
     fn expr_string_display() {
@@ -1128,4 +1661,71 @@ mod tests {
         assert!(Expr::Bool(false).is_false());
         assert!(!Expr::Bool(true).is_false());
     }
+
+    #[test]
+    fn expr_converts_from_and_to_rust_values() {
+        let expr = Expr::from(vec![Some(1_i64), None]);
+        assert_eq!(format!("{expr}"), "[1 ()]");
+
+        let values: Vec<Option<i64>> = expr.try_into().unwrap();
+        assert_eq!(values, vec![Some(1), None]);
+
+        let expr = Expr::from(HashMap::from([("name".to_string(), "tan")]));
+        let map: HashMap<String, String> = expr.try_into().unwrap();
+        assert_eq!(map["name"], "tan");
+
+        assert_eq!(char::try_from(Expr::from('t')).unwrap(), 't');
+        assert!(bool::try_from(Expr::from(true)).unwrap());
+    }
+
+    #[test]
+    fn expr_set_preserves_insertion_order() {
+        let expr = Expr::from(vec![3_i64, 1, 2, 1]);
+        let Expr::Array(items) = expr else {
+            panic!("expected an array");
+        };
+        let set = Expr::set(
+            items
+                .read()
+                .unwrap()
+                .iter()
+                .cloned()
+                .collect::<IndexSet<Expr>>(),
+        );
+        assert_eq!(format!("{set}"), "[3 1 2]");
+    }
+
+    #[test]
+    fn expr_try_into_reports_the_expected_and_found_types() {
+        let error = i64::try_from(Expr::from("hello")).unwrap_err();
+        assert_matches!(
+            &error.variant,
+            ErrorVariant::InvalidConversion(expected, found) if expected == "Int" && found == "String"
+        );
+
+        let error = Vec::<i64>::try_from(Expr::from(vec![1.0_f64])).unwrap_err();
+        assert_matches!(
+            &error.variant,
+            ErrorVariant::InvalidConversion(expected, found) if expected == "Int" && found == "Float"
+        );
+    }
+
+    #[test]
+    fn expr_compares_through_annotations() {
+        let annotations = HashMap::from([("type".to_string(), Expr::typ("Date"))]);
+        let earlier = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let later = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let annotated = Expr::annotated(Expr::Date(later), &annotations);
+
+        assert_eq!(
+            annotated.partial_cmp(&Expr::Date(earlier)),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            Expr::Date(earlier).partial_cmp(&annotated),
+            Some(Ordering::Less)
+        );
+        assert!(Expr::Int(1) < Expr::annotated(Expr::Int(2), &annotations));
+        assert_eq!(annotated.partial_cmp(&Expr::Int(1)), None);
+    }
 }
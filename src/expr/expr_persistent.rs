@@ -0,0 +1,256 @@
+use std::fmt;
+
+use imbl::{HashMap, OrdMap};
+
+use super::Expr;
+
+// #insight
+// The persistent collections share structure between versions, a clone is
+// O(1) and an update is O(log n). The mutable Array/Map/Set variants are still
+// used for explicitly mutable code.
+
+// #insight
+// Insertion order is tracked with a sequence number per entry, the `order` map
+// is sorted by sequence. This keeps the iteration order consistent with
+// Expr::Map, without the O(n) removal of an indexed vector.
+
+// #todo Reuse the sequence numbers of removed entries?
+
+/// An immutable, insertion-ordered map with structural sharing.
+#[derive(Clone, Default)]
+pub struct PersistentMap {
+    entries: HashMap<Expr, (u64, Expr)>,
+    order: OrdMap<u64, Expr>,
+    next_seq: u64,
+}
+
+impl PersistentMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &Expr) -> Option<&Expr> {
+        self.entries.get(key).map(|(_, value)| value)
+    }
+
+    pub fn contains_key(&self, key: &Expr) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Inserts an entry in place, an existing key keeps its position.
+    pub fn insert(&mut self, key: Expr, value: Expr) -> Option<Expr> {
+        if let Some((_, old)) = self.entries.get_mut(&key) {
+            return Some(std::mem::replace(old, value));
+        }
+
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.order.insert(seq, key.clone());
+        self.entries.insert(key, (seq, value));
+        None
+    }
+
+    /// Removes an entry in place.
+    pub fn remove(&mut self, key: &Expr) -> Option<Expr> {
+        let (seq, value) = self.entries.remove(key)?;
+        self.order.remove(&seq);
+        Some(value)
+    }
+
+    /// Returns a new map with the key bound to the value, the original map is
+    /// not modified.
+    pub fn update(&self, key: Expr, value: Expr) -> Self {
+        let mut map = self.clone();
+        map.insert(key, value);
+        map
+    }
+
+    /// Returns a new map without the key, the original map is not modified.
+    pub fn without(&self, key: &Expr) -> Self {
+        let mut map = self.clone();
+        map.remove(key);
+        map
+    }
+
+    /// Iterates the entries in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Expr, &Expr)> {
+        self.order.values().map(|key| (key, &self.entries[key].1))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Expr> {
+        self.order.values()
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Expr> {
+        self.iter().map(|(_, value)| value)
+    }
+}
+
+// #insight Like IndexMap, the equality ignores the insertion order.
+impl PartialEq for PersistentMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl FromIterator<(Expr, Expr)> for PersistentMap {
+    fn from_iter<T: IntoIterator<Item = (Expr, Expr)>>(iter: T) -> Self {
+        let mut map = Self::new();
+        for (key, value) in iter {
+            map.insert(key, value);
+        }
+        map
+    }
+}
+
+impl fmt::Debug for PersistentMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// An immutable, insertion-ordered set with structural sharing.
+#[derive(Clone, Default)]
+pub struct PersistentSet {
+    items: HashMap<Expr, u64>,
+    order: OrdMap<u64, Expr>,
+    next_seq: u64,
+}
+
+impl PersistentSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn contains(&self, item: &Expr) -> bool {
+        self.items.contains_key(item)
+    }
+
+    /// Inserts an item in place, returns false if the item already exists.
+    pub fn insert(&mut self, item: Expr) -> bool {
+        if self.items.contains_key(&item) {
+            return false;
+        }
+
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.order.insert(seq, item.clone());
+        self.items.insert(item, seq);
+        true
+    }
+
+    /// Removes an item in place, returns false if the item does not exist.
+    pub fn remove(&mut self, item: &Expr) -> bool {
+        let Some(seq) = self.items.remove(item) else {
+            return false;
+        };
+        self.order.remove(&seq);
+        true
+    }
+
+    /// Returns a new set that includes the item, the original set is not
+    /// modified.
+    pub fn update(&self, item: Expr) -> Self {
+        let mut set = self.clone();
+        set.insert(item);
+        set
+    }
+
+    /// Returns a new set without the item, the original set is not modified.
+    pub fn without(&self, item: &Expr) -> Self {
+        let mut set = self.clone();
+        set.remove(item);
+        set
+    }
+
+    /// Iterates the items in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = &Expr> {
+        self.order.values()
+    }
+}
+
+impl PartialEq for PersistentSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|item| other.contains(item))
+    }
+}
+
+impl FromIterator<Expr> for PersistentSet {
+    fn from_iter<T: IntoIterator<Item = Expr>>(iter: T) -> Self {
+        let mut set = Self::new();
+        for item in iter {
+            set.insert(item);
+        }
+        set
+    }
+}
+
+impl fmt::Debug for PersistentSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::Expr;
+
+    use super::{PersistentMap, PersistentSet};
+
+    #[test]
+    fn persistent_map_updates_preserve_the_original() {
+        let map: PersistentMap = [
+            (Expr::key_symbol("c"), Expr::Int(1)),
+            (Expr::key_symbol("a"), Expr::Int(2)),
+        ]
+        .into_iter()
+        .collect();
+
+        let updated = map.update(Expr::key_symbol("b"), Expr::Int(3));
+        let updated = updated.update(Expr::key_symbol("c"), Expr::Int(4));
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&Expr::key_symbol("c")), Some(&Expr::Int(1)));
+        assert_eq!(updated.get(&Expr::key_symbol("c")), Some(&Expr::Int(4)));
+
+        let keys: Vec<String> = updated.keys().map(|k| k.to_string()).collect();
+        assert_eq!(keys, [":c", ":a", ":b"]);
+
+        let removed = updated.without(&Expr::key_symbol("a"));
+        assert_eq!(removed.len(), 2);
+        assert_eq!(updated.len(), 3);
+        assert!(!removed.contains_key(&Expr::key_symbol("a")));
+    }
+
+    #[test]
+    fn persistent_set_updates_preserve_the_original() {
+        let set: PersistentSet = [Expr::Int(3), Expr::Int(1)].into_iter().collect();
+
+        let updated = set.update(Expr::Int(2)).update(Expr::Int(3));
+
+        assert_eq!(set.len(), 2);
+        assert_eq!(updated.len(), 3);
+
+        let items: Vec<String> = updated.iter().map(|x| x.to_string()).collect();
+        assert_eq!(items, ["3", "1", "2"]);
+
+        assert!(updated.without(&Expr::Int(1)) != updated);
+        assert!(set.contains(&Expr::Int(1)));
+    }
+}
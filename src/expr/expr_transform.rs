@@ -1,8 +1,9 @@
-use std::collections::HashMap;
+use imbl::Vector;
+use indexmap::IndexMap;
 
 use crate::{context::Context, error::Error, eval::eval, util::expect_lock_read};
 
-use super::Expr;
+use super::{expr_persistent::PersistentMap, Expr};
 
 // #todo these should be functions, not Expr methods!
 
@@ -82,7 +83,7 @@ impl Expr {
             // #todo properly handle array
             (Expr::Map(map), ann) => {
                 // #todo investigate this clone!!!!
-                let map: HashMap<String, Expr> = expect_lock_read(map)
+                let map: IndexMap<Expr, Expr> = expect_lock_read(map)
                     .clone()
                     .into_iter()
                     .map(|(key, value)| (key, value.clone().transform_mut(f)))
@@ -90,6 +91,22 @@ impl Expr {
                 let map = Expr::maybe_annotated(Expr::map(map), ann);
                 f(map)
             }
+            // #insight The persistent collections are iterated without a lock
+            // or a deep copy of the container.
+            (Expr::Vector(terms), ann) => {
+                let terms: Vector<Expr> =
+                    terms.iter().map(|t| t.clone().transform_mut(f)).collect();
+                let vector = Expr::maybe_annotated(Expr::Vector(terms), ann);
+                f(vector)
+            }
+            (Expr::PersistentMap(map), ann) => {
+                let map: PersistentMap = map
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone().transform_mut(f)))
+                    .collect();
+                let map = Expr::maybe_annotated(Expr::PersistentMap(map), ann);
+                f(map)
+            }
             // #todo ARGHHHHHH does not handle Map, Array, etc.
             _ => f(self),
         }
@@ -146,7 +163,7 @@ impl Expr {
             }
             (Expr::Map(map), ann) => {
                 // #todo investigate this clone!!!!
-                let map: HashMap<String, Expr> = expect_lock_read(map)
+                let map: IndexMap<Expr, Expr> = expect_lock_read(map)
                     .clone()
                     .into_iter()
                     .map(|(key, value)| (key, value.quot(context)))
@@ -154,6 +171,17 @@ impl Expr {
 
                 Expr::maybe_annotated(Expr::map(map), ann)
             }
+            (Expr::Vector(terms), ann) => {
+                let terms: Vector<Expr> = terms.iter().map(|t| t.clone().quot(context)).collect();
+                Expr::maybe_annotated(Expr::Vector(terms), ann)
+            }
+            (Expr::PersistentMap(map), ann) => {
+                let map: PersistentMap = map
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone().quot(context)))
+                    .collect();
+                Expr::maybe_annotated(Expr::PersistentMap(map), ann)
+            }
             _ => self,
         }
     }
@@ -0,0 +1,497 @@
+// The canonical formatter (pretty-printer) for Tan source.
+
+// #insight
+// The formatter works on the analysis-mode parse, that keeps comments,
+// annotations and text separators (blank lines). The atoms are printed with
+// their original source text (sliced by range) to preserve escapes, number
+// separators, key-paths, text blocks, etc.
+
+// #insight
+// The line breaks of the source are preserved, the lines are re-indented and
+// the whitespace within the lines is normalized. Only the lists written in a
+// single line that exceed the maximum width are broken. An already formatted
+// source is returned byte-for-byte.
+
+// #insight
+// A line is indented one level per source line that opens the enclosing
+// lists, e.g. `(let f (Func [x]` indents the body once. The closing
+// delimiters at the start of a line are dedented, e.g. `))`.
+
+// #todo Support custom layouts for special forms, e.g. (if ...), (Func ...).
+// #todo Align the values of maps.
+// #todo Consider a Wadler-style document algebra.
+
+use crate::{error::Error, expr::Expr, lexer::Lexer, parser::Parser};
+
+/// The default maximum line width.
+pub const DEFAULT_MAX_WIDTH: usize = 80;
+
+/// The default indentation size, in spaces.
+pub const DEFAULT_INDENT_SIZE: usize = 4;
+
+/// A layout node, a simplified view of the parsed expressions.
+#[derive(Debug)]
+enum Node {
+    Atom(String),
+    Annotation(String),
+    Comment(String),
+    Separator,
+    List {
+        open: char,
+        close: char,
+        items: Vec<Item>,
+        /// The closing delimiter starts a new line in the source.
+        is_closed_on_new_line: bool,
+    },
+    Quote(char, Box<Node>),
+}
+
+impl Node {
+    fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Node::Annotation(..) | Node::Comment(..) | Node::Separator
+        )
+    }
+}
+
+/// An item of a list, or a top-level expression.
+#[derive(Debug)]
+struct Item {
+    node: Node,
+    /// The item starts a new line in the source.
+    is_on_new_line: bool,
+}
+
+/// Formats Tan source text.
+pub struct Formatter {
+    chars: Vec<char>,
+    max_width: usize,
+    indent_size: usize,
+}
+
+impl Formatter {
+    pub fn new(input: impl AsRef<str>) -> Self {
+        Self {
+            chars: input.as_ref().chars().collect(),
+            max_width: DEFAULT_MAX_WIDTH,
+            indent_size: DEFAULT_INDENT_SIZE,
+        }
+    }
+
+    pub fn with_max_width(self, max_width: usize) -> Self {
+        Self { max_width, ..self }
+    }
+
+    pub fn with_indent_size(self, indent_size: usize) -> Self {
+        Self {
+            indent_size,
+            ..self
+        }
+    }
+
+    pub fn format(&self) -> Result<String, Vec<Error>> {
+        let input: String = self.chars.iter().collect();
+
+        let mut lexer = Lexer::new(&input);
+        let tokens = lexer.lex()?;
+
+        let mut parser = Parser::for_analysis(&tokens);
+        let exprs = parser.parse()?;
+
+        let (items, _) = self.to_items(&exprs, 0);
+        let breaks: Vec<bool> = items.iter().map(|item| item.is_on_new_line).collect();
+
+        let mut output = Output::new(self.indent_size);
+        self.write_items(&items, &breaks, &mut output);
+        let mut output = output.finish();
+
+        // #insight The trailing newline is kept, not added.
+        if !output.is_empty() && input.trim_end_matches([' ', '\t']).ends_with('\n') {
+            output.push('\n');
+        }
+
+        Ok(output)
+    }
+
+    /// Returns the original source text of the expression.
+    fn source(&self, expr: &Expr) -> String {
+        if let Some(range) = expr.range() {
+            if range.start.index <= range.end.index && range.end.index <= self.chars.len() {
+                return self.chars[range.start.index..range.end.index]
+                    .iter()
+                    .collect();
+            }
+        }
+        expr.to_string()
+    }
+
+    /// Returns true if the source has a line break between the indices.
+    fn has_line_break(&self, start: usize, end: usize) -> bool {
+        let end = end.min(self.chars.len());
+        start < end && self.chars[start..end].contains(&'\n')
+    }
+
+    /// Converts the expressions to items, `start` is the source index after
+    /// the opening delimiter. Returns the items and the source index after
+    /// the last item.
+    fn to_items(&self, exprs: &[Expr], start: usize) -> (Vec<Item>, usize) {
+        let mut end = start;
+        let mut is_separated = false;
+
+        let items = exprs
+            .iter()
+            .map(|expr| {
+                let node = self.to_node(expr);
+
+                if let Node::Separator = node {
+                    is_separated = true;
+                    return Item {
+                        node,
+                        is_on_new_line: true,
+                    };
+                }
+
+                let mut is_on_new_line = std::mem::take(&mut is_separated);
+                if let Some(range) = expr.range() {
+                    is_on_new_line |= self.has_line_break(end, range.start.index);
+                    end = range.end.index;
+                }
+
+                Item {
+                    node,
+                    is_on_new_line,
+                }
+            })
+            .collect();
+
+        (items, end)
+    }
+
+    fn to_list(&self, expr: &Expr, open: char, items: &[Expr]) -> Node {
+        let close = match open {
+            '(' => ')',
+            '[' => ']',
+            _ => '}',
+        };
+
+        let range = expr.range();
+        let start = range.as_ref().map_or(0, |range| range.start.index + 1);
+        let (items, end) = self.to_items(items, start);
+        let is_closed_on_new_line =
+            range.is_some_and(|range| self.has_line_break(end, range.end.index - 1));
+
+        Node::List {
+            open,
+            close,
+            items,
+            is_closed_on_new_line,
+        }
+    }
+
+    fn to_node(&self, expr: &Expr) -> Node {
+        let source = self.source(expr);
+
+        match expr.unpack() {
+            Expr::Comment(..) => Node::Comment(source.trim_end().to_string()),
+            Expr::TextSeparator => Node::Separator,
+            Expr::Annotation(..) => Node::Annotation(source),
+            Expr::List(items) => match source.chars().next() {
+                Some('(') => self.to_list(expr, '(', items),
+                // #insight skip the implicit `Array` and `Map` heads.
+                Some(open @ ('[' | '{')) => self.to_list(expr, open, &items[1..]),
+                Some(prefix @ ('\'' | '$')) if items.len() == 2 => {
+                    Node::Quote(prefix, Box::new(self.to_node(&items[1])))
+                }
+                // e.g. desugared key-paths.
+                _ => Node::Atom(source),
+            },
+            _ => Node::Atom(source),
+        }
+    }
+
+    /// Returns the single-line representation of the node, if the node is
+    /// written in a single line in the source.
+    fn flat(&self, node: &Node) -> Option<String> {
+        match node {
+            Node::Atom(text) | Node::Annotation(text) => {
+                if text.contains('\n') {
+                    None
+                } else {
+                    Some(text.clone())
+                }
+            }
+            Node::Comment(..) | Node::Separator => None,
+            Node::List {
+                open,
+                close,
+                items,
+                is_closed_on_new_line,
+            } => {
+                if *is_closed_on_new_line || items.iter().any(|item| item.is_on_new_line) {
+                    return None;
+                }
+                let items = items
+                    .iter()
+                    .map(|item| self.flat(&item.node))
+                    .collect::<Option<Vec<String>>>()?;
+                Some(format!("{open}{}{close}", items.join(" ")))
+            }
+            Node::Quote(prefix, node) => Some(format!("{prefix}{}", self.flat(node)?)),
+        }
+    }
+
+    fn write(&self, node: &Node, output: &mut Output) {
+        match node {
+            Node::Atom(text) | Node::Annotation(text) | Node::Comment(text) => {
+                output.push_str(text)
+            }
+            Node::Separator => {}
+            Node::Quote(prefix, node) => {
+                output.push(*prefix);
+                self.write(node, output);
+            }
+            Node::List {
+                open,
+                close,
+                items,
+                is_closed_on_new_line,
+            } => {
+                // #insight The line breaks of the source are kept, a single
+                // line list is broken only if it's too wide.
+                let column = output.column();
+                let (breaks, is_closed_on_new_line) = match self.flat(node) {
+                    Some(flat) if column + flat.chars().count() <= self.max_width => {
+                        (vec![false; items.len()], false)
+                    }
+                    Some(_) => self.layout(*open, items, column),
+                    None => (
+                        items.iter().map(|item| item.is_on_new_line).collect(),
+                        *is_closed_on_new_line,
+                    ),
+                };
+
+                output.open(*open);
+                self.write_items(items, &breaks, output);
+                if is_closed_on_new_line {
+                    output.newline(false);
+                }
+                output.close(*close);
+            }
+        }
+    }
+
+    /// Writes the items of a list (or the top-level expressions), an item
+    /// starts a new line if the corresponding break is set.
+    fn write_items(&self, items: &[Item], breaks: &[bool], output: &mut Output) {
+        let mut is_started = false;
+        let mut is_separated = false;
+
+        for (item, is_on_new_line) in items.iter().zip(breaks) {
+            if let Node::Separator = item.node {
+                // #insight Leading and trailing separators are dropped.
+                is_separated = is_started;
+                continue;
+            }
+
+            if *is_on_new_line {
+                output.newline(is_separated);
+            } else if is_started {
+                output.push(' ');
+            }
+
+            self.write(&item.node, output);
+
+            is_started = true;
+            is_separated = false;
+        }
+    }
+
+    /// Breaks a single line list that is too wide, starting at `column`.
+    /// Returns the line breaks of the items, and of the closing delimiter.
+    fn layout(&self, open: char, items: &[Item], column: usize) -> (Vec<bool>, bool) {
+        let mut breaks = vec![false; items.len()];
+        let mut column = column + 1;
+        let mut is_opening_line = true;
+        let mut is_key = true;
+
+        let mut i = 0;
+
+        while i < items.len() {
+            let (group, next) = self.group(items, i);
+
+            match open {
+                // #insight In (...) lists the head stays in the opening line.
+                '(' if i == 0 => column += self.flat_width(&group),
+                '(' if is_opening_line => {
+                    let width = self.flat_width(&group);
+                    if group.iter().all(is_simple) && column + 1 + width <= self.max_width {
+                        // Keep the group in the opening line.
+                        column += 1 + width;
+                    } else if next == items.len() && group.len() == 1 && is_block(group[0]) {
+                        // #insight The last block 'hangs' in the opening line.
+                        return (breaks, false);
+                    } else {
+                        is_opening_line = false;
+                        breaks[i] = true;
+                    }
+                }
+                // In {...} lists, the key-value pairs are written in separate lines.
+                '{' => {
+                    breaks[i] = is_key;
+                    is_key = !is_key;
+                }
+                // In [...] lists, all items are written in separate lines.
+                _ => breaks[i] = true,
+            }
+
+            i = next;
+        }
+
+        (breaks, true)
+    }
+
+    /// Returns the nodes of the group that starts at index `i`, and the index
+    /// of the next item. A group is an expression with the preceding
+    /// annotations.
+    fn group<'a>(&self, items: &'a [Item], i: usize) -> (Vec<&'a Node>, usize) {
+        let mut group = Vec::new();
+        let mut j = i;
+        while let Some(
+            item @ Item {
+                node: Node::Annotation(..),
+                ..
+            },
+        ) = items.get(j)
+        {
+            group.push(&item.node);
+            j += 1;
+        }
+        if let Some(item) = items.get(j) {
+            if !item.node.is_transient() {
+                group.push(&item.node);
+                j += 1;
+            }
+        }
+        (group, j)
+    }
+
+    fn flat_width(&self, group: &[&Node]) -> usize {
+        let widths = group
+            .iter()
+            .map(|node| self.flat(node).map_or(0, |flat| flat.chars().count()));
+        widths.sum::<usize>() + group.len().saturating_sub(1)
+    }
+}
+
+/// The formatted output, tracks the lines that open the enclosing lists.
+struct Output {
+    text: String,
+    indent_size: usize,
+    line: usize,
+    /// The lines of the open lists, in opening order.
+    open_lines: Vec<usize>,
+    /// The start of the current line, if it's not indented yet. The
+    /// indentation is deferred after the closing delimiters at the start of
+    /// the line.
+    line_start: Option<usize>,
+}
+
+impl Output {
+    fn new(indent_size: usize) -> Self {
+        Self {
+            text: String::new(),
+            indent_size,
+            line: 0,
+            open_lines: Vec::new(),
+            line_start: None,
+        }
+    }
+
+    /// Indents the current line, one level per line that opens the enclosing
+    /// lists.
+    fn indent(&mut self) {
+        if let Some(start) = self.line_start.take() {
+            let mut lines = self.open_lines.clone();
+            lines.dedup();
+            let indent = " ".repeat(lines.len() * self.indent_size);
+            self.text.insert_str(start, &indent);
+        }
+    }
+
+    fn push(&mut self, ch: char) {
+        self.indent();
+        self.text.push(ch);
+    }
+
+    fn push_str(&mut self, text: &str) {
+        self.indent();
+        self.text.push_str(text);
+        self.line += text.matches('\n').count();
+    }
+
+    fn open(&mut self, ch: char) {
+        self.push(ch);
+        self.open_lines.push(self.line);
+    }
+
+    fn close(&mut self, ch: char) {
+        self.open_lines.pop();
+        self.text.push(ch);
+    }
+
+    fn newline(&mut self, is_separated: bool) {
+        if self.text.is_empty() {
+            return;
+        }
+        // #insight The empty lines are not indented.
+        if self.line_start.is_some_and(|start| start < self.text.len()) {
+            self.indent();
+        }
+        if is_separated {
+            self.text.push('\n');
+            self.line += 1;
+        }
+        self.text.push('\n');
+        self.line += 1;
+        self.line_start = Some(self.text.len());
+    }
+
+    fn column(&mut self) -> usize {
+        self.indent();
+        match self.text.rfind('\n') {
+            Some(i) => self.text[(i + 1)..].chars().count(),
+            None => self.text.chars().count(),
+        }
+    }
+
+    fn finish(mut self) -> String {
+        if self.line_start.is_some_and(|start| start < self.text.len()) {
+            self.indent();
+        }
+        self.text
+    }
+}
+
+/// Formats Tan source text, with the default options.
+pub fn format_string(input: impl AsRef<str>) -> Result<String, Vec<Error>> {
+    Formatter::new(input).format()
+}
+
+fn is_simple(node: &&Node) -> bool {
+    match node {
+        Node::Atom(..) | Node::Annotation(..) => true,
+        Node::Quote(_, node) => is_simple(&node.as_ref()),
+        _ => false,
+    }
+}
+
+fn is_block(node: &Node) -> bool {
+    matches!(
+        node,
+        Node::List {
+            open: '[' | '{',
+            ..
+        }
+    )
+}
@@ -0,0 +1,504 @@
+// Helpers for registering Rust (foreign) functions.
+
+// #insight
+// The foreign functions are registered as multi-methods, with a mangled name
+// computed from the types of the arguments, e.g. `add$$Int$$Int`, so that
+// `resolve_op_method` can dispatch on the dynamic signature.
+
+// #insight
+// The registered closures must be `'static`, the foreign function is stored
+// in an Expr that can outlive any borrow, e.g. when it's bound to a name or
+// returned as a value. Share state with a closure through an `Arc`, or use
+// `Context::scoped_register` for a closure that borrows.
+
+// #todo Support variadic functions.
+// #todo Support Expr arguments (`$$*` signature).
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeDelta};
+#[cfg(feature = "bigint")]
+use num_bigint::BigInt;
+use num_rational::Rational64;
+use rust_decimal::Decimal;
+
+use crate::{
+    context::Context,
+    error::Error,
+    expr::{Expr, FnMutContext},
+    scope::Scope,
+    symbol::Symbol,
+};
+
+/// A Rust type that corresponds to a Tan type.
+pub trait TanType {
+    /// The name of the Tan type, used to compute method signatures.
+    fn tan_type() -> &'static str;
+}
+
+macro_rules! impl_tan_type {
+    ($($typ:ty => $name:literal),* $(,)?) => {
+        $(
+            impl TanType for $typ {
+                fn tan_type() -> &'static str {
+                    $name
+                }
+            }
+        )*
+    };
+}
+
+impl_tan_type! {
+    bool => "Bool",
+    u8 => "U8",
+    i64 => "Int",
+    f64 => "Float",
+    Decimal => "Dec",
+    Rational64 => "Ratio",
+    NaiveDate => "Date",
+    DateTime<FixedOffset> => "Instant",
+    TimeDelta => "Duration",
+    char => "Char",
+    String => "String",
+}
+
+#[cfg(feature = "bigint")]
+impl_tan_type! {
+    BigInt => "BigInt",
+}
+
+// #todo Consider parameterized types, e.g. (Array Int).
+
+impl<T> TanType for Vec<T> {
+    fn tan_type() -> &'static str {
+        "Array"
+    }
+}
+
+impl<T> TanType for HashMap<String, T> {
+    fn tan_type() -> &'static str {
+        "Map"
+    }
+}
+
+impl<T> TanType for HashSet<T> {
+    fn tan_type() -> &'static str {
+        "Set"
+    }
+}
+
+/// A value that can be returned from a foreign function.
+pub trait IntoExprResult {
+    fn into_expr_result(self) -> Result<Expr, Error>;
+}
+
+impl IntoExprResult for Expr {
+    fn into_expr_result(self) -> Result<Expr, Error> {
+        Ok(self)
+    }
+}
+
+impl IntoExprResult for () {
+    fn into_expr_result(self) -> Result<Expr, Error> {
+        Ok(Expr::None)
+    }
+}
+
+macro_rules! impl_into_expr_result {
+    ($($typ:ty),* $(,)?) => {
+        $(
+            impl IntoExprResult for $typ {
+                fn into_expr_result(self) -> Result<Expr, Error> {
+                    Ok(self.into())
+                }
+            }
+        )*
+    };
+}
+
+impl_into_expr_result!(
+    bool,
+    u8,
+    i64,
+    f64,
+    Decimal,
+    Rational64,
+    NaiveDate,
+    DateTime<FixedOffset>,
+    TimeDelta,
+    char,
+    String,
+    &str,
+);
+
+#[cfg(feature = "bigint")]
+impl_into_expr_result!(BigInt);
+
+impl<T: Into<Expr>> IntoExprResult for Vec<T> {
+    fn into_expr_result(self) -> Result<Expr, Error> {
+        Ok(self.into())
+    }
+}
+
+impl<T: Into<Expr>> IntoExprResult for HashMap<String, T> {
+    fn into_expr_result(self) -> Result<Expr, Error> {
+        Ok(self.into())
+    }
+}
+
+impl<T: Into<Expr>> IntoExprResult for HashSet<T> {
+    fn into_expr_result(self) -> Result<Expr, Error> {
+        Ok(self.into())
+    }
+}
+
+impl<T: Into<Expr>> IntoExprResult for Option<T> {
+    fn into_expr_result(self) -> Result<Expr, Error> {
+        Ok(self.into())
+    }
+}
+
+impl<T: IntoExprResult> IntoExprResult for Result<T, Error> {
+    fn into_expr_result(self) -> Result<Expr, Error> {
+        self?.into_expr_result()
+    }
+}
+
+/// A foreign function that may borrow from the environment `'env`.
+pub type ScopedFnMutContext<'env> =
+    dyn Fn(&[Expr], &mut Context) -> Result<Expr, Error> + Send + Sync + 'env;
+
+/// A Rust closure that can be converted to a foreign function. The `Args`
+/// type parameter is the tuple of the argument types.
+///
+/// A registered closure must be `'static`, a closure that borrows is rejected:
+///
+/// ```compile_fail
+/// # use tan::context::Context;
+/// let context = Context::new();
+/// let offset = 1;
+/// let offset = &offset;
+/// context.register("inc", |a: i64| a + *offset);
+/// ```
+pub trait IntoForeignFunc<Args> {
+    /// Returns the method signature, e.g. `Int$$Int`.
+    fn signature() -> String;
+
+    /// Converts the closure to a foreign function that lives as long as the
+    /// closure.
+    fn into_scoped_foreign_func<'env>(self) -> Box<ScopedFnMutContext<'env>>
+    where
+        Self: 'env;
+
+    fn into_foreign_func(self) -> Arc<FnMutContext>
+    where
+        Self: Sized + 'static,
+    {
+        Arc::from(self.into_scoped_foreign_func())
+    }
+}
+
+/// A Rust closure that accepts the Context, as the first argument, and can be
+/// converted to a foreign function. The `Args` type parameter is the tuple of
+/// the argument types, excluding the Context.
+pub trait IntoContextForeignFunc<Args> {
+    /// Returns the method signature, e.g. `Int$$Int`.
+    fn signature() -> String;
+
+    fn into_foreign_func(self) -> Arc<FnMutContext>;
+}
+
+/// Checks the arity of a foreign function invocation.
+fn check_arity(args: &[Expr], arity: usize) -> Result<(), Error> {
+    if args.len() != arity {
+        return Err(Error::invalid_arguments(
+            &format!("expected {arity} arguments, found {}", args.len()),
+            None,
+        ));
+    }
+    Ok(())
+}
+
+macro_rules! impl_into_foreign_func {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg,)*> IntoForeignFunc<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R + Send + Sync,
+            R: IntoExprResult + 'static,
+            $($arg: TryFrom<Expr, Error = Error> + TanType + 'static,)*
+        {
+            fn signature() -> String {
+                let types: &[&str] = &[$($arg::tan_type()),*];
+                types.join("$$")
+            }
+
+            #[allow(unused_mut, unused_variables)]
+            fn into_scoped_foreign_func<'env>(self) -> Box<ScopedFnMutContext<'env>>
+            where
+                Self: 'env,
+            {
+                Box::new(move |args: &[Expr], _context: &mut Context| {
+                    let types: &[&str] = &[$($arg::tan_type()),*];
+                    check_arity(args, types.len())?;
+
+                    let mut args = args.iter();
+                    self($($arg::try_from(args.next().unwrap().unpack().clone())?),*)
+                        .into_expr_result()
+                })
+            }
+        }
+
+        impl<F, R, $($arg,)*> IntoContextForeignFunc<($($arg,)*)> for F
+        where
+            F: Fn(&mut Context, $($arg),*) -> R + Send + Sync + 'static,
+            R: IntoExprResult,
+            $($arg: TryFrom<Expr, Error = Error> + TanType,)*
+        {
+            fn signature() -> String {
+                let types: &[&str] = &[$($arg::tan_type()),*];
+                types.join("$$")
+            }
+
+            #[allow(unused_mut, unused_variables)]
+            fn into_foreign_func(self) -> Arc<FnMutContext> {
+                Arc::new(move |args: &[Expr], context: &mut Context| {
+                    let types: &[&str] = &[$($arg::tan_type()),*];
+                    check_arity(args, types.len())?;
+
+                    let mut args = args.iter();
+                    self(context, $($arg::try_from(args.next().unwrap().unpack().clone())?),*)
+                        .into_expr_result()
+                })
+            }
+        }
+    };
+}
+
+impl_into_foreign_func!();
+impl_into_foreign_func!(A1);
+impl_into_foreign_func!(A1, A2);
+impl_into_foreign_func!(A1, A2, A3);
+impl_into_foreign_func!(A1, A2, A3, A4);
+impl_into_foreign_func!(A1, A2, A3, A4, A5);
+impl_into_foreign_func!(A1, A2, A3, A4, A5, A6);
+
+/// Registers a foreign function in the scope, under the mangled name
+/// computed from the argument types.
+pub fn register_foreign_func<Args, F: IntoForeignFunc<Args> + 'static>(
+    scope: &Scope,
+    name: impl AsRef<str>,
+    func: F,
+) -> Option<Arc<Expr>> {
+    let name = format!("{}$${}", name.as_ref(), F::signature());
+    scope.insert_invocable(name, Expr::foreign_func_shared(func.into_foreign_func()))
+}
+
+/// Registers a foreign function that accepts the Context in the scope, under
+/// the mangled name computed from the argument types.
+pub fn register_context_foreign_func<Args, F: IntoContextForeignFunc<Args>>(
+    scope: &Scope,
+    name: impl AsRef<str>,
+    func: F,
+) -> Option<Arc<Expr>> {
+    let name = format!("{}$${}", name.as_ref(), F::signature());
+    scope.insert_invocable(name, Expr::foreign_func_shared(func.into_foreign_func()))
+}
+
+// #insight
+// A scoped foreign function borrows from the environment, it is revoked before
+// the environment ends, see `Context::scoped_register`. The Expr that wraps it
+// can still outlive the environment, a revoked function reports an error.
+
+type ScopedSlot = Arc<RwLock<Option<Box<ScopedFnMutContext<'static>>>>>;
+
+/// Revokes a scoped foreign function, and restores the shadowed bindings, when
+/// dropped.
+pub(crate) struct ScopedRegistration {
+    slot: ScopedSlot,
+    scope: Arc<Scope>,
+    /// The shadowed bindings, `None` if the name was not bound.
+    bindings: Vec<(Symbol, Option<Arc<Expr>>)>,
+}
+
+impl Drop for ScopedRegistration {
+    fn drop(&mut self) {
+        // #insight The write lock waits for the pending invocations.
+        self.slot
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+
+        for (name, value) in self.bindings.drain(..) {
+            match value {
+                Some(value) => self.scope.insert(name, value),
+                None => self.scope.remove(name),
+            };
+        }
+    }
+}
+
+/// Registers a foreign function that may borrow from the environment `'env`,
+/// under the mangled name computed from the argument types. The function is
+/// revoked when the returned registration is dropped.
+///
+/// The registration must be dropped before the end of `'env`, i.e. it should
+/// not be leaked.
+pub(crate) fn register_scoped_foreign_func<'env, Args, F: IntoForeignFunc<Args> + 'env>(
+    scope: &Arc<Scope>,
+    name: impl AsRef<str>,
+    func: F,
+) -> ScopedRegistration {
+    let func = func.into_scoped_foreign_func();
+    // SAFETY: The function is only invoked while it's in the slot, under the
+    // read lock. The caller drops the registration before the end of 'env,
+    // this takes the function out of the slot, after the pending invocations.
+    let func = unsafe {
+        std::mem::transmute::<Box<ScopedFnMutContext<'env>>, Box<ScopedFnMutContext<'static>>>(func)
+    };
+    let slot: ScopedSlot = Arc::new(RwLock::new(Some(func)));
+
+    let name = name.as_ref();
+    let method_name = Symbol::from(format!("{name}$${}", F::signature()));
+
+    let mut bindings = Vec::new();
+    for name in [Symbol::from(name), method_name] {
+        let value = scope
+            .bindings
+            .read()
+            .expect("poisoned lock")
+            .get(&name)
+            .cloned();
+        bindings.push((name, value));
+    }
+
+    let invoked_slot = slot.clone();
+    let invoked_name = method_name;
+    let func = move |args: &[Expr], context: &mut Context| {
+        let slot = invoked_slot.read().unwrap_or_else(PoisonError::into_inner);
+        let Some(func) = slot.as_ref() else {
+            return Err(Error::invalid_arguments(
+                &format!("the scoped function `{invoked_name}` is revoked"),
+                None,
+            ));
+        };
+        func(args, context)
+    };
+    scope.insert_invocable(method_name, Expr::foreign_func_shared(Arc::new(func)));
+
+    ScopedRegistration {
+        slot,
+        scope: scope.clone(),
+        bindings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    };
+
+    use assert_matches::assert_matches;
+
+    use crate::{
+        api::eval_string, context::Context, error::Error, expr::Expr,
+        module::loader::InMemoryModuleLoader,
+    };
+
+    #[test]
+    fn register_dispatches_on_the_argument_types() {
+        let mut context = Context::with_module_loader(Arc::new(InMemoryModuleLoader::new()));
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let int_calls = calls.clone();
+        context.register("add", move |a: i64, b: i64| {
+            int_calls.fetch_add(1, Ordering::Relaxed);
+            a + b
+        });
+        context.register("add", |a: f64, b: f64| a + b);
+        context.register("div", |a: i64, b: i64| {
+            if b == 0 {
+                Err(Error::invalid_arguments("division by zero", None))
+            } else {
+                Ok(a / b)
+            }
+        });
+
+        assert!(context.scope.get("add$$Int$$Int").is_some());
+
+        let value = eval_string("(add 1 2)", &mut context).unwrap();
+        assert_matches!(value.unpack(), Expr::Int(3));
+
+        let value = eval_string("(add 1.5 2.0)", &mut context).unwrap();
+        assert_matches!(value.unpack(), Expr::Float(n) if *n == 3.5);
+
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+        assert!(eval_string("(div 1 0)", &mut context).is_err());
+        assert!(eval_string("(add 1 2.0)", &mut context).is_err());
+    }
+
+    #[test]
+    fn register_with_context_passes_the_context() {
+        let mut context = Context::with_module_loader(Arc::new(InMemoryModuleLoader::new()));
+        context.register_with_context("defined?", |context: &mut Context, name: String| {
+            context.scope.get(name).is_some()
+        });
+        context.register_with_context("define!", |context: &mut Context, name: String| {
+            context.scope.insert(name, Expr::Int(1));
+        });
+
+        assert!(context.scope.get("defined?$$String").is_some());
+
+        let value = eval_string("(let a 1) (defined? \"a\")", &mut context).unwrap();
+        assert_matches!(value.unpack(), Expr::Bool(true));
+
+        let value = eval_string("(defined? \"b\")", &mut context).unwrap();
+        assert_matches!(value.unpack(), Expr::Bool(false));
+
+        let value = eval_string("(define! \"c\") c", &mut context).unwrap();
+        assert_matches!(value.unpack(), Expr::Int(1));
+    }
+
+    #[test]
+    fn scoped_register_accepts_borrowing_closures() {
+        let mut context = Context::with_module_loader(Arc::new(InMemoryModuleLoader::new()));
+
+        let log = Mutex::new(Vec::new());
+        let offset = 10;
+        let offset = &offset;
+
+        let value = context.scoped_register(
+            "inc",
+            |a: i64| {
+                log.lock().unwrap().push(a);
+                a + *offset
+            },
+            |context| eval_string("(let f inc) (inc 1)", context),
+        );
+        assert_matches!(value.unwrap().unpack(), Expr::Int(11));
+        assert_eq!(*log.lock().unwrap(), vec![1]);
+
+        // The function is revoked, also if it escaped the scope.
+        assert!(context.scope.get("inc").is_none());
+        assert!(context.scope.get("inc$$Int").is_none());
+        assert!(eval_string("(f 1)", &mut context).is_err());
+        assert_eq!(*log.lock().unwrap(), vec![1]);
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn register_supports_big_int_arguments() {
+        use num_bigint::BigInt;
+
+        let mut context = Context::with_module_loader(Arc::new(InMemoryModuleLoader::new()));
+        context.register("mul", |a: BigInt, b: BigInt| a * b);
+
+        let value = eval_string("(mul 9223372036854775808 2n)", &mut context).unwrap();
+        assert_matches!(value.unpack(), Expr::BigInt(n) if n.to_string() == "18446744073709551616");
+    }
+}
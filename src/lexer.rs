@@ -6,7 +6,7 @@ use std::str::Chars;
 use crate::{
     error::{Error, ErrorVariant},
     range::{Position, Range},
-    util::is_range_literal,
+    util::{is_named_float_literal, is_range_literal},
 };
 
 use self::{
@@ -40,6 +40,18 @@ fn is_eol(ch: char) -> bool {
     ch == '\n'
 }
 
+/// Returns the char of a single-character escape code, e.g. `n` for `\n`.
+fn escaped_char(ch: char) -> Option<char> {
+    match ch {
+        '\\' | '"' | '\'' => Some(ch),
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        'r' => Some('\r'),
+        '0' => Some('\0'),
+        _ => None,
+    }
+}
+
 // #todo stateful lexer vs buffer
 
 // #insight
@@ -208,13 +220,20 @@ impl<'a> Lexer<'a> {
         line
     }
 
-    // #todo support 'raw' strings, e.g. (write #raw "this is \ cool")
+    /// Returns the position of the `\` character that was just consumed, used
+    /// as the start of escape code ranges.
+    fn escape_start_position(&self) -> Position {
+        Position {
+            index: self.current_position.index - 1,
+            col: self.current_position.col - 1,
+            ..self.current_position
+        }
+    }
+
     /// Scans a string lexeme.
     fn scan_string(&mut self) -> Option<String> {
         let mut string = String::new();
 
-        let mut is_escaping = false;
-
         loop {
             let Some(ch) = self.next_char() else {
                 let mut error = Error::new(ErrorVariant::UnterminatedString);
@@ -226,62 +245,32 @@ impl<'a> Lexer<'a> {
                 return None;
             };
 
-            if is_escaping {
-                // #todo support escaping more than one char
-                // #todo add additional escape sequences.
-                // #todo support \xHH
-                // #todo support \u{HHHH}: Represents a 16-bit Unicode code point (where HHHH is up to four hex digits)
-                // #todo support \U{HHHHHHHH}: Represents a 32-bit Unicode code point (where HHHHHHHH is up to eight hex digits)
+            if ch == '\\' {
+                let escape_start = self.escape_start_position();
+
+                let Some(ch) = self.next_char() else {
+                    continue;
+                };
+
                 match ch {
-                    '\\' | '"' => string.push(ch),
-                    'n' => string.push('\n'),
-                    't' => string.push('\t'),
-                    'x' => {
-                        // #todo streamline this or extract
-                        // an escaped byte, in hexadecimal notation, read two
-                        // characters.
-                        let Some(chars) = self.scan_chars(2) else {
-                            let mut error = Error::new(ErrorVariant::MalformedEscapeCode);
-                            error.push_note(
-                                "the \\x escape code requires two characters",
-                                Some(self.current_range()),
-                            ); // #todo refine the text.
-                            self.errors.push(error);
-                            return None;
-                        };
-                        let code_point = u32::from_str_radix(&chars, 16);
-                        let Ok(code_point) = u32::from_str_radix(&chars, 16) else {
-                            let mut error = Error::new(ErrorVariant::MalformedEscapeCode);
-                            error.push_note(
-                                &format!(
-                                    "invalid \\x escape code point: {}",
-                                    code_point.unwrap_err()
-                                ),
-                                Some(self.current_range()),
-                            ); // #todo refine the text.
-                            self.errors.push(error);
-                            return None;
-                        };
-                        let Some(ch) = char::from_u32(code_point) else {
-                            let mut error = Error::new(ErrorVariant::MalformedEscapeCode);
-                            error.push_note(
-                                "invalid \\x escape code point",
-                                Some(self.current_range()),
-                            ); // #todo refine the text.
-                            self.errors.push(error);
-                            return None;
-                        };
-                        string.push(ch);
+                    'x' => string.push(self.scan_hex_escape_code(escape_start)?),
+                    'u' => string.push(self.scan_unicode_escape_code(escape_start)?),
+                    ch if is_eol(ch) => {
+                        // A line continuation, the EOL and the leading
+                        // whitespace of the next line are skipped.
+                        self.current_position.line += 1;
+                        self.current_position.col = 0;
+                        self.skip_line_indentation();
+                    }
+                    ch => {
+                        if let Some(ch) = escaped_char(ch) {
+                            string.push(ch);
+                        } else {
+                            string.push_str(&format!("\\{ch}")); //#todo what to do here?
+                        }
                     }
-                    _ => string.push_str(&format!("\\{ch}")), //#todo what to do here?
                 }
 
-                is_escaping = false;
-                continue;
-            }
-
-            if ch == '\\' {
-                is_escaping = true;
                 continue;
             }
 
@@ -289,16 +278,175 @@ impl<'a> Lexer<'a> {
                 break;
             }
 
+            if is_eol(ch) {
+                self.current_position.line += 1;
+                self.current_position.col = 0;
+            }
+
             string.push(ch);
         }
 
         Some(string)
     }
 
+    /// Skips the leading whitespace of a line, the EOL is not skipped.
+    fn skip_line_indentation(&mut self) {
+        while let Some(ch) = self.next_char() {
+            if !is_whitespace(ch) || is_eol(ch) {
+                self.put_back_char(ch);
+                break;
+            }
+        }
+    }
+
+    /// Scans an escaped byte, in hexadecimal notation, e.g. `\x1b`. The `\x`
+    /// prefix is already consumed, `start` is the position of the `\`.
+    fn scan_hex_escape_code(&mut self, start: Position) -> Option<char> {
+        // #todo streamline this
+        // Read two characters.
+        let Some(chars) = self.scan_chars(2) else {
+            let mut error = Error::new(ErrorVariant::MalformedEscapeCode);
+            error.push_note(
+                "the \\x escape code requires two characters",
+                Some(start..self.current_position),
+            ); // #todo refine the text.
+            self.errors.push(error);
+            return None;
+        };
+        let code_point = u32::from_str_radix(&chars, 16);
+        let Ok(code_point) = u32::from_str_radix(&chars, 16) else {
+            let mut error = Error::new(ErrorVariant::MalformedEscapeCode);
+            error.push_note(
+                &format!("invalid \\x escape code point: {}", code_point.unwrap_err()),
+                Some(start..self.current_position),
+            ); // #todo refine the text.
+            self.errors.push(error);
+            return None;
+        };
+        let Some(ch) = char::from_u32(code_point) else {
+            let mut error = Error::new(ErrorVariant::MalformedEscapeCode);
+            error.push_note(
+                "invalid \\x escape code point",
+                Some(start..self.current_position),
+            ); // #todo refine the text.
+            self.errors.push(error);
+            return None;
+        };
+        Some(ch)
+    }
+
+    /// Scans a Unicode code point escape, e.g. `\u{1F600}`, up to six
+    /// hexadecimal digits. The `\u` prefix is already consumed, `start` is the
+    /// position of the `\`.
+    fn scan_unicode_escape_code(&mut self, start: Position) -> Option<char> {
+        let mut digits = String::new();
+
+        let mut is_closed = false;
+
+        if self.next_char() == Some('{') {
+            while let Some(ch) = self.next_char() {
+                if ch == '}' {
+                    is_closed = true;
+                    break;
+                }
+                digits.push(ch);
+                if digits.len() > 6 {
+                    break;
+                }
+            }
+        }
+
+        let code_point = if is_closed && !digits.is_empty() {
+            u32::from_str_radix(&digits, 16).ok()
+        } else {
+            None
+        };
+
+        let Some(ch) = code_point.and_then(char::from_u32) else {
+            let mut error = Error::new(ErrorVariant::MalformedEscapeCode);
+            error.push_note(
+                "the \\u escape code requires a code point with 1 to 6 hexadecimal digits, e.g. \\u{1F600}",
+                Some(start..self.current_position),
+            );
+            self.errors.push(error);
+            return None;
+        };
+
+        Some(ch)
+    }
+
+    // #insight
+    // Char literals and quotes share the `'` character, they are disambiguated
+    // with a lookahead of two characters: a `'` followed by a single character
+    // (other than a line end) and a closing `'` is a char literal, otherwise
+    // the `'` is a quote. For example:
+    // - `'a'` is the char `a`, `'('` is the char `(`
+    // - `'a` and `'ab` are quoted symbols, `'(a)` is a quoted list
+    // - `'a'b` is the char `a` followed by the symbol `b`
+    // As a consequence, a quoted symbol of one character cannot be followed
+    // by `'`, and the escaped chars (e.g. `'\n'`) always start with `'\`.
+
+    /// Scans the end of a char literal, e.g. `a'`, returns None if the input
+    /// is not a char literal, the scanned characters are put back.
+    fn scan_char_end(&mut self, ch: char) -> Option<char> {
+        let ch1 = self.next_char();
+
+        if ch1 == Some('\'') && !is_eol(ch) {
+            return Some(ch);
+        }
+
+        if let Some(ch1) = ch1 {
+            self.put_back_char(ch1);
+        }
+        self.put_back_char(ch);
+
+        None
+    }
+
+    /// Scans an escaped char literal, e.g. `'\n'` or `'\u{1F600}'`. The `'\`
+    /// prefix is already consumed.
+    fn scan_escaped_char(&mut self) -> Option<char> {
+        let escape_start = self.escape_start_position();
+
+        let ch = match self.next_char() {
+            Some('x') => self.scan_hex_escape_code(escape_start)?,
+            Some('u') => self.scan_unicode_escape_code(escape_start)?,
+            Some(ch) => {
+                let Some(ch) = escaped_char(ch) else {
+                    let mut error = Error::new(ErrorVariant::MalformedEscapeCode);
+                    error.push_note(
+                        &format!("unknown escape code `\\{ch}`"),
+                        Some(self.current_range()),
+                    );
+                    self.errors.push(error);
+                    return None;
+                };
+                ch
+            }
+            None => {
+                let mut error = Error::new(ErrorVariant::UnterminatedChar);
+                error.push_note("char is not closed", Some(self.current_range()));
+                self.errors.push(error);
+                return None;
+            }
+        };
+
+        if self.next_char() != Some('\'') {
+            let mut error = Error::new(ErrorVariant::UnterminatedChar);
+            error.push_note(
+                "char is missing the closing `'` character",
+                Some(self.current_range()),
+            );
+            self.errors.push(error);
+            return None;
+        }
+
+        Some(ch)
+    }
+
     // #todo needs cleanup.
     // #todo does not support leading tabs.
     // #todo find better name, `scan_indented_string`.
-    // #todo support 'raw' strings, e.g. (write #raw "this is \ cool")
     /// Scans a multi-string 'layout'.
     fn scan_text(&mut self, indent: u64) -> Option<String> {
         let mut string = String::new();
@@ -361,6 +509,81 @@ impl<'a> Lexer<'a> {
         Some(string)
     }
 
+    // #insight
+    // Raw strings use the Rust syntax, e.g. r"C:\path" or r#"say "hi""#, the
+    // number of `#` characters in the delimiters is configurable.
+
+    /// Scans the opening delimiter of a raw string (after the `r`), returns
+    /// the number of `#` characters. If the input is not a raw string, the
+    /// scanned characters are put back.
+    fn scan_raw_string_start(&mut self) -> Option<usize> {
+        let mut scanned = Vec::new();
+
+        while let Some(ch) = self.next_char() {
+            scanned.push(ch);
+
+            match ch {
+                '#' => continue,
+                '"' => return Some(scanned.len() - 1),
+                _ => break,
+            }
+        }
+
+        for ch in scanned.into_iter().rev() {
+            self.put_back_char(ch);
+        }
+
+        None
+    }
+
+    /// Scans a raw string lexeme, the text is passed through verbatim.
+    fn scan_raw_string(&mut self, hash_count: usize) -> Option<String> {
+        let mut string = String::new();
+
+        loop {
+            let Some(ch) = self.next_char() else {
+                let mut error = Error::new(ErrorVariant::UnterminatedString);
+                error.push_note(
+                    &format!(
+                        "raw string is missing the closing `\"{}` delimiter",
+                        "#".repeat(hash_count)
+                    ),
+                    Some(self.current_range()),
+                );
+                self.errors.push(error);
+                return None;
+            };
+
+            if ch == '"' {
+                // Check for the closing `#` characters.
+                let mut closing_count = 0;
+
+                while closing_count < hash_count {
+                    match self.next_char() {
+                        Some('#') => closing_count += 1,
+                        Some(ch) => {
+                            self.put_back_char(ch);
+                            break;
+                        }
+                        None => break,
+                    }
+                }
+
+                if closing_count == hash_count {
+                    break;
+                }
+
+                string.push('"');
+                string.push_str(&"#".repeat(closing_count));
+                continue;
+            }
+
+            string.push(ch);
+        }
+
+        Some(string)
+    }
+
     fn scan_annotation(&mut self) -> Option<String> {
         let mut ann = String::new();
 
@@ -430,6 +653,16 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    fn lex_symbol(&mut self) -> Token {
+        let lexeme = self.scan_lexeme();
+
+        if is_named_float_literal(&lexeme) {
+            Token::number(lexeme, self.current_range())
+        } else {
+            Token::symbol(lexeme, self.current_range())
+        }
+    }
+
     // #todo consider passing into array of chars or something more general.
     pub fn lex(&mut self) -> Result<Vec<Token>, Vec<Error>> {
         let mut tokens: Vec<Token> = Vec::new();
@@ -483,7 +716,24 @@ impl<'a> Lexer<'a> {
                 }
                 '\'' => {
                     // #todo consider `:`
-                    tokens.push(Token::new(TokenKind::Quote, self.current_range()));
+                    match self.next_char() {
+                        Some('\\') => {
+                            let Some(ch) = self.scan_escaped_char() else {
+                                break 'outer;
+                            };
+                            tokens.push(Token::char(ch, self.current_range()));
+                        }
+                        Some(ch1) => {
+                            if let Some(ch) = self.scan_char_end(ch1) {
+                                tokens.push(Token::char(ch, self.current_range()));
+                            } else {
+                                tokens.push(Token::new(TokenKind::Quote, self.current_range()));
+                            }
+                        }
+                        None => {
+                            tokens.push(Token::new(TokenKind::Quote, self.current_range()));
+                        }
+                    }
                 }
                 '$' => {
                     // #insight unquoting is interpolation.
@@ -575,8 +825,7 @@ impl<'a> Lexer<'a> {
                     } else {
                         // #todo lint warning for this!
                         // Symbol starting with `-`.
-                        let lexeme = self.scan_lexeme();
-                        tokens.push(Token::symbol(lexeme, self.current_range()));
+                        tokens.push(self.lex_symbol());
                     }
                 }
                 '#' => {
@@ -616,10 +865,21 @@ impl<'a> Lexer<'a> {
                     self.put_back_char(ch);
                     tokens.push(self.lex_number_or_range());
                 }
+                'r' => {
+                    if let Some(hash_count) = self.scan_raw_string_start() {
+                        let Some(lexeme) = self.scan_raw_string(hash_count) else {
+                            break 'outer;
+                        };
+                        tokens.push(Token::raw_string(lexeme, self.current_range()));
+                    } else {
+                        self.put_back_char(ch);
+                        let lexeme = self.scan_lexeme();
+                        tokens.push(Token::symbol(lexeme, self.current_range()));
+                    }
+                }
                 _ => {
                     self.put_back_char(ch);
-                    let lexeme = self.scan_lexeme();
-                    tokens.push(Token::symbol(lexeme, self.current_range()));
+                    tokens.push(self.lex_symbol());
                 }
             }
 
@@ -20,8 +20,8 @@ use super::comment::CommentKind;
 // A general Number token is used, classification is postponed to a later stage.
 
 // #insight
-// Tan intentionally doesn't provide a Char literal, as chars are not used that
-// often to deserve a dedicated sigil.
+// Char literals use single quotes, e.g. `'a'`, `'\n'`, `'\u{1F600}'`, the
+// closing quote differentiates them from quoted expressions.
 
 // #insight
 // No need to associate the kind with the lexeme, but we do it, for consistency with Error.
@@ -47,8 +47,11 @@ pub enum TokenKind {
     /// MultiLineWhitespace tokens are leveraged by the formatter to maintain
     /// 'paragraphs' of text.
     MultiLineWhitespace, // #todo use something more general, like `Pragma`.
-    // Char(char),
+    Char(char),
     String(String), // #todo support a tag? javascript-style tagged/template string?
+    /// A raw string, e.g. `r"C:\path"` or `r#"say "hi""#`, escapes and
+    /// interpolation are not processed.
+    RawString(String),
     Symbol(String),
     Number(String),
     Annotation(String),
@@ -57,6 +60,8 @@ pub enum TokenKind {
 
 impl fmt::Display for TokenKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut buf = [0; 4];
+
         // #todo optimize this!
         // #todo reconsider how tokens are displayed.
         f.write_str(match self {
@@ -69,11 +74,13 @@ impl fmt::Display for TokenKind {
             TokenKind::Quote => "'", // #todo consider `:`?
             TokenKind::Unquote => "$",
             TokenKind::String(lexeme) => lexeme,
+            TokenKind::RawString(lexeme) => lexeme,
             TokenKind::Symbol(lexeme) => lexeme,
             TokenKind::Number(lexeme) => lexeme,
             TokenKind::Annotation(lexeme) => lexeme,
             TokenKind::Comment(lexeme, _) => lexeme,
             TokenKind::MultiLineWhitespace => "MultiLineWhitespace", // #todo what should we do here? #Idea convert to comment?
+            TokenKind::Char(c) => c.encode_utf8(&mut buf),
         })
     }
 }
@@ -95,6 +102,13 @@ impl Token {
         Self { kind, range }
     }
 
+    pub fn char(c: char, range: Range) -> Self {
+        Self {
+            kind: TokenKind::Char(c),
+            range,
+        }
+    }
+
     pub fn string(lexeme: String, range: Range) -> Self {
         Self {
             kind: TokenKind::String(lexeme),
@@ -102,6 +116,13 @@ impl Token {
         }
     }
 
+    pub fn raw_string(lexeme: String, range: Range) -> Self {
+        Self {
+            kind: TokenKind::RawString(lexeme),
+            range,
+        }
+    }
+
     pub fn symbol(lexeme: String, range: Range) -> Self {
         Self {
             kind: TokenKind::Symbol(lexeme),
@@ -1,16 +1,26 @@
 pub mod api;
 pub mod check;
 pub mod context;
+#[cfg(feature = "serde")]
+pub mod de;
 pub mod error;
 pub mod eval;
 pub mod expr;
+pub mod fmt;
+pub mod foreign;
 pub mod lexer;
+pub mod library;
 pub mod macro_expand;
 pub mod module;
 pub mod optimize;
 pub mod parser;
 pub mod prune;
 pub mod range;
+pub mod resolve_locals;
 pub mod resolver;
 pub mod scope;
+#[cfg(feature = "serde")]
+pub mod ser;
+pub mod symbol;
 pub mod util;
+pub mod vm;
@@ -0,0 +1,14 @@
+// #insight
+// The library functions are implemented in Rust and registered in the prelude
+// of every context, an explicit prelude can still override them.
+
+// #todo Move more of the foreign functions here, e.g. from the std library.
+
+pub mod persistent;
+
+use crate::scope::Scope;
+
+/// Registers the library functions in the scope.
+pub fn setup_library(scope: &Scope) {
+    persistent::setup_persistent(scope);
+}
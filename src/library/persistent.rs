@@ -0,0 +1,124 @@
+// Functional updates of the persistent collections.
+
+// #insight
+// The functions return an updated copy, the original collection is not
+// modified. The copy shares structure with the original, so an update is
+// O(log n).
+
+// #insight
+// The functions accept arbitrary values, they are registered as `$$*` methods
+// and dispatch on the collection variant.
+
+use crate::{
+    error::Error,
+    expr::{check_key, Expr},
+    scope::Scope,
+};
+
+/// Returns the argument at `index`, reports an error if it's missing.
+fn arg(args: &[Expr], index: usize, name: &str) -> Result<Expr, Error> {
+    let Some(arg) = args.get(index) else {
+        return Err(Error::invalid_arguments(
+            &format!(
+                "`{name}` requires {} arguments, found {}",
+                index + 1,
+                args.len()
+            ),
+            None,
+        ));
+    };
+    Ok(arg.unpack().clone())
+}
+
+fn unexpected_collection(name: &str, collection: &Expr) -> Error {
+    Error::invalid_arguments(
+        &format!("`{name}` does not support `{collection}`"),
+        collection.range(),
+    )
+}
+
+/// Returns a copy of the collection with the key (or index) associated to the
+/// value, e.g. `(assoc m :a 1)`, `(assoc v 0 "a")`.
+pub fn assoc(args: &[Expr]) -> Result<Expr, Error> {
+    let collection = arg(args, 0, "assoc")?;
+    let key = arg(args, 1, "assoc")?;
+    let value = arg(args, 2, "assoc")?;
+
+    match collection {
+        Expr::PersistentMap(map) => {
+            check_key(&key, args[1].range())?;
+            Ok(Expr::PersistentMap(map.update(key, value)))
+        }
+        Expr::Vector(mut items) => {
+            // #insight The index after the last item appends the value.
+            let index = key
+                .as_int()
+                .filter(|index| (0..=items.len() as i64).contains(index));
+            let Some(index) = index else {
+                return Err(Error::invalid_arguments(
+                    &format!(
+                        "invalid vector index `{key}`, the length is {}",
+                        items.len()
+                    ),
+                    args[1].range(),
+                ));
+            };
+            let index = index as usize;
+            if index == items.len() {
+                items.push_back(value);
+            } else {
+                items.set(index, value);
+            }
+            Ok(Expr::Vector(items))
+        }
+        _ => Err(unexpected_collection("assoc", &args[0])),
+    }
+}
+
+/// Returns a copy of the map without the key, e.g. `(dissoc m :a)`.
+pub fn dissoc(args: &[Expr]) -> Result<Expr, Error> {
+    let collection = arg(args, 0, "dissoc")?;
+    let key = arg(args, 1, "dissoc")?;
+
+    match collection {
+        Expr::PersistentMap(map) => Ok(Expr::PersistentMap(map.without(&key))),
+        _ => Err(unexpected_collection("dissoc", &args[0])),
+    }
+}
+
+/// Returns a copy of the collection with the item added, e.g. `(conj v 4)`.
+/// Vector items are appended.
+pub fn conj(args: &[Expr]) -> Result<Expr, Error> {
+    let collection = arg(args, 0, "conj")?;
+    let item = arg(args, 1, "conj")?;
+
+    match collection {
+        Expr::Vector(mut items) => {
+            items.push_back(item);
+            Ok(Expr::Vector(items))
+        }
+        Expr::PersistentSet(set) => {
+            check_key(&item, args[1].range())?;
+            Ok(Expr::PersistentSet(set.update(item)))
+        }
+        _ => Err(unexpected_collection("conj", &args[0])),
+    }
+}
+
+/// Returns a copy of the set without the item, e.g. `(disj s 4)`.
+pub fn disj(args: &[Expr]) -> Result<Expr, Error> {
+    let collection = arg(args, 0, "disj")?;
+    let item = arg(args, 1, "disj")?;
+
+    match collection {
+        Expr::PersistentSet(set) => Ok(Expr::PersistentSet(set.without(&item))),
+        _ => Err(unexpected_collection("disj", &args[0])),
+    }
+}
+
+pub fn setup_persistent(scope: &Scope) {
+    scope.insert_invocable("assoc$$*", Expr::foreign_func(&assoc));
+    scope.insert_invocable("dissoc$$*", Expr::foreign_func(&dissoc));
+    scope.insert_invocable("conj$$*", Expr::foreign_func(&conj));
+    scope.insert_invocable("disj$$*", Expr::foreign_func(&disj));
+}
@@ -4,7 +4,7 @@ use crate::{
     context::Context,
     error::Error,
     eval::eval,
-    expr::{annotate_range, expr_clone, Expr},
+    expr::{expr_clone, Expr},
     scope::Scope,
     util::{args::unpack_arg, is_reserved_symbol},
 };
@@ -32,7 +32,15 @@ use crate::{
 
 // #todo Maybe separate macro_def from macro_expand?
 
-// #todo #think This prematurely strips list annotations.
+// #insight
+// The expanded lists keep the annotations of the original expression, e.g.
+// the range is used to anchor the errors raised while evaluating the list.
+
+/// Annotates the expanded list with the annotations of the original
+/// expression.
+fn with_annotations(list: Expr, expr: &Expr) -> Expr {
+    Expr::maybe_annotated(list, expr.annotations())
+}
 
 #[inline]
 fn is_function_capture_argument(arg: &Expr) -> bool {
@@ -67,7 +75,7 @@ fn is_function_capture(args: &[Expr]) -> bool {
 // expanded capture argument coding convention before the expansion.
 fn rename_capture_argument(arg: Expr) -> Expr {
     let name = arg.as_symbol().unwrap();
-    Expr::Symbol(format!("_{name}"))
+    Expr::symbol(format!("_{name}"))
 }
 
 /// Expands macro invocations, at compile time.
@@ -205,7 +213,7 @@ pub fn macro_expand(expr: Expr, context: &mut Context) -> Result<Option<Expr>, E
                             }
                         }
 
-                        Ok(Some(Expr::List(terms)))
+                        Ok(Some(with_annotations(Expr::List(terms), &expr)))
                     }
                 }
                 Expr::Symbol(sym) => {
@@ -277,7 +285,6 @@ pub fn macro_expand(expr: Expr, context: &mut Context) -> Result<Option<Expr>, E
                             }
                         }
 
-                        // #todo #WARNING annotations are stripped here!
                         Ok(Some(Expr::maybe_annotated(
                             Expr::List(result_exprs),
                             expr.annotations(),
@@ -292,10 +299,10 @@ pub fn macro_expand(expr: Expr, context: &mut Context) -> Result<Option<Expr>, E
 
                         // #todo super nasty, quotes should be resolved statically (at compile time)
                         // #todo hm, that clone, maybe `Arc` can fix this?
-                        Ok(Some(Expr::List(vec![
-                            Expr::Symbol("quot".to_owned()),
-                            value.unpack().clone(),
-                        ])))
+                        Ok(Some(with_annotations(
+                            Expr::List(vec![Expr::symbol("quot"), value.unpack().clone()]),
+                            &expr,
+                        )))
                     } else if sym == "+<-" || sym == "*<-" {
                         // #todo Use `ends_with("<-")` instead?
 
@@ -318,15 +325,18 @@ pub fn macro_expand(expr: Expr, context: &mut Context) -> Result<Option<Expr>, E
                         // Get the basic operator part from the assignment symbol.
                         let basic_op = &sym[..(sym.len() - 2)];
 
-                        let expanded_expr = Expr::List(vec![
-                            Expr::symbol("<-"),
-                            expr_clone(accum),
+                        let expanded_expr = with_annotations(
                             Expr::List(vec![
-                                Expr::symbol(basic_op),
+                                Expr::symbol("<-"),
                                 expr_clone(accum),
-                                expr_clone(value),
+                                Expr::List(vec![
+                                    Expr::symbol(basic_op),
+                                    expr_clone(accum),
+                                    expr_clone(value),
+                                ]),
                             ]),
-                        ]);
+                            &expr,
+                        );
 
                         Ok(Some(expanded_expr))
 
@@ -344,7 +354,7 @@ pub fn macro_expand(expr: Expr, context: &mut Context) -> Result<Option<Expr>, E
                             }
                         }
 
-                        Ok(Some(Expr::List(terms)))
+                        Ok(Some(with_annotations(Expr::List(terms), &expr)))
                     }
                 }
                 _ => {
@@ -358,11 +368,7 @@ pub fn macro_expand(expr: Expr, context: &mut Context) -> Result<Option<Expr>, E
                         }
                     }
 
-                    Ok(Some(annotate_range(
-                        Expr::List(terms),
-                        // #todo remove this unwrap!!!
-                        expr.range().unwrap(),
-                    )))
+                    Ok(Some(with_annotations(Expr::List(terms), &expr)))
                 }
             }
         }
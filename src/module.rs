@@ -1,8 +1,17 @@
+pub mod loader;
+
 use std::sync::Arc;
 
-use crate::{expr::Expr, scope::Scope};
+use crate::{
+    expr::Expr,
+    foreign::{
+        register_context_foreign_func, register_foreign_func, IntoContextForeignFunc,
+        IntoForeignFunc,
+    },
+    scope::Scope,
+    symbol::Symbol,
+};
 
-// #idea ModuleLoader
 // #idea Consider hashing to detect the same modules!
 
 // #todo Keep and define `path`, `name`, `prefix`.
@@ -34,7 +43,7 @@ impl Module {
 
     pub fn insert(
         &self,
-        name: impl Into<String>,
+        name: impl Into<Symbol>,
         value: impl Into<Arc<Expr>>,
     ) -> Option<Arc<Expr>> {
         self.scope.insert(name, value)
@@ -43,11 +52,31 @@ impl Module {
     // A specialized helper method that inserts invocables and also handles mangled names.
     pub fn insert_invocable(
         &self,
-        name: impl Into<String>,
+        name: impl Into<Symbol>,
         value: impl Into<Arc<Expr>>,
     ) -> Option<Arc<Expr>> {
         self.scope.insert_invocable(name, value)
     }
+
+    /// Registers a Rust function, the method signature is computed from the
+    /// argument types, e.g. `add$$Int$$Int`.
+    pub fn register<Args>(
+        &self,
+        name: impl AsRef<str>,
+        func: impl IntoForeignFunc<Args> + 'static,
+    ) -> Option<Arc<Expr>> {
+        register_foreign_func(&self.scope, name, func)
+    }
+
+    /// Registers a Rust function that accepts the Context, as the first
+    /// argument.
+    pub fn register_with_context<Args>(
+        &self,
+        name: impl AsRef<str>,
+        func: impl IntoContextForeignFunc<Args>,
+    ) -> Option<Arc<Expr>> {
+        register_context_foreign_func(&self.scope, name, func)
+    }
 }
 
 #[cfg(test)]
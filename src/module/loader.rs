@@ -0,0 +1,240 @@
+use std::{collections::HashMap, path::Path};
+
+use crate::{
+    api::{has_tan_extension, has_tan_extension_strict},
+    error::Error,
+    eval::util::{
+        canonicalize_path, compute_module_file_paths, resolve_non_relative_module_path,
+        resolve_relative_module_path,
+    },
+};
+
+// #insight
+// The module loader decouples `use` from the file-system. Embedders can
+// provide a loader that serves modules from memory, a database, an archive,
+// the network, etc.
+
+// #todo Consider async loading.
+// #todo Consider a caching loader wrapper.
+// #todo Consider a composite loader that tries multiple loaders in order.
+
+/// Resolves and loads the source units of modules.
+pub trait ModuleLoader: Send + Sync + std::fmt::Debug {
+    /// Resolves a module path, as passed to `use`, to a canonical module path
+    /// (id). Relative paths are resolved against the current module path.
+    fn resolve(&self, path: &str, current_module_path: Option<&str>) -> Result<String, Error>;
+
+    /// Returns the paths of the source units (files) of the module. If
+    /// `strict` is true, only units with a strict Tan extension are included.
+    fn source_unit_paths(&self, module_id: &str, strict: bool) -> Result<Vec<String>, Error>;
+
+    /// Reads the source of a unit.
+    fn read_source(&self, unit_path: &str) -> Result<String, Error>;
+}
+
+/// The default path of the local root, that overrides the root path.
+pub const DEFAULT_LOCAL_ROOT_PATH: &str = ".local-tan-root";
+
+/// The default loader, loads modules from the file-system.
+#[derive(Debug, Clone)]
+pub struct FileSystemModuleLoader {
+    pub root_path: String,
+    /// Modules found under the local root path override the modules under
+    /// the root path. Useful for local development of dependencies.
+    pub local_root_path: Option<String>,
+}
+
+impl FileSystemModuleLoader {
+    pub fn new(root_path: impl Into<String>) -> Self {
+        Self {
+            root_path: root_path.into(),
+            local_root_path: Some(DEFAULT_LOCAL_ROOT_PATH.to_string()),
+        }
+    }
+
+    pub fn with_local_root_path(self, local_root_path: Option<String>) -> Self {
+        Self {
+            local_root_path,
+            ..self
+        }
+    }
+}
+
+impl ModuleLoader for FileSystemModuleLoader {
+    fn resolve(&self, path: &str, current_module_path: Option<&str>) -> Result<String, Error> {
+        let path = if path.starts_with("./") || path.starts_with("../") {
+            resolve_relative_module_path(path, current_module_path)?
+        } else if let Some(path) = path.strip_prefix("file://") {
+            // #insight used by tan-run.
+            path.to_string()
+        } else {
+            // #todo Add integration test for local-tan-root.
+            // #todo #perf #IMPORTANT somehow cache this!
+            // #todo Also check `./.patch-tan-root`, first.
+            let local_path = self
+                .local_root_path
+                .as_ref()
+                .map(|local_root_path| resolve_non_relative_module_path(path, local_root_path))
+                .filter(|local_path| Path::new(local_path).exists());
+            if let Some(local_path) = local_path {
+                // #todo Add some tracing here!
+                local_path
+            } else {
+                resolve_non_relative_module_path(path, &self.root_path)
+            }
+        };
+
+        // #todo Check here if the path actually exists!
+        Ok(canonicalize_path(path))
+    }
+
+    fn source_unit_paths(&self, module_id: &str, strict: bool) -> Result<Vec<String>, Error> {
+        Ok(compute_module_file_paths(module_id, strict)?)
+    }
+
+    fn read_source(&self, unit_path: &str) -> Result<String, Error> {
+        Ok(std::fs::read_to_string(unit_path)?)
+    }
+}
+
+// #insight Useful for sandboxing, testing, and embedding in environments
+// without a file-system (e.g. wasm).
+
+/// A loader that serves modules from in-memory source units.
+///
+/// Unit paths are relative to a virtual root, standard library modules are
+/// found under `@std`, e.g. `@std/math/main.tan`.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryModuleLoader {
+    units: HashMap<String, String>,
+}
+
+impl InMemoryModuleLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_unit(mut self, path: impl AsRef<str>, source: impl Into<String>) -> Self {
+        self.insert_unit(path, source);
+        self
+    }
+
+    pub fn insert_unit(&mut self, path: impl AsRef<str>, source: impl Into<String>) {
+        self.units
+            .insert(normalize_path(path.as_ref()), source.into());
+    }
+}
+
+impl ModuleLoader for InMemoryModuleLoader {
+    fn resolve(&self, path: &str, current_module_path: Option<&str>) -> Result<String, Error> {
+        let path = if path.starts_with("./") || path.starts_with("../") {
+            resolve_relative_module_path(path, current_module_path)?
+        } else if let Some(path) = path.strip_prefix("file://") {
+            path.to_string()
+        } else {
+            resolve_non_relative_module_path(path, "")
+        };
+
+        Ok(normalize_path(&path))
+    }
+
+    fn source_unit_paths(&self, module_id: &str, strict: bool) -> Result<Vec<String>, Error> {
+        let module_id = normalize_path(module_id);
+
+        if self.units.contains_key(&module_id) && has_tan_extension(&module_id) {
+            // A single-unit module.
+            return Ok(vec![module_id]);
+        }
+
+        let mut unit_paths: Vec<String> = self
+            .units
+            .keys()
+            .filter(|unit_path| {
+                let unit_path = Path::new(unit_path);
+                let is_child = unit_path.parent() == Some(Path::new(&module_id));
+                let has_tan_extension = if strict {
+                    has_tan_extension_strict(unit_path)
+                } else {
+                    has_tan_extension(unit_path)
+                };
+                is_child && has_tan_extension
+            })
+            .cloned()
+            .collect();
+
+        if unit_paths.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("`{module_id}` not found"),
+            )
+            .into());
+        }
+
+        // #insight Sort to have a deterministic evaluation order.
+        unit_paths.sort();
+
+        Ok(unit_paths)
+    }
+
+    fn read_source(&self, unit_path: &str) -> Result<String, Error> {
+        let Some(source) = self.units.get(&normalize_path(unit_path)) else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("`{unit_path}` not found"),
+            )
+            .into());
+        };
+
+        Ok(source.clone())
+    }
+}
+
+/// Lexically normalizes a path, resolving `.` and `..` segments. The result
+/// always starts with `/`.
+fn normalize_path(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+
+    format!("/{}", segments.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{api::eval_string, context::Context, expr::Expr};
+
+    use super::{normalize_path, InMemoryModuleLoader};
+
+    #[test]
+    fn normalize_path_resolves_dot_segments() {
+        assert_eq!(
+            normalize_path("@std/math/./../math/main.tan"),
+            "/@std/math/main.tan"
+        );
+        assert_eq!(normalize_path("/a//b/"), "/a/b");
+    }
+
+    #[test]
+    fn in_memory_loader_serves_modules() {
+        let loader = InMemoryModuleLoader::new()
+            .with_unit("@std/math/main.tan", "(let answer 42)")
+            .with_unit("@std/math/README.md", "not a tan file");
+        let mut context = Context::with_module_loader(Arc::new(loader));
+
+        let value = eval_string("(use \"math\") math/answer", &mut context).unwrap();
+        assert_matches::assert_matches!(value.unpack(), Expr::Int(42));
+
+        let result = eval_string("(use \"missing\")", &mut context);
+        assert!(result.is_err());
+    }
+}
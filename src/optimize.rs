@@ -1,6 +1,7 @@
-use std::collections::HashMap;
+use imbl::Vector;
+use indexmap::IndexMap;
 
-use crate::expr::{format_value, Expr};
+use crate::expr::Expr;
 
 // #insight The optimizer does not err.
 
@@ -15,42 +16,64 @@ use crate::expr::{format_value, Expr};
 
 // #todo combine in one pass with e.g. check?
 
+// #insight
+// Map key inference:
+// (let name "George" role :admin)
+// (let user {_ name _ role})
+// user ; => {:name "George" :role :admin}
+// #todo should move to another place.
+// #todo move inference to parser?
+// #insight here it handles both {...} and (Map ...)
+fn map_entries(terms: &[Expr]) -> Vec<(Expr, Expr)> {
+    let items: Vec<Expr> = terms.iter().map(|ax| ax.unpack().clone()).collect();
+    let mut entries = Vec::with_capacity(items.len() / 2);
+    for pair in items.chunks(2) {
+        let mut k = pair[0].clone();
+        let v = pair[1].clone();
+        if k.as_symbol() == Some("_") {
+            if let Expr::Symbol(sym) = &v {
+                k = Expr::KeySymbol(*sym);
+            }
+            // #todo report error/warning if we cannot infere!
+        }
+        entries.push((k, v));
+    }
+    entries
+}
+
 pub fn optimize_fn(expr: Expr) -> Expr {
     // #todo let annotations are lost here.
     match expr.unpack() {
         Expr::List(ref terms) => {
             if !terms.is_empty() {
                 if let Expr::Symbol(s) = &terms[0].unpack() {
-                    if s == "Array" {
-                        // #todo we loose support for (Array ...)
-                        let items: Vec<Expr> =
-                            terms[1..].iter().map(|ax| ax.unpack().clone()).collect();
-                        return Expr::maybe_annotated(Expr::array(items), expr.annotations());
-                    } else if s == "Map" {
-                        // #todo we loose support for (Map ...)
-                        let items: Vec<Expr> =
-                            terms[1..].iter().map(|ax| ax.unpack().clone()).collect();
-                        let mut map = HashMap::new();
-                        for pair in items.chunks(2) {
-                            let mut k = format_value(&pair[0]);
-                            let v = pair[1].clone();
-                            // #insight
-                            // Map key inference:
-                            // (let name "George" role :admin)
-                            // (let user {_ name _ role})
-                            // user ; => {:name "George" :role :admin}
-                            // #todo should move to another place.
-                            // #todo move inference to parser?
-                            // #insight here it handles both {...} and (Map ...)
-                            if k == "_" {
-                                if let Expr::Symbol(sym) = &v {
-                                    k.clone_from(sym);
-                                }
-                                // #todo report error/warning if we cannot infere!
-                            }
-                            map.insert(k, v);
-                        }
-                        return Expr::maybe_annotated(Expr::map(map), expr.annotations());
+                    // #todo we loose support for (Array ...), (Map ...), etc.
+                    let collection = match s.as_str() {
+                        "Array" => Some(Expr::array(
+                            terms[1..]
+                                .iter()
+                                .map(|ax| ax.unpack().clone())
+                                .collect::<Vec<Expr>>(),
+                        )),
+                        "Map" => Some(Expr::map(
+                            map_entries(&terms[1..])
+                                .into_iter()
+                                .collect::<IndexMap<Expr, Expr>>(),
+                        )),
+                        "Vector" => Some(Expr::vector(
+                            terms[1..]
+                                .iter()
+                                .map(|ax| ax.unpack().clone())
+                                .collect::<Vector<Expr>>(),
+                        )),
+                        "PersistentMap" => Some(Expr::persistent_map(map_entries(&terms[1..]))),
+                        "PersistentSet" => Some(Expr::persistent_set(
+                            terms[1..].iter().map(|ax| ax.unpack().clone()),
+                        )),
+                        _ => None,
+                    };
+                    if let Some(collection) = collection {
+                        return Expr::maybe_annotated(collection, expr.annotations());
                     }
                 }
             }
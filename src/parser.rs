@@ -1,5 +1,10 @@
 pub mod util;
 
+use std::num::IntErrorKind;
+
+#[cfg(feature = "bigint")]
+use num_bigint::BigInt;
+use num_rational::Rational64;
 use rust_decimal::prelude::*;
 use util::desugar_key_path;
 
@@ -12,10 +17,28 @@ use crate::{
     },
     parser::util::recognize_string_template,
     range::{Position, Range},
-    util::{is_range_literal, is_type, put_back_iterator::PutBackIterator, Break},
+    util::{
+        is_named_float_literal, is_range_literal, is_type, put_back_iterator::PutBackIterator,
+        Break,
+    },
+};
+
+use self::util::{
+    is_key_symbol, recognize_persistent_constructor, recognize_range, recognize_temporal,
 };
 
-use self::util::{is_key_symbol, recognize_range};
+/// Returns true if the number lexeme is a float literal, e.g. `1.5`, `1e9` or
+/// `+inf`.
+fn is_float_literal(lexeme: &str) -> bool {
+    if lexeme.contains('.') || is_named_float_literal(lexeme) {
+        return true;
+    }
+
+    // #insight `e` is a digit in hexadecimal literals, e.g. `0xfe`.
+    let is_prefixed = ["0x", "0b", "0o"].iter().any(|p| lexeme.starts_with(p));
+
+    !is_prefixed && lexeme.contains(['e', 'E'])
+}
 
 // #todo Implement separate, analysis parser, keeps comments, annotations, etc.
 // #todo Or implement an 'analysis mode', and remove the prune stage.
@@ -159,13 +182,44 @@ impl<'a> Parser<'a> {
             let ann_expr = ann_expr.unpack();
 
             match &ann_expr {
-                Expr::Type(..) => {
+                Expr::Type(type_name) => {
                     // #insight
                     // Type shorthand: If the annotation starts with uppercase
                     // letter, it's considered a type annotation.
                     // #insight
                     // Don't use `:=` for type declarations, it's the assignment operator.
                     expr = annotate(expr, "type", ann_expr.clone());
+
+                    if let Some(input) = expr.as_string() {
+                        match recognize_temporal(type_name, input) {
+                            Some(Ok(value)) => {
+                                if let Expr::Annotated(inner, _) = &mut expr {
+                                    **inner = value;
+                                }
+                            }
+                            Some(Err(text)) => {
+                                let mut error = Error::new(ErrorVariant::MalformedTemporal);
+                                error.push_note(&text, expr.range());
+                                self.errors.push(error);
+                            }
+                            None => (),
+                        }
+                    } else if let Expr::Annotated(inner, _) = &mut expr {
+                        if let Some(head) = match inner.as_mut() {
+                            Expr::List(terms) => terms.first_mut(),
+                            _ => None,
+                        } {
+                            let constructor = head.as_symbol().and_then(|constructor| {
+                                recognize_persistent_constructor(type_name, constructor)
+                            });
+                            if let Some(constructor) = constructor {
+                                *head = Expr::maybe_annotated(
+                                    Expr::symbol(constructor),
+                                    head.annotations(),
+                                );
+                            }
+                        }
+                    }
                 }
                 Expr::Symbol(sym) => {
                     // #insight
@@ -185,7 +239,7 @@ impl<'a> Parser<'a> {
                         return expr;
                     }
 
-                    expr = annotate(expr, sym.clone(), Expr::Bool(true));
+                    expr = annotate(expr, *sym, Expr::Bool(true));
                 }
                 Expr::List(list) => {
                     // #todo problem {...} is still (Map ...) at this point!
@@ -205,7 +259,7 @@ impl<'a> Parser<'a> {
                     if is_type_expression {
                         // #todo #IMPORTANT verify that the type expression is valid
                         // #todo investigate if some part of the annotation is missing from ann_expr!
-                        expr = annotate(expr, "type", Expr::Type(format_value(ann_expr)));
+                        expr = annotate(expr, "type", Expr::typ(format_value(ann_expr)));
                     } else {
                         let Some(ann_list) = ann_expr.as_list() else {
                             let mut error = Error::new(ErrorVariant::MalformedAnnotation);
@@ -258,6 +312,51 @@ impl<'a> Parser<'a> {
         expr
     }
 
+    /// Parses a Ratio literal, e.g. `1/3`, the ratio is reduced.
+    fn parse_ratio(&mut self, lexeme: &str, range: Range) -> Option<Expr> {
+        let parts = lexeme.split_once('/').and_then(|(numer, denom)| {
+            Some((numer.parse::<i64>().ok()?, denom.parse::<i64>().ok()?))
+        });
+
+        let Some((numer, denom)) = parts else {
+            let mut error = Error::new(ErrorVariant::MalformedRatio);
+            error.push_note(&format!("invalid Ratio literal `{lexeme}`"), Some(range));
+            self.errors.push(error);
+            return None;
+        };
+
+        if denom == 0 {
+            let mut error = Error::new(ErrorVariant::MalformedRatio);
+            error.push_note("the denominator of a Ratio cannot be zero", Some(range));
+            self.errors.push(error);
+            return None;
+        }
+
+        Some(Expr::Ratio(Rational64::new(numer, denom)))
+    }
+
+    /// Parses a BigInt literal, the `n` postfix and the radix prefix are
+    /// already stripped.
+    #[cfg(feature = "bigint")]
+    fn parse_big_int(&mut self, lexeme: &str, radix: u32, range: Range) -> Option<Expr> {
+        if let Some(n) = BigInt::parse_bytes(lexeme.as_bytes(), radix) {
+            return Some(Expr::BigInt(n));
+        }
+
+        let mut error = Error::new(ErrorVariant::MalformedInt);
+        error.push_note(&format!("invalid BigInt literal `{lexeme}`"), Some(range));
+        self.errors.push(error);
+        None
+    }
+
+    #[cfg(not(feature = "bigint"))]
+    fn parse_big_int(&mut self, _lexeme: &str, _radix: u32, range: Range) -> Option<Expr> {
+        let mut error = Error::new(ErrorVariant::MalformedInt);
+        error.push_note("BigInt literals require the `bigint` feature", Some(range));
+        self.errors.push(error);
+        None
+    }
+
     pub fn parse_expr(&mut self) -> Result<Option<Expr>, Break> {
         let Some(token) = self.next_token() else {
             return Err(Break {});
@@ -267,6 +366,16 @@ impl<'a> Parser<'a> {
 
         let start_position = range.start;
 
+        // #insight
+        // The buffered annotations are held while parsing the items of an
+        // Array or Map literal, otherwise they would be attributed to the
+        // first item, e.g. `#Vector [1 2 3]`.
+        // #todo Also handle lists, currently annotations of lists are attributed to the head.
+        let held_annotations = match token.kind() {
+            TokenKind::LeftBracket | TokenKind::LeftBrace => self.buffered_annotations.take(),
+            _ => None,
+        };
+
         let expr = match token.kind() {
             TokenKind::Comment(lexeme, comment_kind) => {
                 // Preserve the comments as expressions, may be useful for analysis passes (e.g. formatting)
@@ -278,7 +387,7 @@ impl<'a> Parser<'a> {
                 // evaluation pass.
                 Some(Expr::TextSeparator)
             }
-            // Token::Char(c) => Some(Expr::Char(c)),
+            TokenKind::Char(c) => Some(Expr::Char(*c)),
             // #todo handle strings with interpolation (String-Template)
             // #todo javascript-style templated/tagged string, with key at the end.
             // #todo add detailed description.
@@ -306,6 +415,14 @@ impl<'a> Parser<'a> {
                     Some(Expr::String(lexeme.clone()))
                 }
             }
+            // #insight
+            // Raw strings are annotated with `raw`, equivalent to `#raw`, so
+            // that they are not treated as string-templates.
+            TokenKind::RawString(lexeme) => Some(annotate(
+                Expr::String(lexeme.clone()),
+                "raw",
+                Expr::Bool(true),
+            )),
             TokenKind::Symbol(lexeme) => {
                 if lexeme.contains(':') {
                     if is_key_symbol(lexeme) {
@@ -314,7 +431,7 @@ impl<'a> Parser<'a> {
                         // #todo Consider converting to (quote (Symbol ...))? KeySymbol is slightly faster?
                         let sym = str::replace(lexeme, ":", "");
                         // #todo Consider Expr::Key instead of Expr::KeySymbol
-                        Some(Expr::KeySymbol(sym))
+                        Some(Expr::key_symbol(sym))
                     } else {
                         // #todo The desugaring should happen later, so that
                         // it can be skipped in formatting.
@@ -351,17 +468,15 @@ impl<'a> Parser<'a> {
                         }
                     }
                 } else {
-                    Some(Expr::Symbol(lexeme.clone()))
+                    Some(Expr::symbol(lexeme))
                 }
             }
             TokenKind::Number(lexeme) => {
                 let mut lexeme = lexeme.clone();
 
-                // #todo Consider accepting scientific notation numbers without digit as floats.
-
                 // #todo more detailed Number error!
                 // #todo error handling not enough, we need to add context, check error_stack
-                if lexeme.contains('.') {
+                if is_float_literal(&lexeme) {
                     // #todo support radix for non-integers?
 
                     if lexeme.ends_with('d') {
@@ -390,7 +505,15 @@ impl<'a> Parser<'a> {
                             }
                         }
                     }
+                } else if lexeme.contains('/') {
+                    self.parse_ratio(&lexeme, range)
                 } else {
+                    // Numbers ending with an 'n' postfix are BigInt literals.
+                    let is_big_int = lexeme.ends_with('n');
+                    if is_big_int {
+                        lexeme.pop();
+                    }
+
                     // #todo support arbitrary radix https://github.com/golang/go/issues/28256
                     let mut radix = 10;
 
@@ -406,13 +529,27 @@ impl<'a> Parser<'a> {
                         radix = 8
                     }
 
-                    match i64::from_str_radix(&lexeme, radix) {
-                        Ok(n) => Some(Expr::Int(n)),
-                        Err(pi_error) => {
-                            let mut error = Error::new(ErrorVariant::MalformedInt);
-                            error.push_note(&format!("{pi_error}"), Some(range));
-                            self.errors.push(error);
-                            None
+                    if is_big_int {
+                        self.parse_big_int(&lexeme, radix, range)
+                    } else {
+                        match i64::from_str_radix(&lexeme, radix) {
+                            Ok(n) => Some(Expr::Int(n)),
+                            // #insight Literals that overflow Int are promoted to BigInt.
+                            Err(pi_error)
+                                if cfg!(feature = "bigint")
+                                    && matches!(
+                                        pi_error.kind(),
+                                        IntErrorKind::PosOverflow | IntErrorKind::NegOverflow
+                                    ) =>
+                            {
+                                self.parse_big_int(&lexeme, radix, range)
+                            }
+                            Err(pi_error) => {
+                                let mut error = Error::new(ErrorVariant::MalformedInt);
+                                error.push_note(&format!("{pi_error}"), Some(range));
+                                self.errors.push(error);
+                                None
+                            }
                         }
                     }
                 }
@@ -579,6 +716,10 @@ impl<'a> Parser<'a> {
             }
         };
 
+        if held_annotations.is_some() {
+            self.buffered_annotations = held_annotations;
+        }
+
         match expr {
             Some(expr) => {
                 let range = start_position..self.current_position;
@@ -1,3 +1,5 @@
+use chrono::{DateTime, NaiveDate, TimeDelta};
+
 use crate::{api::parse_string_with_position, error::Error, expr::Expr, range::Position};
 
 pub const STRING_INTERPOLATION_FUNC: &str = "String";
@@ -58,6 +60,117 @@ pub fn recognize_string_template(
     Ok(Expr::List(exprs))
 }
 
+// #insight
+// Temporal values don't have a dedicated syntax, a type annotation on a string
+// literal is used instead, e.g. `#Date "2024-01-15"`.
+
+/// Recognizes a temporal literal, i.e. a string annotated with one of the
+/// temporal types `Date`, `Instant` or `Duration`. Returns None if the type is
+/// not temporal.
+pub fn recognize_temporal(type_name: &str, input: &str) -> Option<Result<Expr, String>> {
+    let value = match type_name {
+        "Date" => NaiveDate::parse_from_str(input, "%Y-%m-%d")
+            .map(Expr::Date)
+            .map_err(|err| format!("invalid Date `{input}`: {err}")),
+        "Instant" => DateTime::parse_from_rfc3339(input)
+            .map(Expr::Instant)
+            .map_err(|err| format!("invalid Instant `{input}`: {err}")),
+        "Duration" => parse_duration(input)
+            .map(Expr::Duration)
+            .ok_or_else(|| format!("invalid Duration `{input}`, expected e.g. `PT1H30M`")),
+        _ => return None,
+    };
+
+    Some(value)
+}
+
+// #insight
+// Persistent collections reuse the collection syntax with a type annotation,
+// e.g. `#Vector [1 2 3]` is rewritten to `(Vector 1 2 3)`.
+
+/// Recognizes the constructor of a persistent collection literal, given the
+/// type annotation and the constructor of the annotated collection. Returns
+/// None if the literal is not a persistent collection.
+pub fn recognize_persistent_constructor(
+    type_name: &str,
+    constructor: &str,
+) -> Option<&'static str> {
+    match (type_name, constructor) {
+        ("Vector", "Array") => Some("Vector"),
+        ("PersistentMap", "Map") => Some("PersistentMap"),
+        ("PersistentSet", "Array") => Some("PersistentSet"),
+        _ => None,
+    }
+}
+
+/// Parses an ISO 8601 duration, e.g. `PT1H30M` or `P2DT0.5S`. Years and months
+/// are not supported, as they don't have a fixed length.
+pub fn parse_duration(input: &str) -> Option<TimeDelta> {
+    let (is_negative, input) = match input.strip_prefix('-') {
+        Some(input) => (true, input),
+        None => (false, input),
+    };
+
+    let input = input.strip_prefix('P')?;
+
+    let (date_part, time_part) = match input.split_once('T') {
+        Some((_, "")) => return None,
+        Some((date_part, time_part)) => (date_part, time_part),
+        None => (input, ""),
+    };
+
+    if date_part.is_empty() && time_part.is_empty() {
+        return None;
+    }
+
+    let mut nanos = parse_duration_part(date_part, &[('W', 604_800), ('D', 86_400)])?;
+    nanos += parse_duration_part(time_part, &[('H', 3_600), ('M', 60), ('S', 1)])?;
+
+    let nanos = i64::try_from(if is_negative { -nanos } else { nanos }).ok()?;
+
+    Some(TimeDelta::nanoseconds(nanos))
+}
+
+/// Parses the components of a duration part, the designators should appear in
+/// the order of `units`, a fraction is only allowed in the last component.
+/// Returns the total in nanoseconds.
+fn parse_duration_part(input: &str, units: &[(char, i128)]) -> Option<i128> {
+    let mut units = units.iter();
+    let mut total = 0;
+    let mut number = String::new();
+    let mut has_fraction = false;
+
+    for ch in input.chars() {
+        if ch.is_ascii_digit() || ch == '.' {
+            number.push(ch);
+            continue;
+        }
+
+        if number.is_empty() || has_fraction {
+            return None;
+        }
+
+        let (_, seconds) = units.by_ref().find(|(designator, _)| *designator == ch)?;
+
+        let (whole, fraction) = number.split_once('.').unwrap_or((&number, ""));
+        if fraction.len() > 9 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let whole = whole.parse::<i128>().ok()?;
+        let fraction = format!("{fraction:0<9}").parse::<i128>().ok()?;
+
+        has_fraction = fraction > 0;
+        total += (whole * 1_000_000_000 + fraction) * seconds;
+        number.clear();
+    }
+
+    if number.is_empty() {
+        Some(total)
+    } else {
+        None
+    }
+}
+
 // pub fn recognize_range_old(range_str: &str) -> Option<Expr> {
 //     // #todo should convert to (Range start, end, step)
 //     let parts: Vec<&str> = range_str.split('|').collect();
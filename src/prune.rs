@@ -40,7 +40,9 @@ pub fn prune_fn(expr: Expr) -> Option<Expr> {
             // #insight
             // Only apply the transformation, error checking happened in the
             // parsing stage.
-            if str.contains("${") {
+            // #insight Raw strings are not string-templates.
+            let is_raw = annotations.is_some_and(|ann| ann.contains_key("raw"));
+            if str.contains("${") && !is_raw {
                 // #todo what about this unwrap?
                 let range = expr.range().unwrap_or_default();
                 let start_position = range.start;
@@ -109,4 +111,18 @@ mod tests {
         assert_matches!(&exprs[0].unpack(), Expr::Symbol(s) if s == STRING_INTERPOLATION_FUNC);
         assert_eq!(exprs.len(), 5);
     }
+
+    #[test]
+    fn prune_does_not_transform_raw_strings() {
+        let input = r##"(let m r#"A template: ${num}"#)"##;
+        let expr = parse_string(input).unwrap();
+
+        let expr = prune(expr).unwrap();
+
+        let Expr::List(exprs) = expr.unpack() else {
+            panic!("assertion failed: invalid form")
+        };
+
+        assert_matches!(exprs[2].unpack(), Expr::String(s) if s == "A template: ${num}");
+    }
 }
@@ -0,0 +1,532 @@
+use std::collections::HashSet;
+
+use crate::{
+    expr::Expr,
+    scope::param_name,
+    symbol::Symbol,
+    util::{is_dynamically_scoped, is_reserved_symbol},
+};
+
+// #insight
+// The lexical addressing pass rewrites the references to function parameters
+// and `let` locals into `Expr::LocalSymbol(name, depth, index)` addresses. The
+// depth counts the enclosing functions, the index is the position of the slot,
+// the parameters come first, then the `let` locals in order of declaration. At
+// runtime the address is resolved by walking the invocation frames, without
+// hashing the name, see `Scope::get_slot`.
+
+// #insight
+// Only the `let` bindings evaluated in the scope of the invocation frame are
+// resolved to slots, a parameter rebound by `let` keeps its slot. The `let`
+// bindings in nested blocks, e.g. `do` or the `if` bodies, shadow the names
+// for the whole block, the references are looked up by name.
+
+// #insight
+// The pass is conservative: a name is resolved only if it cannot be rebound
+// by name in the function body, e.g. by `for` or destructuring. Only the forms
+// with known structure are rewritten, the references inside other forms are
+// looked up by name, the slots are also visible by name.
+
+// #todo Rewrite the references in more forms, e.g. for, when, try.
+// #todo A foreign function could insert a binding that shadows a local.
+
+/// The special forms that evaluate all their arguments in the current scope.
+const TRANSPARENT_FORMS: [&str; 9] = [
+    "else",
+    "cond",
+    "and",
+    "or",
+    "while",
+    "return",
+    "break",
+    "assert",
+    "assert-eq",
+];
+
+/// The special forms that evaluate their arguments in a nested scope.
+const BLOCK_FORMS: [&str; 2] = ["do", "if"];
+
+/// The special forms that assign to an existing binding.
+const ASSIGN_FORMS: [&str; 2] = ["<-", "assign"];
+
+/// The special forms that can bind arbitrary names, the locals of the
+/// enclosing functions are not resolved.
+const OPAQUE_FORMS: [&str; 3] = ["eval", "use", "scope-update"];
+
+/// A function frame.
+struct Frame {
+    /// The local names with their slot index, `None` marks a name that is
+    /// looked up by name, e.g. a `let` in a nested block.
+    names: Vec<(Symbol, Option<usize>)>,
+    /// The names that may be bound by name in the function body.
+    binders: HashSet<Symbol>,
+    /// True if the function body can bind arbitrary names.
+    is_opaque: bool,
+    /// The number of slots, the index of the next `let` local.
+    len: usize,
+}
+
+impl Frame {
+    fn is_resolvable(&self, name: &Symbol) -> bool {
+        !self.is_opaque
+            && !self.binders.contains(name)
+            && name != "_"
+            && !is_reserved_symbol(name)
+            && !is_dynamically_scoped(name)
+    }
+
+    /// Declares a `let` local, returns the slot index. A name already in the
+    /// frame, e.g. a parameter, keeps its slot.
+    fn declare(&mut self, name: &Symbol) -> usize {
+        if let Some(index) = self
+            .names
+            .iter()
+            .find_map(|(local, index)| if local == name { *index } else { None })
+        {
+            return index;
+        }
+        let index = self.len;
+        self.len += 1;
+        self.names.push((*name, Some(index)));
+        index
+    }
+}
+
+/// Rewrites the references to function parameters and `let` locals to
+/// (depth, index) addresses.
+pub fn resolve_locals(expr: Expr) -> Expr {
+    let mut frames = Vec::new();
+    resolve_expr(&expr, &mut frames, false)
+}
+
+fn lookup(name: &Symbol, frames: &[Frame]) -> Option<(usize, usize)> {
+    for (depth, frame) in frames.iter().rev().enumerate() {
+        if let Some((_, index)) = frame.names.iter().rev().find(|(local, _)| local == name) {
+            return index.map(|index| (depth, index));
+        }
+    }
+    None
+}
+
+/// Resolves the expression, `is_frame_scope` is true if the expression is
+/// evaluated in the scope of the invocation frame.
+fn resolve_expr(expr: &Expr, frames: &mut Vec<Frame>, is_frame_scope: bool) -> Expr {
+    let resolved = match expr.unpack() {
+        Expr::Symbol(name) => match lookup(name, frames) {
+            Some((depth, index)) => Expr::LocalSymbol(*name, depth, index),
+            None => return expr.clone(),
+        },
+        Expr::List(terms) => return resolve_list(expr, terms, frames, is_frame_scope),
+        Expr::Array(items) => {
+            let Ok(items) = items.read() else {
+                return expr.clone();
+            };
+            Expr::array(
+                items
+                    .iter()
+                    .map(|item| resolve_expr(item, frames, is_frame_scope))
+                    .collect::<Vec<_>>(),
+            )
+        }
+        Expr::Map(map) => {
+            let Ok(map) = map.read() else {
+                return expr.clone();
+            };
+            Expr::map(
+                map.iter()
+                    .map(|(key, value)| (key.clone(), resolve_expr(value, frames, is_frame_scope)))
+                    .collect::<indexmap::IndexMap<_, _>>(),
+            )
+        }
+        Expr::Vector(items) => Expr::Vector(
+            items
+                .iter()
+                .map(|item| resolve_expr(item, frames, is_frame_scope))
+                .collect(),
+        ),
+        Expr::PersistentMap(map) => Expr::persistent_map(
+            map.iter()
+                .map(|(key, value)| (key.clone(), resolve_expr(value, frames, is_frame_scope))),
+        ),
+        Expr::PersistentSet(set) => Expr::persistent_set(
+            set.iter()
+                .map(|item| resolve_expr(item, frames, is_frame_scope)),
+        ),
+        _ => return expr.clone(),
+    };
+
+    Expr::maybe_annotated(resolved, expr.annotations())
+}
+
+fn resolve_list(
+    expr: &Expr,
+    terms: &[Expr],
+    frames: &mut Vec<Frame>,
+    is_frame_scope: bool,
+) -> Expr {
+    let Some((head, args)) = terms.split_first() else {
+        return expr.clone();
+    };
+
+    let mut resolved_terms = vec![head.clone()];
+
+    match head.unpack() {
+        Expr::Symbol(s) | Expr::Type(s) if s == "Func" => {
+            return resolve_func(expr, head, args, frames);
+        }
+        Expr::Symbol(s) if is_reserved_symbol(s) => {
+            if TRANSPARENT_FORMS.contains(&s.as_str()) {
+                resolved_terms.extend(
+                    args.iter()
+                        .map(|arg| resolve_expr(arg, frames, is_frame_scope)),
+                );
+            } else if BLOCK_FORMS.contains(&s.as_str()) {
+                // #insight The `if` predicate is evaluated in the current scope.
+                let (args, block) = if s == "if" {
+                    let Some((predicate, block)) = args.split_first() else {
+                        return expr.clone();
+                    };
+                    resolved_terms.push(resolve_expr(predicate, frames, is_frame_scope));
+                    (block, block)
+                } else {
+                    (args, args)
+                };
+                resolved_terms.extend(resolve_block(args, block, frames));
+            } else if s == "let" {
+                resolve_let(expr, args, &mut resolved_terms, frames, is_frame_scope);
+            } else if ASSIGN_FORMS.contains(&s.as_str()) {
+                let [name, value] = args else {
+                    return expr.clone();
+                };
+                resolved_terms.push(resolve_expr(name, frames, is_frame_scope));
+                resolved_terms.push(resolve_expr(value, frames, is_frame_scope));
+            } else {
+                return expr.clone();
+            }
+        }
+        _ if head.as_symbolic().is_some() => {
+            // #insight The operator is looked up by name, to resolve the method.
+            resolved_terms.extend(
+                args.iter()
+                    .map(|arg| resolve_expr(arg, frames, is_frame_scope)),
+            );
+        }
+        _ => return expr.clone(),
+    }
+
+    Expr::maybe_annotated(Expr::List(resolved_terms), expr.annotations())
+}
+
+/// Resolves the expressions of a nested block, the `let` names in the block
+/// shadow the locals of the frame.
+fn resolve_block(args: &[Expr], block: &[Expr], frames: &mut Vec<Frame>) -> Vec<Expr> {
+    let Some(frame) = frames.last_mut() else {
+        return args.to_vec();
+    };
+
+    let mut shadows = HashSet::new();
+    for expr in block {
+        collect_let_names(expr, &mut shadows);
+    }
+    let len = frame.names.len();
+    frame
+        .names
+        .extend(shadows.into_iter().map(|name| (name, None)));
+
+    let resolved = args
+        .iter()
+        .map(|arg| resolve_expr(arg, frames, false))
+        .collect();
+
+    if let Some(frame) = frames.last_mut() {
+        frame.names.truncate(len);
+    }
+
+    resolved
+}
+
+fn resolve_let(
+    op: &Expr,
+    args: &[Expr],
+    resolved_terms: &mut Vec<Expr>,
+    frames: &mut Vec<Frame>,
+    is_frame_scope: bool,
+) {
+    // #insight A typed `let` binds a method, by the mangled name.
+    let is_declaration = is_frame_scope && op.annotation("type").is_none();
+
+    for pair in args.chunks(2) {
+        let [name, value] = pair else {
+            resolved_terms.extend(pair.iter().cloned());
+            break;
+        };
+
+        // #insight The value is resolved before the name is declared.
+        let value = resolve_expr(value, frames, is_frame_scope);
+
+        let index = match (name.unpack(), frames.last_mut()) {
+            (Expr::Symbol(sym), Some(frame)) if is_declaration && frame.is_resolvable(sym) => {
+                Some((sym, frame.declare(sym)))
+            }
+            _ => None,
+        };
+
+        match index {
+            Some((sym, index)) => resolved_terms.push(Expr::maybe_annotated(
+                Expr::LocalSymbol(*sym, 0, index),
+                name.annotations(),
+            )),
+            None => resolved_terms.push(name.clone()),
+        }
+        resolved_terms.push(value);
+    }
+}
+
+fn resolve_func(expr: &Expr, head: &Expr, args: &[Expr], frames: &mut Vec<Frame>) -> Expr {
+    let Some((params, body)) = args.split_first() else {
+        return expr.clone();
+    };
+
+    let param_list = match params.unpack() {
+        Expr::Array(items) => match items.read() {
+            Ok(items) => items.clone(),
+            Err(_) => return expr.clone(),
+        },
+        Expr::Symbol(..) => vec![params.clone()],
+        Expr::None => Vec::new(),
+        _ => return expr.clone(),
+    };
+
+    let mut frame = Frame {
+        names: Vec::with_capacity(param_list.len()),
+        binders: HashSet::new(),
+        is_opaque: false,
+        len: param_list.len(),
+    };
+    frame.is_opaque = body
+        .iter()
+        .any(|expr| collect_binders(expr, &mut frame.binders));
+
+    for (index, param) in param_list.iter().enumerate() {
+        // #insight A malformed parameter is reported when the function is invoked.
+        let Some(name) = param_name(param) else {
+            return expr.clone();
+        };
+        let index = frame.is_resolvable(&name).then_some(index);
+        frame.names.push((name, index));
+    }
+
+    frames.push(frame);
+    let body: Vec<Expr> = body
+        .iter()
+        .map(|expr| resolve_expr(expr, frames, true))
+        .collect();
+    frames.pop();
+
+    let mut terms = vec![head.clone(), params.clone()];
+    terms.extend(body);
+
+    Expr::maybe_annotated(Expr::List(terms), expr.annotations())
+}
+
+/// Collects the names that may be bound in the expression, in the positions
+/// that are not rewritten. Returns true if the expression contains an opaque
+/// form, the collection stops there since no parameter is resolved.
+fn collect_binders(expr: &Expr, binders: &mut HashSet<Symbol>) -> bool {
+    let Expr::List(terms) = expr.unpack() else {
+        return match expr.unpack() {
+            Expr::Array(..) | Expr::Map(..) => collect_items(expr, binders, false),
+            _ => false,
+        };
+    };
+
+    let Some((head, args)) = terms.split_first() else {
+        return false;
+    };
+
+    match head.unpack() {
+        Expr::Symbol(s) | Expr::Type(s) if s == "Func" => {
+            // #insight The parameters of a nested function shadow, they don't rebind.
+            args.iter().skip(1).any(|arg| collect_binders(arg, binders))
+        }
+        Expr::Symbol(s) if is_reserved_symbol(s) => {
+            if TRANSPARENT_FORMS.contains(&s.as_str()) || BLOCK_FORMS.contains(&s.as_str()) {
+                args.iter().any(|arg| collect_binders(arg, binders))
+            } else if s == "let" {
+                // #insight The untyped `let` of a plain name is resolved, or shadows.
+                let is_typed = expr.annotation("type").is_some();
+                args.iter().enumerate().any(|(i, arg)| {
+                    if i % 2 == 1 {
+                        collect_binders(arg, binders)
+                    } else if !is_typed && matches!(arg.unpack(), Expr::Symbol(..)) {
+                        false
+                    } else {
+                        collect_all(arg, binders)
+                    }
+                })
+            } else if ASSIGN_FORMS.contains(&s.as_str()) && args.len() == 2 {
+                // #insight The assigned name is resolved like a reference.
+                collect_binders(&args[1], binders)
+            } else {
+                collect_all(expr, binders)
+            }
+        }
+        _ if head.as_symbolic().is_some() => args.iter().any(|arg| collect_binders(arg, binders)),
+        _ => collect_all(expr, binders),
+    }
+}
+
+/// Collects the names of the `let` bindings in the expression, the nested
+/// functions are skipped.
+fn collect_let_names(expr: &Expr, names: &mut HashSet<Symbol>) {
+    let terms = match expr.unpack() {
+        Expr::List(terms) => terms,
+        Expr::Array(items) => {
+            if let Ok(items) = items.read() {
+                items.iter().for_each(|item| collect_let_names(item, names));
+            }
+            return;
+        }
+        Expr::Map(map) => {
+            if let Ok(map) = map.read() {
+                map.values()
+                    .for_each(|value| collect_let_names(value, names));
+            }
+            return;
+        }
+        _ => return,
+    };
+
+    match terms.first().map(|head| head.unpack()) {
+        Some(Expr::Symbol(s) | Expr::Type(s)) if s == "Func" => {}
+        Some(Expr::Symbol(s)) if s == "let" => {
+            for (i, term) in terms[1..].iter().enumerate() {
+                if i % 2 == 0 {
+                    if let Some(name) = term.as_symbol() {
+                        names.insert(Symbol::intern(name));
+                    }
+                } else {
+                    collect_let_names(term, names);
+                }
+            }
+        }
+        _ => {
+            for term in terms {
+                collect_let_names(term, names);
+            }
+        }
+    }
+}
+
+/// Collects all the names in the expression, including the names inferred
+/// from the keys of map patterns, e.g. `{:name _}`.
+fn collect_all(expr: &Expr, binders: &mut HashSet<Symbol>) -> bool {
+    match expr.unpack() {
+        Expr::Symbol(name) => {
+            binders.insert(*name);
+            false
+        }
+        Expr::List(terms) => {
+            let is_opaque = terms
+                .first()
+                .and_then(|head| head.as_symbol())
+                .is_some_and(|head| OPAQUE_FORMS.contains(&head));
+            is_opaque || terms.iter().any(|term| collect_all(term, binders))
+        }
+        Expr::Array(..) | Expr::Map(..) => collect_items(expr, binders, true),
+        _ => false,
+    }
+}
+
+fn collect_items(expr: &Expr, binders: &mut HashSet<Symbol>, all: bool) -> bool {
+    let collect = |expr: &Expr, binders: &mut HashSet<Symbol>| {
+        if all {
+            collect_all(expr, binders)
+        } else {
+            collect_binders(expr, binders)
+        }
+    };
+
+    match expr.unpack() {
+        Expr::Array(items) => {
+            let Ok(items) = items.read() else {
+                return true;
+            };
+            items.iter().any(|item| collect(item, binders))
+        }
+        Expr::Map(map) => {
+            let Ok(map) = map.read() else {
+                return true;
+            };
+            map.iter().any(|(key, value)| {
+                if all && value.as_symbol() == Some("_") {
+                    if let Some(name) = key.as_stringable() {
+                        binders.insert(Symbol::intern(name));
+                    }
+                }
+                collect(value, binders)
+            })
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{api::parse_string, expr::Expr, optimize::optimize};
+
+    use super::resolve_locals;
+
+    fn resolve(input: &str) -> Expr {
+        resolve_locals(optimize(parse_string(input).unwrap()))
+    }
+
+    #[test]
+    fn resolve_locals_rewrites_parameter_references() {
+        let expr = resolve("(Func [x y] (Func z (+ x y z)))");
+
+        let text = format!("{expr:?}");
+        assert!(text.contains("'x@1:0"));
+        assert!(text.contains("'y@1:1"));
+        assert!(text.contains("'z@0:0"));
+        // The operator is looked up by name.
+        assert!(text.contains("'+"));
+    }
+
+    #[test]
+    fn resolve_locals_skips_rebound_parameters() {
+        let expr = resolve("(Func [x y] (for [i y] (writeln i)) (+ x y))");
+
+        let text = format!("{expr:?}");
+        assert!(text.contains("'x@0:0"));
+        assert!(!text.contains("'y@"));
+
+        let expr = resolve("(Func [x y] (eval (quot (+ x y))))");
+        assert!(!format!("{expr:?}").contains('@'));
+
+        let expr = resolve("(Func [x name] (let {:name _} x) (+ x name))");
+        let text = format!("{expr:?}");
+        assert!(text.contains("'x@0:0"));
+        assert!(!text.contains("'name@"));
+    }
+
+    #[test]
+    fn resolve_locals_rewrites_let_locals() {
+        // A rebound parameter keeps its slot, the locals follow the parameters.
+        let expr = resolve("(Func [x] (let x (+ x 1) y 2) (let z (+ x y)) (<- z 3) z)");
+
+        let text = format!("{expr:?}");
+        assert!(!text.contains("('x)"));
+        assert!(text.contains("'y@0:1"));
+        assert!(text.contains("'z@0:2"));
+        assert!(!text.contains("('z)"));
+
+        // The `let` in a nested block shadows the local for the whole block.
+        let expr = resolve("(Func [x] (do (writeln x) (let x 2) x) (Func [] x))");
+
+        let text = format!("{expr:?}");
+        assert!(text.contains("'x@1:0"));
+        assert!(!text.contains("'x@0:0"));
+        assert!(text.contains("('x)"));
+    }
+}
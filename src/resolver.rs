@@ -1,7 +1,11 @@
 // #WARNING the resolver is temporarily disabled, some functions are used though.
 
 use crate::{
-    context::Context, error::Error, eval::eval_symbol, expr::Expr,
+    context::Context,
+    error::Error,
+    eval::eval_symbol,
+    expr::{expr_clone, Expr},
+    symbol::{sym, Symbol},
     util::method::compute_dyn_signature,
 };
 
@@ -18,7 +22,7 @@ use crate::{
 // #todo consider passing the name as Expr?
 pub fn resolve_op_method(
     op: &Expr,
-    name: &str,
+    name: &Symbol,
     args: &[Expr],
     context: &mut Context,
 ) -> Result<Expr, Error> {
@@ -27,41 +31,41 @@ pub fn resolve_op_method(
 
     let signature = compute_dyn_signature(args, context);
 
-    let resolved_op = Expr::Symbol(format!("{name}$${signature}"));
+    // #insight The mangled name is interned, it's not formatted per call.
+    let resolved_op = Symbol::method(*name, &signature);
 
-    // #insight No need for a full eval here, we know that op is symbol.
-    match eval_symbol(&resolved_op, context) {
+    // #insight The scope is probed directly, to avoid creating errors for the
+    // missing methods, this is on the hot path of every invocation.
+    if let Some(value) = context.get(resolved_op, false) {
+        return Ok(expr_clone(&value));
+    }
+
+    // The exact method is not found, try to get a fallback `$$*` method.
+    // #todo should do proper type analysis here.
+    // #todo maybe use a custom Expr::DSSymbol expression to move the detection to read/static time?
+
+    let fallback_op = Symbol::method(*name, &[sym!("*")]);
+    if let Some(value) = context.get(fallback_op, false) {
+        return Ok(expr_clone(&value));
+    }
+
+    // #insight This is used for function passed as parameters.
+    // #todo Think about the correct solution.
+    // #todo #hack This must me a temp solution.
+    // #todo Differentiate 'local' symbols.
+
+    let unmangled_op = Expr::symbol(name);
+    match eval_symbol(&unmangled_op, context) {
         value @ Ok(_) => value,
         Err(_) => {
-            // The exact method is not found, try to get a fallback `$$*` method.
-            // #todo should do proper type analysis here.
-            // #todo maybe use a custom Expr::DSSymbol expression to move the detection to read/static time?
-
-            let fallback_op = Expr::Symbol(format!("{name}$$*"));
-            match eval_symbol(&fallback_op, context) {
-                value @ Ok(_) => value,
-                Err(_) => {
-                    // #insight This is used for function passed as parameters.
-                    // #todo Think about the correct solution.
-                    // #todo #hack This must me a temp solution.
-                    // #todo Differentiate 'local' symbols.
-
-                    let unmangled_op = Expr::Symbol(name.to_string());
-                    match eval_symbol(&unmangled_op, context) {
-                        value @ Ok(_) => value,
-                        Err(_) => {
-                            // #insight Intentionally report the 'non-fallback' symbol.
-                            Err(Error::undefined_symbol(
-                                &format!("{resolved_op}"),
-                                &format!(
-                                    "method not defined: `{resolved_op}`, tried fallback method: `{fallback_op}`"
-                                ),
-                                op.range(),
-                            ))
-                        }
-                    }
-                }
-            }
+            // #insight Intentionally report the 'non-fallback' symbol.
+            Err(Error::undefined_symbol(
+                &resolved_op,
+                &format!(
+                    "method not defined: `{resolved_op}`, tried fallback method: `{fallback_op}`"
+                ),
+                op.range(),
+            ))
         }
     }
 }
@@ -1,9 +1,15 @@
 use std::{
     collections::HashMap,
-    sync::{Arc, RwLock},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
 };
 
-use crate::expr::Expr;
+use crate::{
+    expr::Expr,
+    symbol::{Symbol, ToSymbol},
+};
 
 // #todo should we name this `Env`?
 // #todo consider removing `Into`s and `AsRef`s
@@ -33,6 +39,55 @@ use crate::expr::Expr;
 // context -> dynamic
 // scope/environment -> static? what about closure's scope? could merge scope + context?
 
+// #insight
+// The parameters, and the `let` locals, of a function invocation are stored
+// in slots, a Vec indexed by the position assigned statically. References
+// resolved to a (depth, index) address skip the name lookup, see
+// `resolve_locals`. The slots are also visible by name, for the unresolved
+// references, the names are computed when the frame is created.
+
+/// A slot of a function invocation frame, the name and the value. `None` if
+/// the slot is not bound, e.g. for a missing argument.
+pub type Slot = Option<(Symbol, Arc<Expr>)>;
+
+/// The slots of a function invocation frame.
+#[derive(Debug)]
+pub struct Slots {
+    values: RwLock<Vec<Slot>>,
+}
+
+impl Slots {
+    /// Returns the slot index of `name`, if it's bound.
+    fn index_of(values: &[Slot], name: &Symbol) -> Option<usize> {
+        values.iter().rposition(|slot| {
+            slot.as_ref()
+                .is_some_and(|(slot_name, _)| slot_name == name)
+        })
+    }
+}
+
+/// Returns the name bound by a function parameter, strips the ellipsis of a
+/// rest parameter.
+pub fn param_name(param: &Expr) -> Option<Symbol> {
+    let Expr::Symbol(name) = param.unpack() else {
+        return None;
+    };
+    match name.strip_prefix("...") {
+        Some(name) => Some(Symbol::intern(name)),
+        None => Some(*name),
+    }
+}
+
+// #insight
+// A (re)definition of a method bumps the method version of the scope, the
+// dispatch caches check the versions of the scopes that bind methods, see
+// `eval::dispatch_cache`.
+
+/// Returns true if the name is a mangled method name, e.g. `add$$Int$$Int`.
+fn is_method_name(name: &Symbol) -> bool {
+    name.contains("$$")
+}
+
 #[derive(Debug, Default)]
 pub struct Scope {
     // #todo add global/session ?
@@ -40,9 +95,13 @@ pub struct Scope {
     pub parent: Option<Arc<Scope>>,
     // #todo explain why we have RefCell here.
     // #todo do we need RwLock here?
-    pub bindings: RwLock<HashMap<String, Arc<Expr>>>,
+    pub bindings: RwLock<HashMap<Symbol, Arc<Expr>>>,
     // #idea have separate values/annotations!!!
     // #idea annotate only named expressions/bindings, don't annotate literals! to make the above work.
+    /// The parameter slots, only for function invocation frames.
+    pub slots: Option<Slots>,
+    /// The version of the method bindings, 0 if no method is ever bound.
+    method_version: AtomicU64,
 }
 
 impl Scope {
@@ -51,31 +110,111 @@ impl Scope {
         Self {
             parent: Some(parent),
             bindings: RwLock::new(HashMap::new()),
+            slots: None,
+            method_version: AtomicU64::new(0),
+        }
+    }
+
+    /// Creates a function invocation frame, with the given slots, e.g. the
+    /// parameters bound to the arguments.
+    pub fn with_slots(parent: Arc<Scope>, slots: Vec<Slot>) -> Self {
+        Self {
+            parent: Some(parent),
+            bindings: RwLock::new(HashMap::new()),
+            slots: Some(Slots {
+                values: RwLock::new(slots),
+            }),
+            method_version: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the invocation frame at `depth`, the scopes without slots are
+    /// skipped.
+    fn frame(&self, depth: usize) -> Option<(&Scope, &Slots)> {
+        let mut scope = self;
+        let mut depth = depth;
+        loop {
+            if let Some(slots) = &scope.slots {
+                if depth == 0 {
+                    return Some((scope, slots));
+                }
+                depth -= 1;
+            }
+            scope = scope.parent.as_deref()?;
+        }
+    }
+
+    /// Returns the value of the slot `index` of the invocation frame at
+    /// `depth`.
+    pub fn get_slot(&self, depth: usize, index: usize) -> Option<Arc<Expr>> {
+        let (_, slots) = self.frame(depth)?;
+        let values = slots.values.read().expect("poisoned lock");
+        values.get(index)?.as_ref().map(|(_, value)| value.clone())
+    }
+
+    /// Binds the slot `index` of the invocation frame at `depth` to the
+    /// value. Returns false if there is no such frame.
+    pub fn set_slot(
+        &self,
+        depth: usize,
+        index: usize,
+        name: &Symbol,
+        value: impl Into<Arc<Expr>>,
+    ) -> bool {
+        let Some((scope, slots)) = self.frame(depth) else {
+            return false;
+        };
+        let mut values = slots.values.write().expect("poisoned lock");
+        if values.len() <= index {
+            values.resize(index + 1, None);
+        }
+        values[index] = Some((*name, value.into()));
+        scope.bump_method_version(name);
+        true
+    }
+
+    /// Returns the version of the method bindings of this scope, 0 if the
+    /// scope never bound a method.
+    pub fn method_version(&self) -> u64 {
+        self.method_version.load(Ordering::Acquire)
+    }
+
+    fn bump_method_version(&self, name: &Symbol) {
+        if is_method_name(name) {
+            self.method_version.fetch_add(1, Ordering::Release);
         }
     }
 
+    fn get_slot_by_name(&self, name: &Symbol) -> Option<Arc<Expr>> {
+        let slots = self.slots.as_ref()?;
+        let values = slots.values.read().expect("poisoned lock");
+        let index = Slots::index_of(&values, name)?;
+        values[index].as_ref().map(|(_, value)| value.clone())
+    }
+
     // #todo do the `impl Into`s slow down?
     pub fn insert(
         &self,
-        name: impl Into<String>,
+        name: impl Into<Symbol>,
         value: impl Into<Arc<Expr>>,
     ) -> Option<Arc<Expr>> {
+        let name = name.into();
+        self.bump_method_version(&name);
         self.bindings
             .write()
             .expect("poisoned lock")
-            .insert(name.into(), value.into())
+            .insert(name, value.into())
     }
 
     // A specialized helper method that inserts invocables and also handles mangled names.
     pub fn insert_invocable(
         &self,
-        name: impl Into<String>,
+        name: impl Into<Symbol>,
         value: impl Into<Arc<Expr>>,
     ) -> Option<Arc<Expr>> {
         let name = name.into();
         let value = value.into();
-        // #todo Extract helper predicate function for mangled names.
-        if name.contains("$$") {
+        if is_method_name(&name) {
             let (base_name, _) = name.split_once("$$").unwrap();
             if !self.contains_name(base_name) {
                 self.insert(base_name, value.clone());
@@ -92,35 +231,48 @@ impl Scope {
     // #todo We need a recursive version!
     // #todo consider `contains_symbol`
     // #todo think about name <> symbol.
-    pub fn contains_name(&self, name: impl AsRef<str>) -> bool {
+    pub fn contains_name(&self, name: impl ToSymbol) -> bool {
+        let Some(name) = name.to_symbol() else {
+            return false;
+        };
         self.bindings
             .read()
             .expect("poisoned lock")
-            .contains_key(name.as_ref())
+            .contains_key(&name)
+            || self.get_slot_by_name(&name).is_some()
     }
 
     // #todo Have delegate in Context?
     // #todo Find a better postfix than recursive.
-    pub fn contains_name_recursive(&self, name: impl AsRef<str>) -> bool {
-        if self.contains_name(name.as_ref()) {
+    pub fn contains_name_recursive(&self, name: impl ToSymbol) -> bool {
+        let Some(name) = name.to_symbol() else {
+            return false;
+        };
+        if self.contains_name(name) {
             true
         } else if let Some(parent) = &self.parent {
-            parent.contains_name(name.as_ref())
+            parent.contains_name(name)
         } else {
             false
         }
     }
 
     // #todo Add non-recursive version?
-    pub fn get(&self, name: impl AsRef<str>) -> Option<Arc<Expr>> {
+    pub fn get(&self, name: impl ToSymbol) -> Option<Arc<Expr>> {
+        self.get_symbol(&name.to_symbol()?)
+    }
+
+    fn get_symbol(&self, name: &Symbol) -> Option<Arc<Expr>> {
         let bindings = self.bindings.read().expect("poisoned lock");
 
-        let value = bindings.get(name.as_ref());
+        let value = bindings.get(name);
 
         if let Some(value) = value {
             Some(value.clone())
+        } else if let Some(value) = self.get_slot_by_name(name) {
+            Some(value)
         } else if let Some(parent) = &self.parent {
-            parent.get(name)
+            parent.get_symbol(name)
         } else {
             None
         }
@@ -129,14 +281,28 @@ impl Scope {
     // #todo only allow updating mutable bindings
     // #todo should we even allow this?
     /// Updates an existing binding, walks the environment.
-    pub fn update(&self, name: impl AsRef<str>, value: impl Into<Expr>) {
+    pub fn update(&self, name: impl Into<Symbol>, value: impl Into<Expr>) {
+        let name = name.into();
         let mut bindings = self.bindings.write().expect("poisoned lock");
 
-        let binding = bindings.get_mut(name.as_ref());
+        let binding = bindings.get_mut(&name);
 
         if let Some(binding) = binding {
             *binding = Arc::new(value.into());
-        } else if let Some(parent) = &self.parent {
+            self.bump_method_version(&name);
+            return;
+        }
+
+        if let Some(slots) = &self.slots {
+            let mut values = slots.values.write().expect("poisoned lock");
+            if let Some(index) = Slots::index_of(&values, &name) {
+                values[index] = Some((name, Arc::new(value.into())));
+                self.bump_method_version(&name);
+                return;
+            }
+        }
+
+        if let Some(parent) = &self.parent {
             parent.update(name, value);
         } else {
             // #todo should report an error here!
@@ -145,8 +311,10 @@ impl Scope {
 
     // #todo is this really useful?
     // #todo no need to return anything here?
-    pub fn remove(&self, name: impl AsRef<str>) -> Option<Arc<Expr>> {
+    pub fn remove(&self, name: impl ToSymbol) -> Option<Arc<Expr>> {
+        let name = name.to_symbol()?;
+        self.bump_method_version(&name);
         let mut bindings = self.bindings.write().expect("poisoned lock");
-        bindings.remove(name.as_ref())
+        bindings.remove(&name)
     }
 }
@@ -0,0 +1,449 @@
+// Serialization of Rust values to Tan values, via serde.
+
+// #insight
+// All integers are serialized as Int, so that the formatted text can be
+// parsed back. Unit variants are serialized as KeySymbols, other variants as
+// single-entry Maps, e.g. `{:Circle 1.0}`.
+
+// #todo Support Set.
+// #todo Consider pretty-printing in to_string.
+
+use std::fmt;
+
+use indexmap::IndexMap;
+use serde::ser::{self, Serialize};
+
+use crate::{
+    error::Error,
+    expr::{format_value, Expr},
+};
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::general(&msg.to_string())
+    }
+}
+
+/// Serializes a Rust value to Tan source text.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+    let expr = to_expr(value)?;
+    Ok(format_data(&expr))
+}
+
+/// Serializes a Rust value to a Tan expression.
+pub fn to_expr<T: Serialize + ?Sized>(value: &T) -> Result<Expr, Error> {
+    value.serialize(Serializer)
+}
+
+/// Formats a data value, strings are escaped so that the text can be parsed
+/// back.
+fn format_data(expr: &Expr) -> String {
+    match expr.unpack() {
+        Expr::String(s) => {
+            let mut text = String::with_capacity(s.len() + 2);
+            text.push('"');
+            for c in s.chars() {
+                match c {
+                    '"' => text.push_str("\\\""),
+                    '\\' => text.push_str("\\\\"),
+                    '\n' => text.push_str("\\n"),
+                    '\t' => text.push_str("\\t"),
+                    '\r' => text.push_str("\\r"),
+                    '\0' => text.push_str("\\0"),
+                    c if c.is_control() => text.push_str(&format!("\\u{{{:x}}}", c as u32)),
+                    c => text.push(c),
+                }
+            }
+            text.push('"');
+            text
+        }
+        Expr::Array(items) => {
+            let items = items
+                .read()
+                .expect("poisoned lock")
+                .iter()
+                .map(format_data)
+                .collect::<Vec<String>>()
+                .join(" ");
+            format!("[{items}]")
+        }
+        Expr::Map(map) => {
+            let map = map.read().expect("poisoned lock");
+            // #insight Entries are emitted in insertion order.
+            let items = map
+                .iter()
+                .map(|(k, v)| format!("{} {}", format_data(k), format_data(v)))
+                .collect::<Vec<String>>()
+                .join(" ");
+            format!("{{{items}}}")
+        }
+        // #insight Persistent collections are formatted as plain data.
+        Expr::Vector(items) => {
+            let items = items
+                .iter()
+                .map(format_data)
+                .collect::<Vec<String>>()
+                .join(" ");
+            format!("[{items}]")
+        }
+        Expr::PersistentMap(map) => {
+            let items = map
+                .iter()
+                .map(|(k, v)| format!("{} {}", format_data(k), format_data(v)))
+                .collect::<Vec<String>>()
+                .join(" ");
+            format!("{{{items}}}")
+        }
+        Expr::KeySymbol(s) => format!(":{s}"),
+        expr => format_value(expr),
+    }
+}
+
+fn variant_map(variant: &str, value: Expr) -> Expr {
+    Expr::map(IndexMap::from([(Expr::key_symbol(variant), value)]))
+}
+
+/// A serde serializer that produces Tan values.
+pub struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = Expr;
+    type Error = Error;
+
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeArray;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeMap;
+
+    fn serialize_bool(self, v: bool) -> Result<Expr, Error> {
+        Ok(Expr::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Expr, Error> {
+        Ok(Expr::Int(v.into()))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Expr, Error> {
+        Ok(Expr::Int(v.into()))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Expr, Error> {
+        Ok(Expr::Int(v.into()))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Expr, Error> {
+        Ok(Expr::Int(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Expr, Error> {
+        Ok(Expr::Int(v.into()))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Expr, Error> {
+        Ok(Expr::Int(v.into()))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Expr, Error> {
+        Ok(Expr::Int(v.into()))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Expr, Error> {
+        let Ok(n) = i64::try_from(v) else {
+            return Err(Error::invalid_arguments(
+                &format!("`{v}` is out of the Int range"),
+                None,
+            ));
+        };
+        Ok(Expr::Int(n))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Expr, Error> {
+        Ok(Expr::Float(v.into()))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Expr, Error> {
+        Ok(Expr::Float(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Expr, Error> {
+        Ok(Expr::Char(v))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Expr, Error> {
+        Ok(Expr::string(v))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Expr, Error> {
+        Ok(Expr::array(
+            v.iter().map(|b| Expr::Int((*b).into())).collect::<Vec<_>>(),
+        ))
+    }
+
+    fn serialize_none(self) -> Result<Expr, Error> {
+        Ok(Expr::None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Expr, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Expr, Error> {
+        Ok(Expr::None)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Expr, Error> {
+        Ok(Expr::None)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Expr, Error> {
+        Ok(Expr::key_symbol(variant))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Expr, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Expr, Error> {
+        Ok(variant_map(variant, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeArray, Error> {
+        Ok(SerializeArray {
+            variant: None,
+            items: Vec::with_capacity(len.unwrap_or_default()),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeArray, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeArray, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeArray, Error> {
+        Ok(SerializeArray {
+            variant: Some(variant),
+            items: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeMap, Error> {
+        Ok(SerializeMap {
+            variant: None,
+            map: IndexMap::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeMap, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<SerializeMap, Error> {
+        Ok(SerializeMap {
+            variant: Some(variant),
+            map: IndexMap::new(),
+            key: None,
+        })
+    }
+}
+
+pub struct SerializeArray {
+    variant: Option<&'static str>,
+    items: Vec<Expr>,
+}
+
+impl SerializeArray {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.items.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Expr, Error> {
+        let array = Expr::array(self.items);
+        if let Some(variant) = self.variant {
+            Ok(variant_map(variant, array))
+        } else {
+            Ok(array)
+        }
+    }
+}
+
+impl ser::SerializeSeq for SerializeArray {
+    type Ok = Expr;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Expr, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SerializeArray {
+    type Ok = Expr;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Expr, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeArray {
+    type Ok = Expr;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Expr, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeArray {
+    type Ok = Expr;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Expr, Error> {
+        self.finish()
+    }
+}
+
+pub struct SerializeMap {
+    variant: Option<&'static str>,
+    map: IndexMap<Expr, Expr>,
+    key: Option<Expr>,
+}
+
+impl SerializeMap {
+    fn finish(self) -> Result<Expr, Error> {
+        let map = Expr::map(self.map);
+        if let Some(variant) = self.variant {
+            Ok(variant_map(variant, map))
+        } else {
+            Ok(map)
+        }
+    }
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Expr;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        // #insight String keys are created at runtime, they are kept as
+        // Strings and not interned, unlike the field names.
+        match key.serialize(Serializer)?.unpack_consuming() {
+            key @ (Expr::String(..)
+            | Expr::KeySymbol(..)
+            | Expr::Int(..)
+            | Expr::Bool(..)
+            | Expr::Char(..)) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            key => Err(Error::invalid_arguments(
+                &format!("unsupported Map key `{key}`"),
+                None,
+            )),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let Some(key) = self.key.take() else {
+            return Err(Error::invalid_arguments("missing Map key", None));
+        };
+        self.map.insert(key, value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Expr, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = Expr;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.map
+            .insert(Expr::key_symbol(key), value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Expr, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeMap {
+    type Ok = Expr;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.map
+            .insert(Expr::key_symbol(key), value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Expr, Error> {
+        self.finish()
+    }
+}
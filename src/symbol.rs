@@ -0,0 +1,366 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    ops::Deref,
+    sync::{LazyLock, OnceLock, RwLock},
+};
+
+// #insight
+// Symbols are interned in a global table, a Symbol is a `Copy` id, the index
+// of the unique copy of the name. Comparing and hashing a Symbol uses the id,
+// the name is only accessed at the Display boundary.
+
+// #insight
+// The interner is global, and not per Context, since expressions are created
+// by the parser (and Rust code) without a Context.
+
+// #insight
+// The names are never freed, only the names of the source and of the Rust
+// definitions (e.g. registered functions, struct fields) are interned, so the
+// table is bounded by the code, not by the data. The strings created at
+// runtime, e.g. the keys of serialized maps, are kept as Strings, and the
+// lookups by name don't intern, see `ToSymbol`.
+
+// #todo Consider a faster hasher for the Symbol-keyed maps.
+
+/// The size of the first bucket of names, the next buckets double in size.
+const FIRST_BUCKET_SIZE: usize = 64;
+
+/// Enough buckets for `u32` ids.
+const BUCKET_COUNT: usize = 27;
+
+type Bucket = Box<[OnceLock<Box<str>>]>;
+
+// #insight
+// The names are stored in buckets that are never moved or freed, so a name is
+// read without locking.
+static NAMES: [OnceLock<Bucket>; BUCKET_COUNT] = [const { OnceLock::new() }; BUCKET_COUNT];
+
+static INTERNER: LazyLock<RwLock<HashMap<&'static str, Symbol>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+thread_local! {
+    // #insight The interned names are cached per thread, to avoid the global
+    // lock when converting a name, e.g. a `&str`, to a Symbol.
+    static CACHE: RefCell<HashMap<&'static str, Symbol>> = RefCell::new(HashMap::new());
+}
+
+/// Returns the bucket and the offset of the name with the given id.
+fn slot(id: u32) -> (usize, usize) {
+    let position = id as usize + FIRST_BUCKET_SIZE;
+    let bucket = (usize::BITS - 1 - position.leading_zeros()) as usize;
+    let first_bucket = (FIRST_BUCKET_SIZE.trailing_zeros()) as usize;
+    (bucket - first_bucket, position - (1 << bucket))
+}
+
+/// The mangled names of the methods of a symbol, signature -> mangled name.
+type Methods = HashMap<Box<[Symbol]>, Symbol>;
+
+/// The cached method symbols.
+#[derive(Default)]
+struct MethodCache {
+    methods: HashMap<Symbol, Methods>,
+    len: usize,
+}
+
+impl MethodCache {
+    // #insight The cache is cleared when full, the method symbols are
+    // interned, so they are computed again on demand.
+    const MAX_LEN: usize = 4096;
+
+    fn get(&self, name: Symbol, signature: &[Symbol]) -> Option<Symbol> {
+        self.methods.get(&name)?.get(signature).copied()
+    }
+
+    fn insert(&mut self, name: Symbol, signature: &[Symbol], method: Symbol) {
+        if self.len >= Self::MAX_LEN {
+            self.methods.clear();
+            self.len = 0;
+        }
+        let methods = self.methods.entry(name).or_default();
+        if methods.insert(signature.into(), method).is_none() {
+            self.len += 1;
+        }
+    }
+}
+
+static METHODS: LazyLock<RwLock<MethodCache>> =
+    LazyLock::new(|| RwLock::new(MethodCache::default()));
+
+/// Interns a string literal, once per call site.
+macro_rules! sym {
+    ($name:literal) => {{
+        static SYMBOL: std::sync::OnceLock<$crate::symbol::Symbol> = std::sync::OnceLock::new();
+        *SYMBOL.get_or_init(|| $crate::symbol::Symbol::intern($name))
+    }};
+}
+
+pub(crate) use sym;
+
+/// An interned name, e.g. of a Symbol, KeySymbol, or Type.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    pub fn intern(name: &str) -> Self {
+        if let Some(symbol) = Self::cached(name) {
+            return symbol;
+        }
+
+        let symbol = {
+            let mut interner = INTERNER.write().expect("poisoned lock");
+            // #insight Check again, the name could be interned by another
+            // thread.
+            match interner.get(name) {
+                Some(symbol) => *symbol,
+                None => {
+                    let id = u32::try_from(interner.len()).expect("too many symbols");
+                    let (bucket, offset) = slot(id);
+                    let bucket = NAMES[bucket].get_or_init(|| {
+                        let size = FIRST_BUCKET_SIZE << bucket;
+                        (0..size).map(|_| OnceLock::new()).collect()
+                    });
+                    let name: &'static str = bucket[offset].get_or_init(|| name.into());
+                    let symbol = Symbol(id);
+                    interner.insert(name, symbol);
+                    symbol
+                }
+            }
+        };
+
+        Self::cache(symbol);
+        symbol
+    }
+
+    /// Returns the symbol of the name, if the name is interned. Unlike
+    /// `intern`, the name is not added to the table.
+    pub fn lookup(name: &str) -> Option<Self> {
+        if let Some(symbol) = Self::cached(name) {
+            return Some(symbol);
+        }
+
+        let symbol = INTERNER.read().expect("poisoned lock").get(name).copied()?;
+        Self::cache(symbol);
+        Some(symbol)
+    }
+
+    fn cached(name: &str) -> Option<Self> {
+        CACHE.with(|cache| cache.borrow().get(name).copied())
+    }
+
+    fn cache(symbol: Self) {
+        CACHE.with(|cache| cache.borrow_mut().insert(symbol.as_str(), symbol));
+    }
+
+    /// Interns the mangled name of the method with the given signature,
+    /// e.g. `add$$Int$$Int`. The mangled name is computed only once per
+    /// signature, while cached.
+    pub fn method(name: Symbol, signature: &[Symbol]) -> Self {
+        if let Some(method) = METHODS.read().expect("poisoned lock").get(name, signature) {
+            return method;
+        }
+
+        let mut mangled = name.as_str().to_string();
+        for typ in signature {
+            mangled.push_str("$$");
+            mangled.push_str(typ.as_str());
+        }
+        let method = Symbol::intern(&mangled);
+
+        METHODS
+            .write()
+            .expect("poisoned lock")
+            .insert(name, signature, method);
+
+        method
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        let (bucket, offset) = slot(self.0);
+        NAMES[bucket]
+            .get()
+            .and_then(|bucket| bucket[offset].get())
+            .expect("the symbol should be interned")
+    }
+}
+
+/// A name that is looked up, e.g. in a Scope, without interning it. A name
+/// that is not interned is not bound.
+pub trait ToSymbol {
+    fn to_symbol(&self) -> Option<Symbol>;
+}
+
+impl ToSymbol for Symbol {
+    fn to_symbol(&self) -> Option<Symbol> {
+        Some(*self)
+    }
+}
+
+impl ToSymbol for &Symbol {
+    fn to_symbol(&self) -> Option<Symbol> {
+        Some(**self)
+    }
+}
+
+impl ToSymbol for &str {
+    fn to_symbol(&self) -> Option<Symbol> {
+        Symbol::lookup(self)
+    }
+}
+
+impl ToSymbol for &String {
+    fn to_symbol(&self) -> Option<Symbol> {
+        Symbol::lookup(self)
+    }
+}
+
+impl ToSymbol for String {
+    fn to_symbol(&self) -> Option<Symbol> {
+        Symbol::lookup(self)
+    }
+}
+
+// #insight Symbols are ordered by name, to keep sorting deterministic.
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for Symbol {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Symbol {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Symbol::intern(name)
+    }
+}
+
+impl From<&String> for Symbol {
+    fn from(name: &String) -> Self {
+        Symbol::intern(name)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(name: String) -> Self {
+        Symbol::intern(&name)
+    }
+}
+
+impl From<&Symbol> for Symbol {
+    fn from(symbol: &Symbol) -> Self {
+        *symbol
+    }
+}
+
+impl From<Symbol> for String {
+    fn from(symbol: Symbol) -> Self {
+        symbol.as_str().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{slot, Symbol, FIRST_BUCKET_SIZE, METHODS};
+
+    #[test]
+    fn symbols_are_interned() {
+        let a = Symbol::intern("a-symbol");
+        let b = Symbol::from(String::from("a-symbol"));
+
+        assert_eq!(a, b);
+        assert!(std::ptr::eq(a.as_str(), b.as_str()));
+        assert_ne!(a, Symbol::intern("another-symbol"));
+        assert_eq!(a, "a-symbol");
+        assert_eq!(a.to_string(), "a-symbol");
+    }
+
+    #[test]
+    fn symbol_ids_map_to_buckets() {
+        assert_eq!(slot(0), (0, 0));
+        assert_eq!(
+            slot(FIRST_BUCKET_SIZE as u32 - 1),
+            (0, FIRST_BUCKET_SIZE - 1)
+        );
+        assert_eq!(slot(FIRST_BUCKET_SIZE as u32), (1, 0));
+        assert_eq!(slot(3 * FIRST_BUCKET_SIZE as u32), (2, 0));
+        assert_eq!(slot(u32::MAX).0, 26);
+    }
+
+    #[test]
+    fn lookups_do_not_intern() {
+        assert_eq!(Symbol::lookup("a-never-interned-symbol"), None);
+        let symbol = Symbol::intern("a-looked-up-symbol");
+        assert_eq!(Symbol::lookup("a-looked-up-symbol"), Some(symbol));
+    }
+
+    #[test]
+    fn method_symbols_are_mangled() {
+        let method = Symbol::method(Symbol::intern("add"), &["Int".into(), "Int".into()]);
+        assert_eq!(method, Symbol::intern("add$$Int$$Int"));
+        assert_eq!(
+            Symbol::method(Symbol::intern("add"), &["Int".into(), "Int".into()]),
+            method
+        );
+        assert_eq!(Symbol::method(Symbol::intern("f"), &[]), "f");
+    }
+
+    #[test]
+    fn method_cache_is_bounded() {
+        let name = Symbol::intern("a-cached-method");
+        for i in 0..5000 {
+            let typ = Symbol::intern(&format!("Type{i}"));
+            Symbol::method(name, &[typ]);
+        }
+        assert!(METHODS.read().unwrap().len <= 4096);
+    }
+}
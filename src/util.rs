@@ -64,6 +64,8 @@ pub fn is_reserved_symbol(sym: &str) -> bool {
             | "else"
             | "when"
             | "cond"
+            | "try"
+            | "catch"
             | "return"
             | "continue"
             | "break"
@@ -124,6 +126,13 @@ pub fn is_range_literal(input: &str) -> bool {
     input.contains("..") && (!is_path_literal(input)) && (!is_ellipsis(input))
 }
 
+/// Returns true if the input is a named float literal, i.e. `+inf`, `-inf` or
+/// `nan`.
+#[inline]
+pub fn is_named_float_literal(input: &str) -> bool {
+    matches!(input, "+inf" | "-inf" | "nan")
+}
+
 // #todo consider is_type_symbol, is_type_literal.
 // A type starts with an uppercase character.
 pub fn is_type(input: &str) -> bool {
@@ -2,12 +2,13 @@
 
 use std::{
     any::Any,
-    collections::HashMap,
     ops::Range,
     sync::{Arc, RwLockReadGuard, RwLockWriteGuard},
 };
 
-use crate::{error::Error, expr::Expr};
+use indexmap::IndexMap;
+
+use crate::{error::Error, expr::Expr, symbol::Symbol};
 
 // #todo move to eval/utils or something.
 // #todo convert those to macros.
@@ -153,11 +154,7 @@ pub fn unpack_stringable_arg<'a>(
     Ok(s)
 }
 
-pub fn unpack_symbolic_arg<'a>(
-    args: &'a [Expr],
-    index: usize,
-    name: &str,
-) -> Result<&'a str, Error> {
+pub fn unpack_symbolic_arg(args: &[Expr], index: usize, name: &str) -> Result<Symbol, Error> {
     let Some(expr) = args.get(index) else {
         // #todo introduce 'missing argument' error variant.
         // #todo also report the index.
@@ -181,7 +178,7 @@ pub fn unpack_map_arg<'a>(
     args: &'a [Expr],
     index: usize,
     name: &str,
-) -> Result<RwLockReadGuard<'a, HashMap<String, Expr>>, Error> {
+) -> Result<RwLockReadGuard<'a, IndexMap<Expr, Expr>>, Error> {
     let Some(expr) = args.get(index) else {
         // #todo introduce 'missing argument' error variant.
         // #todo also report the index.
@@ -205,7 +202,7 @@ pub fn unpack_map_mut_arg<'a>(
     args: &'a [Expr],
     index: usize,
     name: &str,
-) -> Result<RwLockWriteGuard<'a, HashMap<String, Expr>>, Error> {
+) -> Result<RwLockWriteGuard<'a, IndexMap<Expr, Expr>>, Error> {
     let Some(expr) = args.get(index) else {
         // #todo introduce 'missing argument' error variant.
         // #todo also report the index.
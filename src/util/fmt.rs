@@ -1,6 +1,18 @@
+use chrono::{DateTime, FixedOffset, SecondsFormat, TimeDelta};
+use num_rational::Rational64;
+
 // #todo what about other float types?
-/// Formats a float number, ensures it always has a decimal separator.
+/// Formats a float number, ensures it always has a decimal separator. The
+/// special values are formatted as the named literals `+inf`, `-inf` and `nan`.
 pub fn format_float(n: f64) -> String {
+    if n.is_nan() {
+        return "nan".to_owned();
+    }
+
+    if n.is_infinite() {
+        return if n > 0.0 { "+inf" } else { "-inf" }.to_owned();
+    }
+
     let s = n.to_string();
 
     if !s.contains('.') {
@@ -10,4 +22,55 @@ pub fn format_float(n: f64) -> String {
     }
 }
 
+/// Formats a ratio number, e.g. `1/3`, the denominator is always included.
+pub fn format_ratio(n: &Rational64) -> String {
+    format!("{}/{}", n.numer(), n.denom())
+}
+
+/// Formats an instant in RFC 3339 (ISO 8601) notation, e.g.
+/// `2024-01-15T10:30:00Z`.
+pub fn format_instant(t: &DateTime<FixedOffset>) -> String {
+    t.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// Formats a duration in ISO 8601 notation, e.g. `PT1H30M` or `-P1DT0.5S`.
+pub fn format_duration(d: &TimeDelta) -> String {
+    if d.is_zero() {
+        return "PT0S".to_owned();
+    }
+
+    let sign = if *d < TimeDelta::zero() { "-" } else { "" };
+    let d = d.abs();
+
+    let days = d.num_days();
+    let hours = d.num_hours() % 24;
+    let minutes = d.num_minutes() % 60;
+    let seconds = d.num_seconds() % 60;
+    let nanos = d.subsec_nanos();
+
+    let mut s = format!("{sign}P");
+
+    if days > 0 {
+        s.push_str(&format!("{days}D"));
+    }
+
+    if hours > 0 || minutes > 0 || seconds > 0 || nanos > 0 {
+        s.push('T');
+        if hours > 0 {
+            s.push_str(&format!("{hours}H"));
+        }
+        if minutes > 0 {
+            s.push_str(&format!("{minutes}M"));
+        }
+        if nanos > 0 {
+            let fraction = format!("{nanos:09}");
+            s.push_str(&format!("{seconds}.{}S", fraction.trim_end_matches('0')));
+        } else if seconds > 0 {
+            s.push_str(&format!("{seconds}S"));
+        }
+    }
+
+    s
+}
+
 // #todo move format_error_pretty here?
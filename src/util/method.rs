@@ -5,6 +5,7 @@
 use crate::{
     context::Context,
     expr::{format_value, Expr},
+    symbol::Symbol,
 };
 
 // #todo write unit test!
@@ -43,18 +44,19 @@ pub fn compute_signature_from_annotations(expr: &Expr) -> Option<String> {
 
 // #todo signature should also encode the return type!!
 // #todo how to handle VARARG functions ?!?!
-pub fn compute_dyn_signature(args: &[Expr], context: &Context) -> String {
-    let mut signature = Vec::new();
+/// Returns the types of the arguments, see `Symbol::method`.
+pub fn compute_dyn_signature(args: &[Expr], context: &Context) -> Vec<Symbol> {
+    let mut signature = Vec::with_capacity(args.len());
 
     for arg in args {
         let typ = arg.dyn_type(context);
-        let Expr::Type(typ) = typ else {
+        let Expr::Type(typ) = typ.unpack() else {
             panic!("invalid dynamic type: {typ:?}");
         };
-        signature.push(typ)
+        signature.push(*typ)
     }
 
-    signature.join("$$")
+    signature
 }
 
 #[cfg(test)]
@@ -0,0 +1,595 @@
+pub mod compiler;
+pub mod op;
+
+use std::{borrow::Cow, collections::HashMap, rc::Rc, sync::Arc};
+
+use indexmap::IndexMap;
+
+use crate::{
+    context::Context,
+    error::{Error, ErrorFrame, ErrorVariant},
+    eval::{
+        bind_func_args, eval_invocation, eval_local_symbol, eval_symbol, insert_binding,
+        resolve_invocable, update_binding,
+        util::{anchor_error, anchor_error_to_range, get_current_file_path, push_call_frame},
+        TailCall,
+    },
+    expr::{check_key, is_truthy, Expr},
+    scope::Scope,
+};
+
+use self::{
+    compiler::{compile, compile_func_body},
+    op::{Chunk, Op},
+};
+
+// #insight
+// The VM is an alternative execution engine, selected with `Context.engine`.
+// It executes the output of `api::compile` with the same observable
+// semantics as `eval`, errors carry the same ranges and call stack.
+
+// #insight
+// Calls to Tan functions do not recurse on the native stack, they push a VM
+// frame. Tail calls replace the current frame.
+
+// #todo Cache the compiled chunks across evaluations, e.g. in the Module.
+
+/// A `break`/`continue` handler, installed by `while`.
+#[derive(Debug)]
+struct Handler {
+    on_break: usize,
+    on_continue: usize,
+    stack_len: usize,
+    callees_len: usize,
+    scopes_len: usize,
+}
+
+/// The state of a function invocation.
+#[derive(Debug)]
+struct Invocation {
+    /// The scope of the caller, restored on return.
+    prev_scope: Arc<Scope>,
+    file_path: Rc<str>,
+    /// The call-site of the last tail call.
+    tail_frame: Option<ErrorFrame>,
+}
+
+#[derive(Debug)]
+struct Frame {
+    chunk: Rc<Chunk>,
+    pc: usize,
+    stack_base: usize,
+    callees_base: usize,
+    /// The scopes saved by PushScope.
+    scopes: Vec<Arc<Scope>>,
+    handlers: Vec<Handler>,
+    /// None for the top-level frame.
+    invocation: Option<Invocation>,
+}
+
+/// A compiled function body.
+#[derive(Debug)]
+struct FuncChunk {
+    // #insight The body is kept alive, the address is not reused.
+    _body: Arc<Vec<Expr>>,
+    chunk: Rc<Chunk>,
+    // #insight A body is created with the function, the file path is the same.
+    file_path: Rc<str>,
+}
+
+/// A stack-based virtual machine.
+#[derive(Debug, Default)]
+pub struct Vm {
+    stack: Vec<Expr>,
+    callees: Vec<Arc<Expr>>,
+    frames: Vec<Frame>,
+    /// The compiled function bodies, keyed by the address of the body.
+    func_chunks: HashMap<usize, FuncChunk>,
+}
+
+fn pop(stack: &mut Vec<Expr>) -> Expr {
+    stack.pop().expect("the VM stack should not be empty")
+}
+
+/// Compiles and executes the expression, in a new VM.
+pub fn eval(expr: &Expr, context: &mut Context) -> Result<Expr, Error> {
+    Vm::new().eval(expr, context)
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compiles and executes the expression.
+    pub fn eval(&mut self, expr: &Expr, context: &mut Context) -> Result<Expr, Error> {
+        context.take_tail_position();
+
+        let chunk = Rc::new(compile(expr));
+
+        self.stack.clear();
+        self.callees.clear();
+        self.frames.clear();
+        self.frames.push(Frame {
+            chunk,
+            pc: 0,
+            stack_base: 0,
+            callees_base: 0,
+            scopes: Vec::new(),
+            handlers: Vec::new(),
+            invocation: None,
+        });
+
+        self.run(context)
+    }
+
+    fn run(&mut self, context: &mut Context) -> Result<Expr, Error> {
+        loop {
+            let frame = self.frames.last_mut().expect("the VM should have a frame");
+            let chunk = frame.chunk.clone();
+            let op = chunk.ops[frame.pc];
+            frame.pc += 1;
+
+            let result = match op {
+                Op::Const(index) => {
+                    self.stack.push(chunk.constants[index].clone());
+                    Ok(())
+                }
+                Op::Load(index) => eval_symbol(&chunk.constants[index], context)
+                    .map(|value| self.stack.push(value)),
+                Op::LoadLocal(index) => eval_local_symbol(&chunk.constants[index], context)
+                    .map(|value| self.stack.push(value)),
+                Op::Pop => {
+                    self.stack.pop();
+                    Ok(())
+                }
+                Op::Jump(target) => {
+                    frame.pc = target;
+                    Ok(())
+                }
+                Op::Step(index) => context.consume_step(Some(&chunk.constants[index])),
+                Op::JumpIfFalse(target) => {
+                    let predicate = pop(&mut self.stack);
+                    if !is_truthy(&predicate) {
+                        frame.pc = target;
+                    }
+                    Ok(())
+                }
+                Op::PushScope => {
+                    let prev_scope = context.scope.clone();
+                    context.scope = Arc::new(Scope::new(prev_scope.clone()));
+                    frame.scopes.push(prev_scope);
+                    Ok(())
+                }
+                Op::PopScope => {
+                    context.scope = frame.scopes.pop().expect("PopScope should match PushScope");
+                    Ok(())
+                }
+                Op::Let { name, op } => {
+                    let value = pop(&mut self.stack);
+                    let value = Expr::maybe_annotated(value, chunk.constants[op].annotations());
+                    insert_binding(&chunk.constants[name], value, context)
+                }
+                Op::StoreLocal { name, op } => {
+                    let value = pop(&mut self.stack);
+                    let value = Expr::maybe_annotated(value, chunk.constants[op].annotations());
+                    let Expr::LocalSymbol(sym, depth, index) = chunk.constants[name].unpack()
+                    else {
+                        unreachable!("StoreLocal should address a local symbol");
+                    };
+                    let value = Arc::new(value);
+                    if context.scope.set_slot(*depth, *index, sym, value.clone()) {
+                        Ok(())
+                    } else {
+                        let value = Arc::unwrap_or_clone(value);
+                        insert_binding(&chunk.constants[name], value, context)
+                    }
+                }
+                Op::Assign(name) => {
+                    let value = pop(&mut self.stack);
+                    update_binding(&chunk.constants[name], value, context);
+                    Ok(())
+                }
+                Op::Callee { name, fallback } => {
+                    let name = chunk.constants[name]
+                        .as_symbolic()
+                        .expect("the callee should be a symbol");
+                    // #insight Dynamic scoping is not supported in this position, like eval.
+                    if let Some(value) = context.scope.get(name) {
+                        self.callees.push(value);
+                    } else {
+                        frame.pc = fallback;
+                    }
+                    Ok(())
+                }
+                Op::Call { expr, argc } => self.call(&chunk.constants[expr], argc, false, context),
+                Op::TailCall { expr, argc } => {
+                    self.call(&chunk.constants[expr], argc, true, context)
+                }
+                Op::Eval(index) => crate::eval::eval(&chunk.constants[index], context)
+                    .map(|value| self.stack.push(value)),
+                Op::TailEval(index) => {
+                    context.is_tail_position = true;
+                    match crate::eval::eval(&chunk.constants[index], context) {
+                        Ok(value) => match context.take_tail_call() {
+                            // #insight The evaluator defers the calls in tail
+                            // position, the frame is replaced here.
+                            Some(tail_call) => {
+                                let tail_frame = TailCall::frame(
+                                    tail_call.name,
+                                    tail_call.range,
+                                    get_current_file_path(context),
+                                );
+                                self.tail_call(&tail_call.func, tail_call.args, tail_frame, context)
+                            }
+                            None => {
+                                self.stack.push(value);
+                                Ok(())
+                            }
+                        },
+                        Err(error) => Err(error),
+                    }
+                }
+                Op::PushHandler {
+                    on_break,
+                    on_continue,
+                } => {
+                    frame.handlers.push(Handler {
+                        on_break,
+                        on_continue,
+                        stack_len: self.stack.len(),
+                        callees_len: self.callees.len(),
+                        scopes_len: frame.scopes.len(),
+                    });
+                    Ok(())
+                }
+                Op::PopHandler => {
+                    frame.handlers.pop();
+                    Ok(())
+                }
+                Op::And { expr, target } | Op::Or { expr, target } => {
+                    let is_and = matches!(op, Op::And { .. });
+                    let value = pop(&mut self.stack);
+                    match value.as_bool() {
+                        Some(predicate) => {
+                            if predicate != is_and {
+                                frame.pc = target;
+                            }
+                            Ok(())
+                        }
+                        None => Err(Error::invalid_arguments(
+                            &format!(
+                                "`{}` argument should be boolean",
+                                if is_and { "and" } else { "or" }
+                            ),
+                            chunk.constants[expr].range(),
+                        )),
+                    }
+                }
+                Op::MakeArray(len) => {
+                    let items = self.stack.split_off(self.stack.len() - len);
+                    self.stack.push(Expr::array(items));
+                    Ok(())
+                }
+                Op::MakeMap(len) => {
+                    let items = self.stack.split_off(self.stack.len() - 2 * len);
+                    items
+                        .iter()
+                        .step_by(2)
+                        .try_for_each(|key| check_key(key, None))
+                        .map(|_| {
+                            let mut items = items.into_iter();
+                            let mut map = IndexMap::with_capacity(len);
+                            while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                                map.insert(key, value);
+                            }
+                            self.stack.push(Expr::map(map));
+                        })
+                }
+                Op::Return => {
+                    let value = pop(&mut self.stack);
+                    let frame = self.frames.pop().expect("the VM should have a frame");
+                    let Some(invocation) = frame.invocation else {
+                        return Ok(value);
+                    };
+                    context.scope = invocation.prev_scope;
+                    self.stack.push(value);
+                    Ok(())
+                }
+            };
+
+            if let Err(error) = result {
+                self.unwind(error, context)?;
+            }
+        }
+    }
+
+    /// Invokes the callee, at the call-site `expr`, with the arguments on the
+    /// stack.
+    fn call(
+        &mut self,
+        expr: &Expr,
+        argc: usize,
+        is_tail: bool,
+        context: &mut Context,
+    ) -> Result<(), Error> {
+        let base = self.stack.len() - argc;
+        let value = self.callees.pop().expect("Call should match Callee");
+
+        // #insight The call-site is a list with a symbolic operator.
+        let op = &expr.as_list().expect("the call-site should be a list")[0];
+        let name = op.as_symbolic().expect("the operator should be a symbol");
+
+        let head = resolve_invocable(op, &name, &value, &self.stack[base..], context)?;
+
+        if !matches!(head.unpack(), Expr::Func(..)) {
+            // #insight The args are borrowed from the stack, e.g. by a foreign function.
+            let args = Cow::Borrowed(&self.stack[base..]);
+            let value = eval_invocation(expr, op, &head, args, is_tail, context);
+            self.stack.truncate(base);
+            self.stack.push(value?);
+            return Ok(());
+        }
+
+        let args = self.stack.split_off(base);
+
+        if is_tail {
+            let tail_frame = ErrorFrame {
+                name: op
+                    .as_symbolic()
+                    .map_or_else(|| "<anonymous>".to_owned(), String::from),
+                file_path: get_current_file_path(context),
+                range: expr.range().or_else(|| op.range()),
+            };
+            self.tail_call(&head, args, tail_frame, context)
+        } else {
+            self.call_func(&head, args, context)
+        }
+    }
+
+    /// Pushes a frame for the invocation of the function.
+    fn call_func(
+        &mut self,
+        func: &Expr,
+        args: Vec<Expr>,
+        context: &mut Context,
+    ) -> Result<(), Error> {
+        let Expr::Func(params, body, func_scope, file_path) = func.unpack() else {
+            return Err(Error::invalid_arguments("should be a Func", func.range()));
+        };
+
+        let (chunk, func_file_path) = self.func_chunk(body, file_path);
+
+        self.frames.push(Frame {
+            chunk,
+            pc: 0,
+            stack_base: self.stack.len(),
+            callees_base: self.callees.len(),
+            scopes: Vec::new(),
+            handlers: Vec::new(),
+            invocation: Some(Invocation {
+                prev_scope: context.scope.clone(),
+                file_path: func_file_path,
+                tail_frame: None,
+            }),
+        });
+
+        bind_func_args(params, func_scope, file_path, args, context)
+    }
+
+    /// Replaces the current frame with a frame for the invocation of the
+    /// function.
+    fn tail_call(
+        &mut self,
+        func: &Expr,
+        args: Vec<Expr>,
+        tail_frame: ErrorFrame,
+        context: &mut Context,
+    ) -> Result<(), Error> {
+        let Expr::Func(params, body, func_scope, file_path) = func.unpack() else {
+            return Err(Error::invalid_arguments("should be a Func", func.range()));
+        };
+
+        let (chunk, func_file_path) = self.func_chunk(body, file_path);
+
+        let frame = self.frames.last_mut().expect("the VM should have a frame");
+        let Some(invocation) = &mut frame.invocation else {
+            // #insight Not reachable, tail calls are compiled only in function bodies.
+            return Err(Error::invalid_arguments(
+                "tail call outside of function",
+                func.range(),
+            ));
+        };
+
+        invocation.file_path = func_file_path;
+        invocation.tail_frame = Some(tail_frame);
+        frame.chunk = chunk;
+        frame.pc = 0;
+        frame.scopes.clear();
+        frame.handlers.clear();
+        self.stack.truncate(frame.stack_base);
+        self.callees.truncate(frame.callees_base);
+
+        bind_func_args(params, func_scope, file_path, args, context)
+    }
+
+    fn func_chunk(&mut self, body: &Arc<Vec<Expr>>, file_path: &str) -> (Rc<Chunk>, Rc<str>) {
+        let key = Arc::as_ptr(body) as usize;
+        let func_chunk = self.func_chunks.entry(key).or_insert_with(|| FuncChunk {
+            _body: body.clone(),
+            chunk: Rc::new(compile_func_body(body)),
+            file_path: Rc::from(file_path),
+        });
+        (func_chunk.chunk.clone(), func_chunk.file_path.clone())
+    }
+
+    /// Unwinds the frames until the error is handled, returns the error if
+    /// it reaches the top-level frame.
+    fn unwind(&mut self, mut error: Error, context: &mut Context) -> Result<(), Error> {
+        loop {
+            let frame = self.frames.last_mut().expect("the VM should have a frame");
+
+            // #insight A new frame (pc == 0) has no anchors, e.g. when binding the args.
+            if frame.pc > 0 {
+                let pc = frame.pc - 1;
+                // #insight The nested regions precede their parents.
+                for (start, end, range) in &frame.chunk.anchors {
+                    if (*start..*end).contains(&pc) {
+                        anchor_error_to_range(&mut error, range.clone());
+                    }
+                }
+            }
+
+            let is_break = matches!(error.variant, ErrorVariant::BreakCF(..));
+            if is_break || matches!(error.variant, ErrorVariant::ContinueCF) {
+                if let Some(handler) = frame.handlers.pop() {
+                    // #insight Like eval, the scope is not restored here.
+                    frame.scopes.truncate(handler.scopes_len);
+                    self.stack.truncate(handler.stack_len);
+                    self.callees.truncate(handler.callees_len);
+                    frame.pc = if is_break {
+                        handler.on_break
+                    } else {
+                        handler.on_continue
+                    };
+                    return Ok(());
+                }
+            }
+
+            if frame.invocation.is_some() {
+                match error.variant {
+                    ErrorVariant::ReturnCF(value) => {
+                        let frame = self.frames.pop().unwrap();
+                        context.scope = frame.invocation.unwrap().prev_scope;
+                        self.stack.truncate(frame.stack_base);
+                        self.callees.truncate(frame.callees_base);
+                        self.stack.push(*value);
+                        return Ok(());
+                    }
+                    variant => error.variant = variant,
+                }
+            }
+
+            let frame = self.frames.pop().unwrap();
+            let Some(invocation) = frame.invocation else {
+                return Err(error);
+            };
+
+            // The error propagates out of the function invocation.
+
+            if !error.has_file_path() {
+                error.file_path = invocation.file_path.to_string();
+            }
+
+            context.scope = invocation.prev_scope;
+            self.stack.truncate(frame.stack_base);
+            self.callees.truncate(frame.callees_base);
+
+            if let Some(tail_frame) = invocation.tail_frame {
+                if !error.variant.is_control_flow() {
+                    error.push_stack_frame(tail_frame);
+                }
+            }
+
+            if let ErrorVariant::Panic(msg) = &error.variant {
+                if context.abort_on_panic {
+                    panic!("{}", msg);
+                }
+            }
+
+            // Anchor the error to the call-site, in the caller frame.
+
+            let caller = self.frames.last().expect("the caller should have a frame");
+            let (Op::Call { expr, .. } | Op::TailCall { expr, .. }) =
+                caller.chunk.ops[caller.pc - 1]
+            else {
+                unreachable!("the caller should be at a Call op");
+            };
+            let chunk = caller.chunk.clone();
+            let expr = &chunk.constants[expr];
+            let op = &expr.as_list().expect("the call-site should be a list")[0];
+            let result = anchor_error(Err(error), expr);
+            let Err(caller_error) = push_call_frame(result, op, expr, context) else {
+                unreachable!();
+            };
+            error = caller_error;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use crate::{
+        api::eval_string,
+        context::{Context, ContextBuilder, Engine},
+        error::ErrorVariant,
+        expr::Expr,
+    };
+
+    fn vm_context() -> Context {
+        let context = ContextBuilder::new()
+            .root_path("/some/tan/root")
+            .engine(Engine::Vm)
+            .try_build()
+            .unwrap();
+        context.register("+", |a: i64, b: i64| a + b);
+        context.register("<", |a: i64, b: i64| a < b);
+        context.register(">", |a: i64, b: i64| a > b);
+        context.register("=", |a: i64, b: i64| a == b);
+        context
+    }
+
+    #[test]
+    fn vm_evaluates_control_flow() {
+        let mut context = vm_context();
+        let input = r#"
+        (let sum (Func [xs]
+            (let total 0)
+            (let i 0)
+            (while (< i 3)
+                (<- total (+ total (xs i)))
+                (<- i (+ i 1))
+            )
+            (if (and (> total 5) (or false true)) total (else 0))
+        ))
+        (sum [1 2 3])
+        "#;
+        let value = eval_string(input, &mut context).unwrap();
+        assert_matches!(value.unpack(), Expr::Int(6));
+    }
+
+    #[test]
+    fn vm_handles_break_continue_and_return() {
+        let mut context = vm_context();
+        let input = r#"
+        (let find (Func [n]
+            (let i 0)
+            (while true
+                (<- i (+ i 1))
+                (if (= i n) (return i))
+            )
+        ))
+        (find 4)
+        "#;
+        let value = eval_string(input, &mut context).unwrap();
+        assert_matches!(value.unpack(), Expr::Int(4));
+    }
+
+    #[test]
+    fn vm_errors_carry_the_call_stack() {
+        let mut context = vm_context();
+        let input = r#"
+        (let inner (Func [] (panic! "early exit")))
+        (let outer (Func [] (inner)))
+        (outer)
+        "#;
+        let errors = eval_string(input, &mut context).unwrap_err();
+        let error = errors.first().unwrap();
+        assert_matches!(&error.variant, ErrorVariant::Panic(msg) if msg == "early exit");
+
+        let names: Vec<&str> = error.stack().iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["inner", "outer"]);
+    }
+}
@@ -0,0 +1,421 @@
+use crate::{
+    eval::is_constant,
+    expr::Expr,
+    util::{is_reserved_symbol, try_lock_read},
+};
+
+use super::op::{Chunk, Op};
+
+// #insight
+// The compiler lowers the most frequent forms (symbols, literals, calls, do,
+// if, let, <-, while, and, or) to bytecode. All other forms are compiled to an
+// Eval op, i.e. they are delegated to the tree-walking evaluator, this keeps
+// the semantics of the two engines in sync. The delegated forms include the
+// special forms not listed above (e.g. for, cond, try, Func, use), calls with
+// a non-symbolic head, and the non-constant persistent collections. A program
+// that mostly consists of such forms runs at the speed of the evaluator.
+
+// #todo Compile more special forms natively, e.g. for, cond, when.
+// #todo The call-site expressions are cloned into the constants pool, avoid the deep clones.
+
+/// Compiles a top-level expression.
+pub fn compile(expr: &Expr) -> Chunk {
+    let mut compiler = Compiler::default();
+    // #insight The whole chunk is anchored, the errors of the unanchored ops
+    // fall back to the range of the top-level expression.
+    compiler.anchored(expr, |c| {
+        c.compile_expr(expr, false);
+        c.chunk.push_op(Op::Return);
+    });
+    compiler.chunk
+}
+
+/// Compiles the body of a function, the last expression is compiled in tail
+/// position.
+pub fn compile_func_body(body: &[Expr]) -> Chunk {
+    let mut compiler = Compiler::default();
+    compiler.compile_block(body, true);
+    compiler.chunk.push_op(Op::Return);
+    compiler.chunk
+}
+
+/// Returns true if the `<-` arguments are a name and a value, the other forms
+/// are delegated to report the error.
+fn is_assignable(args: &[Expr]) -> bool {
+    args.len() == 2 && matches!(args[0].unpack(), Expr::Symbol(..) | Expr::LocalSymbol(..))
+}
+
+#[derive(Default)]
+struct Compiler {
+    chunk: Chunk,
+}
+
+impl Compiler {
+    fn compile_expr(&mut self, expr: &Expr, is_tail: bool) {
+        match expr.unpack() {
+            Expr::Symbol(..) => {
+                let index = self.chunk.push_constant(expr.clone());
+                self.chunk.push_op(Op::Load(index));
+            }
+            Expr::LocalSymbol(..) => {
+                let index = self.chunk.push_constant(expr.clone());
+                self.chunk.push_op(Op::LoadLocal(index));
+            }
+            Expr::List(list) => self.compile_list(expr, list, is_tail),
+            Expr::Array(items) => {
+                let Ok(items) = try_lock_read(items, expr.range()) else {
+                    self.compile_eval(expr, is_tail);
+                    return;
+                };
+                self.anchored(expr, |c| {
+                    for item in items.iter() {
+                        c.compile_expr(item, false);
+                    }
+                    c.chunk.push_op(Op::MakeArray(items.len()));
+                });
+            }
+            Expr::Map(map) => {
+                let Ok(map) = try_lock_read(map, expr.range()) else {
+                    self.compile_eval(expr, is_tail);
+                    return;
+                };
+                self.anchored(expr, |c| {
+                    for (key, value) in map.iter() {
+                        let index = c.chunk.push_constant(key.clone());
+                        c.chunk.push_op(Op::Const(index));
+                        c.compile_expr(value, false);
+                    }
+                    c.chunk.push_op(Op::MakeMap(map.len()));
+                });
+            }
+            Expr::Vector(..) | Expr::PersistentMap(..) | Expr::PersistentSet(..) => {
+                if is_constant(expr) {
+                    let index = self.chunk.push_constant(expr.unpack().clone());
+                    self.chunk.push_op(Op::Const(index));
+                } else {
+                    self.compile_eval(expr, is_tail);
+                }
+            }
+            Expr::If(predicate, true_clause, false_clause) => self.anchored(expr, |c| {
+                c.compile_expr(predicate, false);
+                let jump_to_else = c.chunk.push_op(Op::JumpIfFalse(0));
+                c.compile_expr(true_clause, is_tail);
+                let jump_to_end = c.chunk.push_op(Op::Jump(0));
+                c.chunk.patch_jump(jump_to_else);
+                if let Some(false_clause) = false_clause {
+                    c.compile_expr(false_clause, is_tail);
+                } else {
+                    let index = c.chunk.push_constant(Expr::None);
+                    c.chunk.push_op(Op::Const(index));
+                }
+                c.chunk.patch_jump(jump_to_end);
+            }),
+            _ => {
+                // Other expression variants evaluate to themselves.
+                let index = self.chunk.push_constant(expr.clone());
+                self.chunk.push_op(Op::Const(index));
+            }
+        }
+    }
+
+    fn compile_list(&mut self, expr: &Expr, list: &[Expr], is_tail: bool) {
+        let Some((op, args)) = list.split_first() else {
+            // () == None
+            let index = self.chunk.push_constant(Expr::None);
+            self.chunk.push_op(Op::Const(index));
+            return;
+        };
+
+        if let Expr::Symbol(name) = op.unpack() {
+            match name.as_str() {
+                "do" => return self.anchored(expr, |c| c.compile_do(args, is_tail)),
+                "if" if !args.is_empty() => {
+                    return self.anchored(expr, |c| c.compile_if(args, is_tail));
+                }
+                "let" => return self.anchored(expr, |c| c.compile_let(op, args)),
+                "<-" | "assign" if is_assignable(args) => {
+                    return self.anchored(expr, |c| c.compile_assign(args));
+                }
+                "while" if args.len() > 1 => {
+                    return self.anchored(expr, |c| c.compile_while(args));
+                }
+                "and" => return self.anchored(expr, |c| c.compile_and_or(expr, args, true)),
+                "or" => return self.anchored(expr, |c| c.compile_and_or(expr, args, false)),
+                _ => (),
+            }
+        }
+
+        self.anchored(expr, |c| match op.as_symbolic() {
+            Some(name) if !is_reserved_symbol(&name) => c.compile_call(expr, op, args, is_tail),
+            _ => c.compile_eval(expr, is_tail),
+        });
+    }
+
+    /// Delegates the evaluation of the expression to the evaluator.
+    fn compile_eval(&mut self, expr: &Expr, is_tail: bool) {
+        let index = self.chunk.push_constant(expr.clone());
+        if is_tail {
+            self.chunk.push_op(Op::TailEval(index));
+        } else {
+            self.chunk.push_op(Op::Eval(index));
+        }
+    }
+
+    /// Anchors the errors raised by the ops emitted in `f` to the expression.
+    fn anchored(&mut self, expr: &Expr, f: impl FnOnce(&mut Self)) {
+        let start = self.chunk.offset();
+        f(self);
+        let end = self.chunk.offset();
+        self.chunk.anchors.push((start, end, expr.range()));
+    }
+
+    /// Compiles the expressions in sequence, leaves the value of the last
+    /// expression on the stack.
+    fn compile_block(&mut self, exprs: &[Expr], is_tail: bool) {
+        let Some((last, exprs)) = exprs.split_last() else {
+            let index = self.chunk.push_constant(Expr::None);
+            self.chunk.push_op(Op::Const(index));
+            return;
+        };
+
+        for expr in exprs {
+            self.compile_expr(expr, false);
+            self.chunk.push_op(Op::Pop);
+        }
+
+        // #insight The last expression inherits the tail position.
+        self.compile_expr(last, is_tail);
+    }
+
+    fn compile_do(&mut self, args: &[Expr], is_tail: bool) {
+        self.chunk.push_op(Op::PushScope);
+        self.compile_block(args, is_tail);
+        self.chunk.push_op(Op::PopScope);
+    }
+
+    fn compile_if(&mut self, args: &[Expr], is_tail: bool) {
+        let predicate = &args[0];
+        let body = &args[1..];
+
+        // #insight (else ...) is recognized as the last clause, like eval_if.
+        let else_clause = body.last().and_then(|clause| {
+            let clause = clause.as_list()?;
+            if clause.len() > 1 && clause[0].as_symbol() == Some("else") {
+                Some(clause)
+            } else {
+                None
+            }
+        });
+
+        let body = if else_clause.is_some() {
+            &body[..(body.len() - 1)]
+        } else {
+            body
+        };
+
+        self.compile_expr(predicate, false);
+        let jump_to_else = self.chunk.push_op(Op::JumpIfFalse(0));
+
+        self.compile_do(body, is_tail);
+        let jump_to_end = self.chunk.push_op(Op::Jump(0));
+
+        self.chunk.patch_jump(jump_to_else);
+        if let Some(else_clause) = else_clause {
+            self.compile_do(&else_clause[1..], is_tail);
+        } else {
+            let index = self.chunk.push_constant(Expr::None);
+            self.chunk.push_op(Op::Const(index));
+        }
+        self.chunk.patch_jump(jump_to_end);
+    }
+
+    fn compile_let(&mut self, op: &Expr, args: &[Expr]) {
+        let op = self.chunk.push_constant(op.clone());
+
+        // #insight A trailing name without value is ignored, like eval_let.
+        for pair in args.chunks_exact(2) {
+            self.compile_expr(&pair[1], false);
+            let name = self.chunk.push_constant(pair[0].clone());
+            // #insight The locals resolved to frame slots skip the name lookup.
+            if matches!(pair[0].unpack(), Expr::LocalSymbol(..)) {
+                self.chunk.push_op(Op::StoreLocal { name, op });
+            } else {
+                self.chunk.push_op(Op::Let { name, op });
+            }
+        }
+
+        let index = self.chunk.push_constant(Expr::None);
+        self.chunk.push_op(Op::Const(index));
+    }
+
+    fn compile_assign(&mut self, args: &[Expr]) {
+        self.compile_expr(&args[1], false);
+        let name = self.chunk.push_constant(args[0].clone());
+        self.chunk.push_op(Op::Assign(name));
+
+        let index = self.chunk.push_constant(Expr::None);
+        self.chunk.push_op(Op::Const(index));
+    }
+
+    fn compile_while(&mut self, args: &[Expr]) {
+        let start = self.chunk.offset();
+
+        // #insight Like eval_while, every iteration consumes a step.
+        let predicate = self.chunk.push_constant(args[0].clone());
+        self.chunk.push_op(Op::Step(predicate));
+        self.compile_expr(&args[0], false);
+        let jump_to_end = self.chunk.push_op(Op::JumpIfFalse(0));
+
+        // #insight
+        // Like eval_while, a `break` skips the rest of the body and checks the
+        // predicate again, a `continue` skips to the next body expression.
+        for expr in &args[1..] {
+            let handler = self.chunk.push_op(Op::PushHandler {
+                on_break: start,
+                on_continue: 0,
+            });
+            self.compile_expr(expr, false);
+            self.chunk.push_op(Op::Pop);
+            self.chunk.push_op(Op::PopHandler);
+            let next = self.chunk.offset();
+            if let Op::PushHandler { on_continue, .. } = &mut self.chunk.ops[handler] {
+                *on_continue = next;
+            }
+        }
+
+        self.chunk.push_op(Op::Jump(start));
+        self.chunk.patch_jump(jump_to_end);
+
+        let index = self.chunk.push_constant(Expr::None);
+        self.chunk.push_op(Op::Const(index));
+    }
+
+    fn compile_and_or(&mut self, expr: &Expr, args: &[Expr], is_and: bool) {
+        let form = self.chunk.push_constant(expr.clone());
+
+        let mut short_circuits = Vec::with_capacity(args.len());
+        for arg in args {
+            self.compile_expr(arg, false);
+            let op = if is_and {
+                Op::And {
+                    expr: form,
+                    target: 0,
+                }
+            } else {
+                Op::Or {
+                    expr: form,
+                    target: 0,
+                }
+            };
+            short_circuits.push(self.chunk.push_op(op));
+        }
+
+        let index = self.chunk.push_constant(Expr::Bool(is_and));
+        self.chunk.push_op(Op::Const(index));
+        let jump_to_end = self.chunk.push_op(Op::Jump(0));
+
+        for offset in short_circuits {
+            self.chunk.patch_jump(offset);
+        }
+        let index = self.chunk.push_constant(Expr::Bool(!is_and));
+        self.chunk.push_op(Op::Const(index));
+
+        self.chunk.patch_jump(jump_to_end);
+    }
+
+    fn compile_call(&mut self, expr: &Expr, op: &Expr, args: &[Expr], is_tail: bool) {
+        // #insight
+        // If the operator is not bound, the whole call is delegated to the
+        // evaluator, e.g. to report the undefined symbol.
+
+        let name = self.chunk.push_constant(op.clone());
+        let callee = self.chunk.push_op(Op::Callee { name, fallback: 0 });
+
+        for arg in args {
+            self.compile_expr(arg, false);
+        }
+
+        let call_expr = self.chunk.push_constant(expr.clone());
+        let argc = args.len();
+        if is_tail {
+            self.chunk.push_op(Op::TailCall {
+                expr: call_expr,
+                argc,
+            });
+        } else {
+            self.chunk.push_op(Op::Call {
+                expr: call_expr,
+                argc,
+            });
+        }
+        let jump_to_end = self.chunk.push_op(Op::Jump(0));
+
+        self.chunk.patch_jump(callee);
+        self.compile_eval(expr, is_tail);
+
+        self.chunk.patch_jump(jump_to_end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        api::parse_string, expr::Expr, optimize::optimize, resolve_locals::resolve_locals,
+        vm::op::Op,
+    };
+
+    use super::{compile, compile_func_body};
+
+    #[test]
+    fn compile_lowers_calls_and_delegates_other_forms() {
+        let expr = optimize(parse_string("(if (> x 1) (f x) (else (for [i 3] i)))").unwrap());
+        let chunk = compile(&expr);
+
+        assert!(chunk
+            .ops
+            .iter()
+            .any(|op| matches!(op, Op::Call { argc: 1, .. })));
+        assert!(chunk.ops.iter().any(|op| {
+            matches!(op, Op::Callee { name, .. } if chunk.constants[*name].as_symbol() == Some("f"))
+        }));
+        assert!(chunk.ops.iter().any(|op| matches!(op, Op::Eval(..))));
+        assert_eq!(chunk.ops.last(), Some(&Op::Return));
+
+        // The nested regions precede their parents, the whole chunk is
+        // anchored to the top-level expression.
+        let (start, end, range) = chunk.anchors.last().unwrap();
+        assert_eq!((*start, *end), (0, chunk.ops.len()));
+        assert_eq!(*range, expr.range());
+        assert!(chunk.anchors.len() > 1);
+    }
+
+    #[test]
+    fn compile_lowers_the_let_locals_to_slot_ops() {
+        let expr = parse_string("(Func [x] (let y x) (<- y 2) y)").unwrap();
+        let expr = resolve_locals(optimize(expr));
+        let terms = expr.as_list().expect("the function should be a list");
+        let chunk = compile_func_body(&terms[2..]);
+
+        assert!(chunk
+            .ops
+            .iter()
+            .any(|op| matches!(op, Op::StoreLocal { .. })));
+        assert!(chunk.ops.iter().any(|op| matches!(op, Op::Assign(..))));
+        assert!(!chunk.ops.iter().any(|op| matches!(op, Op::Eval(..))));
+    }
+
+    #[test]
+    fn compile_lowers_the_if_expr() {
+        let expr = Expr::If(
+            Box::new(Expr::symbol("x")),
+            Box::new(Expr::Int(1)),
+            Some(Box::new(Expr::Int(2))),
+        );
+        let chunk = compile(&expr);
+
+        assert!(!chunk.ops.iter().any(|op| matches!(op, Op::Eval(..))));
+        assert!(chunk.ops.iter().any(|op| matches!(op, Op::JumpIfFalse(..))));
+        // The if, and the top-level expression.
+        assert_eq!(chunk.anchors.len(), 2);
+    }
+}
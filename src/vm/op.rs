@@ -0,0 +1,125 @@
+use crate::{expr::Expr, range::Range};
+
+// #insight
+// The operands are indices into the constants pool of the chunk, or jump
+// targets (absolute offsets into the ops of the chunk).
+
+/// A VM instruction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    /// Pushes the constant.
+    Const(usize),
+    /// Pushes the value bound to the symbol constant.
+    Load(usize),
+    /// Pushes the value of the slot addressed by the local symbol constant.
+    LoadLocal(usize),
+    /// Discards the top of the stack.
+    Pop,
+    Jump(usize),
+    /// Consumes a step, e.g. for a loop iteration, the constant anchors the
+    /// `BudgetExhausted` error.
+    Step(usize),
+    /// Pops the predicate, jumps if it's not truthy.
+    JumpIfFalse(usize),
+    /// Enters a nested scope, e.g. for `do`.
+    PushScope,
+    /// Restores the scope saved by the matching PushScope.
+    PopScope,
+    /// Pops the value and binds it to the name constant, the value is
+    /// annotated with the annotations of the `op` constant.
+    Let {
+        name: usize,
+        op: usize,
+    },
+    /// Pops the value and binds it to the slot addressed by the local symbol
+    /// constant, the value is annotated with the annotations of the `op`
+    /// constant.
+    StoreLocal {
+        name: usize,
+        op: usize,
+    },
+    /// Pops the value and assigns it to the name constant, like `<-`.
+    Assign(usize),
+    /// Looks up the operator symbol, jumps to `fallback` if it's not bound.
+    Callee {
+        name: usize,
+        fallback: usize,
+    },
+    /// Invokes the callee with `argc` arguments, `expr` is the call-site.
+    Call {
+        expr: usize,
+        argc: usize,
+    },
+    /// Like Call, in tail position.
+    TailCall {
+        expr: usize,
+        argc: usize,
+    },
+    /// Evaluates the constant with the tree-walking evaluator.
+    Eval(usize),
+    /// Like Eval, in tail position.
+    TailEval(usize),
+    /// Catches `break` and `continue` in the protected ops.
+    PushHandler {
+        on_break: usize,
+        on_continue: usize,
+    },
+    PopHandler,
+    /// Pops a boolean, jumps to `target` if false. `expr` is the `and` form.
+    And {
+        expr: usize,
+        target: usize,
+    },
+    /// Pops a boolean, jumps to `target` if true. `expr` is the `or` form.
+    Or {
+        expr: usize,
+        target: usize,
+    },
+    /// Pops the items and pushes an Array.
+    MakeArray(usize),
+    /// Pops the key/value pairs and pushes a Map.
+    MakeMap(usize),
+    /// Returns the top of the stack to the caller.
+    Return,
+}
+
+/// A compiled unit of bytecode, e.g. a top-level expression or a function
+/// body.
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub ops: Vec<Op>,
+    pub constants: Vec<Expr>,
+    // #insight
+    // Errors raised by the ops in `start..end` are anchored to the range, like
+    // `anchor_error` in eval. Nested regions precede their parents.
+    /// The anchor regions `(start, end, range)`.
+    pub anchors: Vec<(usize, usize, Option<Range>)>,
+}
+
+impl Chunk {
+    pub fn push_op(&mut self, op: Op) -> usize {
+        self.ops.push(op);
+        self.ops.len() - 1
+    }
+
+    pub fn push_constant(&mut self, expr: Expr) -> usize {
+        self.constants.push(expr);
+        self.constants.len() - 1
+    }
+
+    /// Returns the offset of the next op.
+    pub fn offset(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Sets the target of the jump op at `offset` to the next op.
+    pub fn patch_jump(&mut self, offset: usize) {
+        let target = self.offset();
+        match &mut self.ops[offset] {
+            Op::Jump(t) | Op::JumpIfFalse(t) => *t = target,
+            Op::Callee { fallback, .. } => *fallback = target,
+            Op::And { target: t, .. } | Op::Or { target: t, .. } => *t = target,
+            op => panic!("cannot patch non-jump op `{op:?}`"),
+        }
+    }
+}
@@ -6,7 +6,7 @@
 
 use tan::{
     api::{compile_string, eval_string, lex_string, parse_string},
-    context::Context,
+    context::{Context, ContextBuilder, Engine},
     error::Error,
     expr::Expr,
     lexer::token::Token,
@@ -32,17 +32,25 @@ pub fn resolve_file(filename: &str) -> Result<Vec<Expr>, Vec<Error>> {
     compile_string(input, &mut context)
 }
 
-pub fn eval_file(filename: &str) -> Result<Expr, Vec<Error>> {
+/// Creates a context that executes with the given engine.
+pub fn new_context(engine: Engine) -> Context {
+    ContextBuilder::new()
+        .engine(engine)
+        .try_build()
+        .expect("env variable `TAN_ROOT` should be set")
+}
+
+pub fn eval_file(filename: &str, engine: Engine) -> Result<Expr, Vec<Error>> {
     // #todo use eval_module here!!
     let input = &read_file(filename);
-    let mut context = Context::new();
+    let mut context = new_context(engine);
     eval_string(input, &mut context)
 }
 
 // #todo find a better name.
 // #todo move this function to api.rs?
 /// Evaluates an input string. A thin wrapper around eval_string.
-pub fn eval_input(input: &str) -> Result<Expr, Vec<Error>> {
-    let mut context = Context::new();
+pub fn eval_input(input: &str, engine: Engine) -> Result<Expr, Vec<Error>> {
+    let mut context = new_context(engine);
     eval_string(input, &mut context)
 }
@@ -1,24 +1,57 @@
 mod common;
 
+use std::collections::HashMap;
+
 use assert_matches::assert_matches;
+use indexmap::{IndexMap, IndexSet};
 
 use tan::{
     api::eval_string,
-    context::Context,
+    context::{Context, Engine},
     error::{Error, ErrorVariant},
-    eval::eval,
+    eval::{eval, invoke_func},
     expr::Expr,
 };
 
-use crate::common::{eval_file, eval_input, read_file};
+use crate::common::{eval_file, eval_input, new_context, read_file};
+
+// #insight
+// The tests that take an `engine` parameter are registered at the bottom of
+// the file, they run once per engine, as `eval::<name>` and `vm::<name>`.
+macro_rules! engine_tests {
+    ($($(#[$meta:meta])* fn $name:ident;)*) => {
+        mod eval {
+            use tan::context::Engine;
+
+            $(
+                #[test]
+                $(#[$meta])*
+                fn $name() {
+                    super::$name(Engine::Eval);
+                }
+            )*
+        }
+
+        mod vm {
+            use tan::context::Engine;
+
+            $(
+                #[test]
+                $(#[$meta])*
+                fn $name() {
+                    super::$name(Engine::Vm);
+                }
+            )*
+        }
+    };
+}
 
 // #todo Add more tests, especially for error-reporting.
 // #todo Prefer adding tan tests.
 // #todo Convert the remaining tests to Tan.
 
-#[test]
-fn do_reports_intermediate_errors() {
-    let result = eval_file("do-intermediate-error.tan");
+fn do_reports_intermediate_errors(engine: Engine) {
+    let result = eval_file("do-intermediate-error.tan", engine);
 
     assert!(result.is_err());
 
@@ -34,9 +67,8 @@ fn do_reports_intermediate_errors() {
     assert_matches!(err, Error{ variant: ErrorVariant::UndefinedSymbol(s), .. } if s == "write33");
 }
 
-#[test]
-fn eval_processes_keyword_symbols() {
-    let result = eval_input(":key").unwrap();
+fn eval_processes_keyword_symbols(engine: Engine) {
+    let result = eval_input(":key", engine).unwrap();
 
     assert_matches!(result.unpack(), Expr::KeySymbol(x) if x == "key");
 }
@@ -53,9 +85,8 @@ fn eval_processes_empty_list() {
 }
 
 // #keep
-#[test]
-fn eval_reports_let_errors() {
-    let result = eval_input("(do (let if (+ 1 2 3)) a)");
+fn eval_reports_let_errors(engine: Engine) {
+    let result = eval_input("(do (let if (+ 1 2 3)) a)", engine);
 
     assert!(result.is_err());
 
@@ -80,9 +111,8 @@ fn eval_reports_let_errors() {
     assert_eq!(range.end.index, 11);
 }
 
-#[test]
-fn eval_handles_function_with_no_params() {
-    let result = eval_file("func-no-params.tan");
+fn eval_handles_function_with_no_params(engine: Engine) {
+    let result = eval_file("func-no-params.tan", engine);
     assert!(result.is_ok());
 
     let value = format!("{}", result.unwrap());
@@ -90,9 +120,8 @@ fn eval_handles_function_with_no_params() {
     assert_eq!(value, expected_value);
 }
 
-#[test]
-fn eval_processes_map() {
-    let result = eval_file("map.tan");
+fn eval_processes_map(engine: Engine) {
+    let result = eval_file("map.tan", engine);
 
     assert!(result.is_ok());
 
@@ -102,9 +131,8 @@ fn eval_processes_map() {
     assert_eq!(value, expected_value);
 }
 
-#[test]
-fn eval_processes_map_with_keys() {
-    let result = eval_file("key-map.tan");
+fn eval_processes_map_with_keys(engine: Engine) {
+    let result = eval_file("key-map.tan", engine);
 
     assert!(result.is_ok());
 
@@ -114,9 +142,77 @@ fn eval_processes_map_with_keys() {
     assert_eq!(value, expected_value);
 }
 
+fn eval_preserves_the_type_of_map_keys(engine: Engine) {
+    let mut context = new_context(engine);
+
+    let input =
+        r#"(do (let m {1 "int" "1" "string" :k "key"}) (let {:k _} m) [(m 1) (m "1") (m :k) k m])"#;
+    let value = eval_string(input, &mut context).unwrap();
+    let items = value.as_array().unwrap();
+
+    assert_matches!(items[0].unpack(), Expr::String(s) if s == "int");
+    assert_matches!(items[1].unpack(), Expr::String(s) if s == "string");
+    assert_matches!(items[2].unpack(), Expr::String(s) if s == "key");
+    assert_matches!(items[3].unpack(), Expr::String(s) if s == "key");
+
+    let map = items[4].as_map().unwrap();
+    assert_eq!(map.len(), 3);
+    assert!(map.contains_key(&Expr::Int(1)));
+    assert!(map.contains_key(&Expr::string("1")));
+    assert!(map.contains_key(&Expr::key_symbol("k")));
+    drop(map);
+
+    // The keys are formatted with their type.
+    let text = items[4].to_string();
+    assert!(text.contains(r#"1 "int""#));
+    assert!(text.contains(r#""1" "string""#));
+    assert!(text.contains(r#":k "key""#));
+}
+
+fn eval_rejects_reference_values_as_map_keys(engine: Engine) {
+    let mut context = new_context(engine);
+
+    // Arrays are mutable and cannot be compared, so they would collapse into
+    // a single entry.
+    for input in [r#"{[1] "x" [2] "y"}"#, r#"{{1 2} "x"}"#] {
+        let result = eval_string(input, &mut context);
+        let errors = result.unwrap_err();
+        assert_matches!(errors[0].variant(), ErrorVariant::InvalidArguments);
+        assert!(errors[0].range().is_some());
+    }
+
+    // Immutable collections are valid keys.
+    let value = eval_string(r#"{'(1) "x" '(2) "y"}"#, &mut context).unwrap();
+    assert_eq!(value.as_map().unwrap().len(), 2);
+
+    // NaN is not equal to itself, every entry would get a separate key.
+    let inputs = [
+        "{nan 1 nan 2}",
+        "#PersistentMap {nan 1}",
+        "#PersistentSet [1 nan]",
+        "{'(1 nan) 1}",
+    ];
+    for input in inputs {
+        let result = eval_string(input, &mut context);
+        let errors = result.unwrap_err();
+        assert_matches!(errors[0].variant(), ErrorVariant::InvalidArguments);
+        assert!(errors[0].range().is_some());
+    }
+}
+
 #[test]
-fn eval_processes_multiline_strings() {
-    let result = eval_file("multi-line-string.tan");
+fn map_keys_ignore_annotations() {
+    let annotations = HashMap::from([("type".to_string(), Expr::typ("Int"))]);
+    let annotated = Expr::annotated(Expr::Int(1), &annotations);
+    assert_eq!(annotated, Expr::Int(1));
+    assert_eq!(Expr::Int(1), annotated);
+
+    let map = IndexMap::from([(annotated, Expr::string("one"))]);
+    assert_eq!(map.get(&Expr::Int(1)), Some(&Expr::string("one")));
+}
+
+fn eval_processes_multiline_strings(engine: Engine) {
+    let result = eval_file("multi-line-string.tan", engine);
 
     assert!(result.is_ok());
 
@@ -126,9 +222,8 @@ fn eval_processes_multiline_strings() {
     assert_eq!(value, expected_value);
 }
 
-#[test]
-fn eval_processes_multiline_text() {
-    let result = eval_file("multi-line-text.tan");
+fn eval_processes_multiline_text(engine: Engine) {
+    let result = eval_file("multi-line-text.tan", engine);
 
     assert!(result.is_ok());
 
@@ -138,9 +233,8 @@ fn eval_processes_multiline_text() {
     assert_eq!(value, expected_value);
 }
 
-#[test]
-fn eval_processes_deep_data() {
-    let result = eval_file("data.tan");
+fn eval_processes_deep_data(engine: Engine) {
+    let result = eval_file("data.tan", engine);
 
     assert!(result.is_ok());
 
@@ -150,38 +244,504 @@ fn eval_processes_deep_data() {
     assert_eq!(value, expected_value);
 }
 
-#[test]
-fn should_eval_panic() {
-    let result = eval_file("panic.tan");
+fn should_eval_panic(engine: Engine) {
+    let result = eval_file("panic.tan", engine);
     let error = result.unwrap_err();
     let error = error.first().unwrap();
     assert_matches!(error.variant, ErrorVariant::Panic(..));
 }
 
-#[test]
-fn eval_should_report_errors_in_function_incovations() {
-    let result = eval_file("func-error.tan");
+fn eval_should_report_errors_in_function_incovations(engine: Engine) {
+    let result = eval_file("func-error.tan", engine);
     assert!(result.is_err());
 }
 
-#[test]
-fn eval_should_report_undefined_symbol_errors() {
-    let result = eval_file("undefined.tan");
+fn eval_should_report_undefined_symbol_errors(engine: Engine) {
+    let result = eval_file("undefined.tan", engine);
     assert!(result.is_err());
 }
 
-#[test]
-fn eval_should_report_errors_in_args() {
-    let result = eval_file("array-errors.tan");
+fn eval_should_report_errors_in_args(engine: Engine) {
+    let result = eval_file("array-errors.tan", engine);
     assert!(result.is_err());
 }
 
-#[test]
-fn eval_should_handle_func_def_with_annotation() {
+fn eval_should_handle_func_def_with_annotation(engine: Engine) {
     let input = &read_file("func-with-ann.tan");
-    let mut context = Context::new();
+    let mut context = new_context(engine);
     let _ = eval_string(input, &mut context);
     assert!(context.scope.contains_name("relu$$Float"));
     // #insight Verifies hack-fix for method lookup.
     assert!(context.scope.contains_name("relu"));
 }
+
+fn eval_try_catches_errors(engine: Engine) {
+    let result = eval_input(
+        r#"
+        (try
+            (undefined-func 1 2)
+            (catch err
+                (err :variant)
+            )
+        )
+        "#,
+        engine,
+    );
+    let value = result.unwrap();
+    assert_matches!(value.unpack(), Expr::String(s) if s == "UndefinedSymbol");
+
+    let result = eval_input(
+        r#"
+        (try
+            "no error"
+            (catch _ "error")
+        )
+        "#,
+        engine,
+    );
+    let value = result.unwrap();
+    assert_matches!(value.unpack(), Expr::String(s) if s == "no error");
+}
+
+fn eval_try_binds_the_error_with_type(engine: Engine) {
+    let mut context = new_context(engine);
+    let input = r#"
+    (let message (try (use "/missing/module") (catch err (err :message))))
+    (let caught (try (use "/missing/module") (catch err err)))
+    "#;
+    eval_string(input, &mut context).unwrap();
+
+    let message = context.scope.get("message").unwrap();
+    assert_matches!(message.unpack(), Expr::String(s) if s == "failed use `/missing/module`");
+
+    let caught = context.scope.get("caught").unwrap();
+    assert_matches!(caught.dyn_type(&context).unpack(), Expr::Type(s) if s == "Error");
+}
+
+fn eval_try_requires_catch_clause(engine: Engine) {
+    let result = eval_input("(try (undefined-func))", engine);
+    assert!(result.is_err());
+}
+
+fn eval_panic_unwinds_to_the_embedder(engine: Engine) {
+    let input = r#"
+    (let inner (Func [] (panic! "early exit")))
+    (let outer (Func [] (inner)))
+    (outer)
+    "#;
+    let result = eval_input(input, engine);
+    let errors = result.unwrap_err();
+    let error = errors.first().unwrap();
+    assert_matches!(&error.variant, ErrorVariant::Panic(msg) if msg == "early exit");
+    assert!(error.range().is_some());
+
+    let names: Vec<&str> = error
+        .stack()
+        .iter()
+        .map(|frame| frame.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["inner", "outer"]);
+}
+
+/// A foreign higher-order function, invokes the function argument.
+fn call_with_one(args: &[Expr], context: &mut Context) -> Result<Expr, Error> {
+    invoke_func(&args[0], vec![Expr::Int(1)], context)
+}
+
+fn eval_errors_carry_the_foreign_call_stack(engine: Engine) {
+    let mut context = new_context(engine);
+    context.scope.insert_invocable(
+        "call-with-one$$*",
+        Expr::foreign_func_mut_context(&call_with_one),
+    );
+
+    let input = r#"
+    (let f (Func [x] (panic! "early exit")))
+    (let g (Func [] (call-with-one f)))
+    (g)
+    "#;
+    let errors = eval_string(input, &mut context).unwrap_err();
+    let error = errors.first().unwrap();
+    assert_matches!(&error.variant, ErrorVariant::Panic(msg) if msg == "early exit");
+
+    // The foreign call, and the function it invokes, are on the stack.
+    let names: Vec<&str> = error
+        .stack()
+        .iter()
+        .map(|frame| frame.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["<anonymous>", "call-with-one", "g"]);
+    assert!(error.stack()[1].range.is_some());
+}
+
+fn eval_panic_aborts_if_requested(engine: Engine) {
+    let input = r#"
+    (let f (Func [] (panic! "early exit")))
+    (f)
+    "#;
+    let mut context = new_context(engine);
+    context.abort_on_panic = true;
+    let _ = eval_string(input, &mut context);
+}
+
+fn eval_errors_carry_the_call_stack(engine: Engine) {
+    let mut context = new_context(engine);
+    let result = tan::eval::util::eval_file("tests/fixtures/func-error.tan", &mut context);
+    let errors = result.unwrap_err();
+    let error = errors.first().unwrap();
+
+    let names: Vec<&str> = error
+        .stack()
+        .iter()
+        .map(|frame| frame.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["dummy", "<top-level>"]);
+
+    let top_frame = error.stack().last().unwrap();
+    assert_eq!(top_frame.file_path, "tests/fixtures/func-error.tan");
+    assert_eq!(top_frame.range.as_ref().unwrap().start.line, 5);
+
+    let text = error.to_string();
+    assert!(text.contains("\n  at dummy ("));
+    assert!(text.contains("\n  at <top-level> (tests/fixtures/func-error.tan:6:1)"));
+}
+
+// #insight
+// The recursion runs in a thread with a small native stack, it would overflow
+// without the trampoline, even for a modest depth.
+fn tail_calls_do_not_grow_the_stack(engine: Engine) {
+    let handle = std::thread::Builder::new()
+        .stack_size(256 * 1024)
+        .spawn(move || {
+            let mut context = new_context(engine);
+            context.register("zero?", |n: i64| n == 0);
+            context.register("dec", |n: i64| n - 1);
+
+            let input = r#"
+            (let count-down (Func n
+                (if (zero? n)
+                    n
+                    (else (count-down (dec n)))
+                )
+            ))
+            (count-down 20000)
+            "#;
+            let value = eval_string(input, &mut context).unwrap();
+            assert_matches!(value.unpack(), Expr::Int(0));
+
+            // Mutual recursion, through cond and do.
+            let input = r#"
+            (let is-even? (Func n
+                (cond
+                    (zero? n) true
+                    else (do (let m (dec n)) (is-odd? m))
+                )
+            ))
+            (let is-odd? (Func n
+                (cond
+                    (zero? n) false
+                    else (is-even? (dec n))
+                )
+            ))
+            (is-even? 10001)
+            "#;
+            let value = eval_string(input, &mut context).unwrap();
+            assert_matches!(value.unpack(), Expr::Bool(false));
+        })
+        .unwrap();
+
+    handle.join().unwrap();
+}
+
+fn eval_preserves_the_insertion_order_of_maps_and_sets(engine: Engine) {
+    let mut context = new_context(engine);
+
+    let input = r#"(do (let m {:c 1 :a 2 :b 3}) [m (for->list [e m] e)])"#;
+    let value = eval_string(input, &mut context).unwrap();
+    let items = value.as_array().unwrap();
+
+    assert_eq!(items[0].to_string(), "{:c 1 :a 2 :b 3}");
+    assert_eq!(items[1].to_string(), "[[:c 1] [:a 2] [:b 3]]");
+
+    let set: IndexSet<Expr> = [3, 1, 2].into_iter().map(Expr::Int).collect();
+    context.scope.insert("s", Expr::set(set));
+    context
+        .scope
+        .get("s")
+        .unwrap()
+        .as_set_mut()
+        .unwrap()
+        .insert(Expr::Int(0));
+
+    let input = r#"[s (for->list [e s] e) #PersistentSet [3 1 2 1]]"#;
+    let value = eval_string(input, &mut context).unwrap();
+    let items = value.as_array().unwrap();
+
+    assert_eq!(items[0].to_string(), "[3 1 2 0]");
+    assert_eq!(items[1].to_string(), "[3 1 2 0]");
+    assert_eq!(items[2].to_string(), "#PersistentSet [3 1 2]");
+}
+
+fn eval_handles_persistent_collections(engine: Engine) {
+    let mut context = new_context(engine);
+
+    let input = r#"(do
+        (let x 2)
+        (let v #Vector [1 x 3])
+        (let m #PersistentMap {:b x :a 1})
+        [v (v 1) (v 5) m (m :b) (for->list [e m] e) #Vector [:a "b"]]
+    )"#;
+    let value = eval_string(input, &mut context).unwrap();
+    let items = value.as_array().unwrap();
+
+    assert_eq!(items[0].to_string(), "#Vector [1 2 3]");
+    assert_matches!(items[1].unpack(), Expr::Int(2));
+    assert_matches!(items[2].unpack(), Expr::None);
+    assert_eq!(items[3].to_string(), "#PersistentMap {:b 2 :a 1}");
+    assert_matches!(items[4].unpack(), Expr::Int(2));
+    assert_eq!(items[5].to_string(), "[#Vector [:b 2] #Vector [:a 1]]");
+    assert_eq!(items[6].to_string(), "#Vector [:a \"b\"]");
+
+    // Functional updates don't modify the original value.
+    let v = items[0].as_vector().unwrap();
+    let updated = v.update(0, Expr::Int(10));
+    assert_eq!(Expr::vector(updated).to_string(), "#Vector [10 2 3]");
+    assert_eq!(items[0].to_string(), "#Vector [1 2 3]");
+
+    let m = items[3].as_persistent_map().unwrap();
+    let updated = m.update(Expr::key_symbol("c"), Expr::Int(3));
+    assert_eq!(updated.len(), 3);
+    assert_eq!(m.len(), 2);
+}
+
+fn eval_updates_persistent_collections(engine: Engine) {
+    let mut context = new_context(engine);
+
+    let input = r#"(do
+        (let v #Vector [1 2 3])
+        (let m #PersistentMap {:b 2 :a 1})
+        (let s #PersistentSet [1 2])
+        [
+            (assoc v 0 10) (assoc v 3 4) (conj v 4) v
+            (assoc m :c 3) (assoc m :b 20) (dissoc m :b) m
+            (conj s 3) (conj s 1) (disj s 1) s
+        ]
+    )"#;
+    let value = eval_string(input, &mut context).unwrap();
+    let items = value.as_array().unwrap();
+
+    assert_eq!(items[0].to_string(), "#Vector [10 2 3]");
+    assert_eq!(items[1].to_string(), "#Vector [1 2 3 4]");
+    assert_eq!(items[2].to_string(), "#Vector [1 2 3 4]");
+    assert_eq!(items[3].to_string(), "#Vector [1 2 3]");
+    assert_eq!(items[4].to_string(), "#PersistentMap {:b 2 :a 1 :c 3}");
+    assert_eq!(items[5].to_string(), "#PersistentMap {:b 20 :a 1}");
+    assert_eq!(items[6].to_string(), "#PersistentMap {:a 1}");
+    assert_eq!(items[7].to_string(), "#PersistentMap {:b 2 :a 1}");
+    assert_eq!(items[8].to_string(), "#PersistentSet [1 2 3]");
+    assert_eq!(items[9].to_string(), "#PersistentSet [1 2]");
+    assert_eq!(items[10].to_string(), "#PersistentSet [2]");
+    assert_eq!(items[11].to_string(), "#PersistentSet [1 2]");
+
+    // The updates check the keys, and the indices.
+    for input in [
+        "(assoc #PersistentMap {:a 1} nan 2)",
+        "(conj #PersistentSet [1] nan)",
+        "(assoc #Vector [1] 2 3)",
+        "(assoc [1] 0 3)",
+    ] {
+        let errors = eval_string(input, &mut context).unwrap_err();
+        assert_matches!(errors[0].variant(), ErrorVariant::InvalidArguments);
+    }
+}
+
+fn eval_resolves_parameters_to_frame_slots(engine: Engine) {
+    let mut context = new_context(engine);
+    context.register("+", |a: i64, b: i64| a + b);
+
+    let input = r#"
+    (let y 100)
+    (let make-adder (Func x (Func y (+ x y))))
+    (let shadow (Func x (let x (+ x 1)) x))
+    (let fallback (Func [x y] (+ x y)))
+    (let update (Func x (<- x (+ x 2)) x))
+    [((make-adder 1) 2) (shadow 1) (fallback 3) (update 1)]
+    "#;
+    let value = eval_string(input, &mut context).unwrap();
+    let items = value.as_array().unwrap();
+
+    assert_matches!(items[0].unpack(), Expr::Int(3));
+    assert_matches!(items[1].unpack(), Expr::Int(2));
+    // The missing argument falls back to the outer binding.
+    assert_matches!(items[2].unpack(), Expr::Int(103));
+    assert_matches!(items[3].unpack(), Expr::Int(3));
+}
+
+fn eval_resolves_let_locals_to_frame_slots(engine: Engine) {
+    let mut context = new_context(engine);
+    context.register("+", |a: i64, b: i64| a + b);
+    context.register("<", |a: i64, b: i64| a < b);
+
+    let input = r#"
+    (let z 100)
+    (let sum (Func n (let i 0 s 0) (while (< i n) (<- s (+ s i)) (<- i (+ i 1))) s))
+    (let shadow (Func x (let y 1) (do (let y 2) (<- x (+ x y))) (+ x y)))
+    (let counter (Func [] (let n 0) (Func [] (<- n (+ n 1)) n)))
+    (let global (Func [] (let y z) (let z 1) (+ y z)))
+    (let c (counter))
+    (c)
+    [(sum 5) (shadow 1) (c) (global)]
+    "#;
+    let value = eval_string(input, &mut context).unwrap();
+    let items = value.as_array().unwrap();
+
+    assert_matches!(items[0].unpack(), Expr::Int(10));
+    // The nested `let` doesn't overwrite the slot.
+    assert_matches!(items[1].unpack(), Expr::Int(4));
+    // The closure updates the local of the enclosing frame.
+    assert_matches!(items[2].unpack(), Expr::Int(2));
+    // The reference before the `let` is looked up by name.
+    assert_matches!(items[3].unpack(), Expr::Int(101));
+}
+
+fn eval_bounds_execution_with_a_step_budget(engine: Engine) {
+    let mut context = new_context(engine);
+    context.register("+", |a: i64, b: i64| a + b);
+    context.step_budget = Some(1000);
+
+    // A step is an invocation or a loop iteration.
+    let value = eval_string("(do 1 2 (+ 1 2))", &mut context).unwrap();
+    assert_matches!(value.unpack(), Expr::Int(3));
+    assert_eq!(context.consumed_steps(), 1);
+
+    let input = r#"
+    (let spin (Func [] (while true 1)))
+    (spin)
+    "#;
+    let errors = eval_string(input, &mut context).unwrap_err();
+    let error = errors.first().unwrap();
+    assert_matches!(error.variant, ErrorVariant::BudgetExhausted);
+    assert!(error.range().is_some());
+    assert_eq!(context.consumed_steps(), 1001);
+    // The exhausted budget is sticky.
+    assert!(eval_string("1", &mut context).is_err());
+
+    // Tail calls don't grow the stack, but consume steps.
+    context.reset_consumed_steps();
+    let input = r#"
+    (let forever (Func x (forever x)))
+    (forever 1)
+    "#;
+    let errors = eval_string(input, &mut context).unwrap_err();
+    assert_matches!(errors[0].variant, ErrorVariant::BudgetExhausted);
+}
+
+fn eval_consumes_the_same_steps_in_both_engines(engine: Engine) {
+    let mut context = new_context(engine);
+    context.register("+", |a: i64, b: i64| a + b);
+    context.register("-", |a: i64, b: i64| a - b);
+    context.register("<", |a: i64, b: i64| a < b);
+    context.step_budget = Some(1000);
+
+    // 11 iterations, 11 invocations of `<`, 10 invocations of `+`.
+    let input = "(do (let i 0) (while (< i 10) (<- i (+ i 1))) i)";
+    let value = eval_string(input, &mut context).unwrap();
+    assert_matches!(value.unpack(), Expr::Int(10));
+    assert_eq!(context.consumed_steps(), 32);
+
+    // The Tan function invocations consume a step, also in tail position.
+    context.reset_consumed_steps();
+    let input = r#"
+    (let count-down (Func [n] (if (< 0 n) (count-down (- n 1)) (else n))))
+    (count-down 3)
+    "#;
+    let value = eval_string(input, &mut context).unwrap();
+    assert_matches!(value.unpack(), Expr::Int(0));
+    // 4 invocations of `count-down` and `<`, 3 invocations of `-`.
+    assert_eq!(context.consumed_steps(), 11);
+}
+
+fn eval_anchors_budget_exhaustion_to_the_enclosing_form(engine: Engine) {
+    let mut context = new_context(engine);
+    context.step_budget = Some(100);
+
+    // The literals have no range, the error is anchored to the loop.
+    let input = "(let i 0) (while true (<- i 1))";
+    let errors = eval_string(input, &mut context).unwrap_err();
+    let error = errors.first().unwrap();
+    assert_matches!(error.variant, ErrorVariant::BudgetExhausted);
+    let range = error.range().unwrap();
+    assert!(range.start.index >= input.find("(while").unwrap());
+    assert!(range.end.index <= input.len());
+}
+
+#[test]
+fn eval_and_vm_report_the_same_budget_errors() {
+    let inputs = [
+        // The exhausted budget is not caught by `try`.
+        "(let f (Func [x] (try (while true 1) (catch e)))) (f 1)",
+        // The budget is exhausted in a tail call.
+        "(let f (Func [n] (if (< n 1) 0 (else (f (+ n -1)))))) (f 100)",
+        "(let g (Func [n] (+ n 1))) (let f (Func [n] (g n) (f n))) (f 1)",
+        "(let f (Func [] (let i 0) (while true (<- i (+ i 1))))) (f)",
+    ];
+
+    for input in inputs {
+        let [eval_error, vm_error] = [Engine::Eval, Engine::Vm].map(|engine| {
+            let mut context = new_context(engine);
+            context.register("+", |a: i64, b: i64| a + b);
+            context.register("<", |a: i64, b: i64| a < b);
+            context.step_budget = Some(50);
+            let errors = eval_string(input, &mut context).unwrap_err();
+            errors.into_iter().next().unwrap()
+        });
+
+        assert_matches!(eval_error.variant, ErrorVariant::BudgetExhausted);
+        assert_matches!(vm_error.variant, ErrorVariant::BudgetExhausted);
+        assert_eq!(eval_error.range(), vm_error.range(), "{input}");
+        let frames = |error: &Error| {
+            error
+                .stack()
+                .iter()
+                .map(|frame| (frame.name.clone(), frame.range.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(frames(&eval_error), frames(&vm_error), "{input}");
+    }
+}
+
+engine_tests! {
+    fn do_reports_intermediate_errors;
+    fn eval_processes_keyword_symbols;
+    fn eval_reports_let_errors;
+    fn eval_handles_function_with_no_params;
+    fn eval_processes_map;
+    fn eval_processes_map_with_keys;
+    fn eval_preserves_the_type_of_map_keys;
+    fn eval_rejects_reference_values_as_map_keys;
+    fn eval_processes_multiline_strings;
+    fn eval_processes_multiline_text;
+    fn eval_processes_deep_data;
+    fn should_eval_panic;
+    fn eval_should_report_errors_in_function_incovations;
+    fn eval_should_report_undefined_symbol_errors;
+    fn eval_should_report_errors_in_args;
+    fn eval_should_handle_func_def_with_annotation;
+    fn eval_try_catches_errors;
+    fn eval_try_binds_the_error_with_type;
+    fn eval_try_requires_catch_clause;
+    fn eval_panic_unwinds_to_the_embedder;
+    fn eval_errors_carry_the_foreign_call_stack;
+    #[should_panic(expected = "early exit")]
+    fn eval_panic_aborts_if_requested;
+    fn eval_errors_carry_the_call_stack;
+    fn tail_calls_do_not_grow_the_stack;
+    fn eval_preserves_the_insertion_order_of_maps_and_sets;
+    fn eval_handles_persistent_collections;
+    fn eval_updates_persistent_collections;
+    fn eval_resolves_parameters_to_frame_slots;
+    fn eval_resolves_let_locals_to_frame_slots;
+    fn eval_bounds_execution_with_a_step_budget;
+    fn eval_consumes_the_same_steps_in_both_engines;
+    fn eval_anchors_budget_exhaustion_to_the_enclosing_form;
+}
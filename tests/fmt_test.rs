@@ -0,0 +1,130 @@
+use std::{fs, path::Path};
+
+use tan::{api::parse_string_all, fmt::format_string};
+
+fn collect_fixtures(path: &Path, paths: &mut Vec<std::path::PathBuf>) {
+    for entry in fs::read_dir(path).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect_fixtures(&path, paths);
+        } else if path.extension().is_some_and(|ext| ext == "tan") {
+            paths.push(path);
+        }
+    }
+}
+
+/// The fixtures with syntax errors, they cannot be formatted.
+const INVALID_FIXTURES: &[&str] = &[
+    "tests/fixtures/multiple-errors.tan",
+    "tests/fixtures/unterminated-list-expr.tan",
+];
+
+#[test]
+fn format_string_round_trips_all_fixtures() {
+    let mut paths = Vec::new();
+    collect_fixtures(Path::new("tests/fixtures"), &mut paths);
+
+    for path in paths {
+        let input = fs::read_to_string(&path).unwrap();
+
+        if INVALID_FIXTURES
+            .iter()
+            .any(|invalid| path == Path::new(invalid))
+        {
+            assert!(format_string(&input).is_err(), "{}", path.display());
+            continue;
+        }
+
+        // The unformatted inputs of the expected-output fixtures.
+        if path.starts_with("tests/fixtures/fmt") && !path.to_string_lossy().ends_with(".fmt.tan") {
+            continue;
+        }
+
+        let formatted =
+            format_string(&input).unwrap_or_else(|errors| panic!("{}: {errors:?}", path.display()));
+        assert_eq!(formatted, input, "{}", path.display());
+    }
+}
+
+// #insight The expected output of `name.tan` is kept in `name.fmt.tan`.
+#[test]
+fn format_string_matches_the_expected_output_fixtures() {
+    let mut count = 0;
+
+    for entry in fs::read_dir("tests/fixtures/fmt").unwrap() {
+        let path = entry.unwrap().path();
+        let Some(name) = path.to_str().and_then(|path| path.strip_suffix(".tan")) else {
+            continue;
+        };
+        if name.ends_with(".fmt") {
+            continue;
+        }
+
+        let input = fs::read_to_string(&path).unwrap();
+        let expected = fs::read_to_string(format!("{name}.fmt.tan")).unwrap();
+        let formatted = format_string(&input).unwrap();
+        assert_eq!(formatted, expected, "{}", path.display());
+
+        // The formatting does not change the meaning of the source.
+        let exprs = parse_string_all(&input).unwrap();
+        let formatted_exprs = parse_string_all(&formatted).unwrap();
+        let exprs: Vec<String> = exprs.iter().map(|expr| expr.to_string()).collect();
+        let formatted_exprs: Vec<String> = formatted_exprs
+            .iter()
+            .map(|expr| expr.to_string())
+            .collect();
+        assert_eq!(exprs, formatted_exprs, "{}", path.display());
+
+        // The canonical source is preserved byte-for-byte.
+        assert_eq!(
+            format_string(&expected).unwrap(),
+            expected,
+            "{name}.fmt.tan"
+        );
+
+        count += 1;
+    }
+
+    assert!(count > 0);
+}
+
+#[test]
+fn format_string_breaks_long_lists() {
+    let input = r#"; The users.
+(let users [{:name "George" :age 42} {:name "Alice" :age 30} {:name "Bob" :age 28}])
+
+
+(write   users)"#;
+
+    let expected = r#"; The users.
+(let users [
+    {:name "George" :age 42}
+    {:name "Alice" :age 30}
+    {:name "Bob" :age 28}
+])
+
+(write users)"#;
+
+    assert_eq!(format_string(input).unwrap(), expected);
+}
+
+#[test]
+fn format_string_keeps_the_line_breaks_and_reindents_the_lines() {
+    let input = r#"#(Func [Int] Int)
+(let f (Func [x]
+  (if (<   x 0)
+ 0
+        x)
+   ))
+"#;
+
+    let expected = r#"#(Func [Int] Int)
+(let f (Func [x]
+    (if (< x 0)
+        0
+        x)
+))
+"#;
+
+    assert_eq!(format_string(input).unwrap(), expected);
+}
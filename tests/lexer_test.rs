@@ -194,6 +194,91 @@ fn lex_handles_multiline_strings_with_double_quotes() {
     assert_matches!(string.kind(), TokenKind::String(lexeme) if lexeme == "Hello \"George\"\nHow are you?\n");
 }
 
+#[test]
+fn lex_handles_chars() {
+    let input = r"(write 'a' '\n' '\u{1F600}' '\'' ' ' '(1 2) 'sym)";
+    let tokens = Lexer::new(input).lex().unwrap();
+    assert_matches!(tokens[2].kind(), TokenKind::Char('a'));
+    assert_matches!(tokens[3].kind(), TokenKind::Char('\n'));
+    assert_matches!(tokens[4].kind(), TokenKind::Char('😀'));
+    assert_matches!(tokens[5].kind(), TokenKind::Char('\''));
+    assert_matches!(tokens[6].kind(), TokenKind::Char(' '));
+    assert_matches!(tokens[7].kind(), TokenKind::Quote);
+    assert_matches!(tokens[8].kind(), TokenKind::LeftParen);
+    assert_matches!(tokens[12].kind(), TokenKind::Quote);
+    assert_matches!(tokens[13].kind(), TokenKind::Symbol(lexeme) if lexeme == "sym");
+
+    let input = r"(write '\q')";
+    let err = Lexer::new(input).lex().unwrap_err();
+    assert_matches!(err[0].variant(), ErrorVariant::MalformedEscapeCode);
+
+    let input = r"(write '\u{110000}')";
+    let err = Lexer::new(input).lex().unwrap_err();
+    assert_matches!(err[0].variant(), ErrorVariant::MalformedEscapeCode);
+
+    let input = r"(write '\n)";
+    let err = Lexer::new(input).lex().unwrap_err();
+    assert_matches!(err[0].variant(), ErrorVariant::UnterminatedChar);
+}
+
+#[test]
+fn lex_disambiguates_chars_and_quoted_symbols() {
+    // A `'` followed by a single character and a closing `'` is a char.
+    let input = "'a'b";
+    let tokens = Lexer::new(input).lex().unwrap();
+    assert_eq!(tokens.len(), 2);
+    assert_matches!(tokens[0].kind(), TokenKind::Char('a'));
+    assert_matches!(tokens[1].kind(), TokenKind::Symbol(lexeme) if lexeme == "b");
+
+    let input = "'(a)";
+    let tokens = Lexer::new(input).lex().unwrap();
+    assert_eq!(tokens.len(), 4);
+    assert_matches!(tokens[0].kind(), TokenKind::Quote);
+    assert_matches!(tokens[1].kind(), TokenKind::LeftParen);
+    assert_matches!(tokens[2].kind(), TokenKind::Symbol(lexeme) if lexeme == "a");
+    assert_matches!(tokens[3].kind(), TokenKind::RightParen);
+
+    let input = "'('";
+    let tokens = Lexer::new(input).lex().unwrap();
+    assert_eq!(tokens.len(), 1);
+    assert_matches!(tokens[0].kind(), TokenKind::Char('('));
+
+    let input = "('ab 'a 'b' 'c)";
+    let tokens = Lexer::new(input).lex().unwrap();
+    assert_eq!(tokens.len(), 9);
+    assert_matches!(tokens[1].kind(), TokenKind::Quote);
+    assert_matches!(tokens[2].kind(), TokenKind::Symbol(lexeme) if lexeme == "ab");
+    assert_matches!(tokens[3].kind(), TokenKind::Quote);
+    assert_matches!(tokens[4].kind(), TokenKind::Symbol(lexeme) if lexeme == "a");
+    assert_matches!(tokens[5].kind(), TokenKind::Char('b'));
+    assert_matches!(tokens[6].kind(), TokenKind::Quote);
+    assert_matches!(tokens[7].kind(), TokenKind::Symbol(lexeme) if lexeme == "c");
+    assert_matches!(tokens[8].kind(), TokenKind::RightParen);
+
+    // A quote at the end of a line, or of the input, is not a char.
+    let input = "'\n'a";
+    let tokens = Lexer::new(input).lex().unwrap();
+    assert_matches!(tokens[0].kind(), TokenKind::Quote);
+    let input = "'";
+    let tokens = Lexer::new(input).lex().unwrap();
+    assert_matches!(tokens[0].kind(), TokenKind::Quote);
+}
+
+#[test]
+fn lex_handles_raw_strings() {
+    let input = r###"(write r"C:\path\n" r#"say "hi" ${name}"# r##"a "# b"## rest r)"###;
+    let tokens = Lexer::new(input).lex().unwrap();
+    assert_matches!(tokens[2].kind(), TokenKind::RawString(lexeme) if lexeme == r"C:\path\n");
+    assert_matches!(tokens[3].kind(), TokenKind::RawString(lexeme) if lexeme == r#"say "hi" ${name}"#);
+    assert_matches!(tokens[4].kind(), TokenKind::RawString(lexeme) if lexeme == r##"a "# b"##);
+    assert_matches!(tokens[5].kind(), TokenKind::Symbol(lexeme) if lexeme == "rest");
+    assert_matches!(tokens[6].kind(), TokenKind::Symbol(lexeme) if lexeme == "r");
+
+    let input = r##"(write r#"unterminated")"##;
+    let err = Lexer::new(input).lex().unwrap_err();
+    assert_matches!(err[0].variant(), ErrorVariant::UnterminatedString);
+}
+
 #[test]
 fn lex_handles_escape_codes_in_strings() {
     let input = r#"(writeln "hello\tworld")"#;
@@ -214,6 +299,43 @@ fn lex_handles_escape_codes_in_strings() {
     assert_matches!(err.variant(), ErrorVariant::MalformedEscapeCode);
 }
 
+#[test]
+fn lex_handles_unicode_and_control_escape_codes_in_strings() {
+    let input = r#"(writeln "smile \u{1F600}\r\0!")"#;
+    let tokens = Lexer::new(input).lex().unwrap();
+    assert_matches!(tokens[2].kind(), TokenKind::String(lexeme) if lexeme == "smile \u{1F600}\r\0!");
+
+    // Escape codes in template strings.
+    let input = r#"(writeln "${name} \u{2764}\t!")"#;
+    let tokens = Lexer::new(input).lex().unwrap();
+    assert_matches!(tokens[2].kind(), TokenKind::String(lexeme) if lexeme == "${name} \u{2764}\t!");
+
+    // Line continuation, the EOL and the indentation are skipped.
+    let input = "(writeln \"hello \\\n        world\")\n(exit)";
+    let tokens = Lexer::new(input).lex().unwrap();
+    assert_matches!(tokens[2].kind(), TokenKind::String(lexeme) if lexeme == "hello world");
+    assert_eq!(tokens[2].range().end.line, 1);
+    assert_eq!(tokens[5].range().start.line, 2);
+
+    // malformed code point, the note points at the escape code.
+    let input = "(writeln \"hello\n  \\u{110000} world\")";
+    let err = Lexer::new(input).lex().unwrap_err();
+    let err = &err[0];
+    assert_matches!(err.variant(), ErrorVariant::MalformedEscapeCode);
+    let range = err.range().unwrap();
+    assert_eq!(range.start.line, 1);
+    assert_eq!(range.start.col, 2);
+    assert_eq!(range.start.index, 18);
+    assert_eq!(range.end.col, 12);
+
+    let input = r#"(writeln "\u{}")"#;
+    let err = Lexer::new(input).lex().unwrap_err();
+    assert_matches!(err[0].variant(), ErrorVariant::MalformedEscapeCode);
+    let range = err[0].range().unwrap();
+    assert_eq!(range.start.index, 10);
+    assert_eq!(range.end.index, 14);
+}
+
 #[test]
 fn lex_reports_unterminated_strings() {
     let input = r##"(write "Hello)"##;
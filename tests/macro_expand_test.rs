@@ -0,0 +1,42 @@
+mod common;
+
+use tan::{
+    api::{compile_string, parse_string},
+    context::Context,
+    expr::{annotate, Expr},
+    macro_expand::macro_expand,
+};
+
+#[test]
+fn macro_expand_keeps_the_let_annotations() {
+    let input = "#(Func [Float] Float) (let relu (Func [x] x)) (let #Int a 1) (do (let b 2))";
+    let mut context = Context::new();
+    let exprs = compile_string(input, &mut context).unwrap();
+
+    // The annotation of the `let` form.
+    let relu = exprs[0].as_list().unwrap();
+    assert!(relu[0].annotation("type").is_some());
+    // The annotation of the bound name.
+    let a = exprs[1].as_list().unwrap();
+    assert!(a[1].annotation("type").is_some());
+    // The nested `let` keeps the range.
+    let nested = exprs[2].as_list().unwrap();
+    assert!(nested[1].range().is_some());
+
+    for expr in &exprs {
+        assert!(expr.range().is_some());
+    }
+}
+
+#[test]
+fn macro_expand_keeps_the_annotations_of_expanded_lists() {
+    let mut context = Context::new();
+
+    for input in ["(do (let a 1))", "(+<- a 1)", "'(a b)"] {
+        let expr = parse_string(input).unwrap();
+        let expr = annotate(expr, "deprecated", Expr::Bool(true));
+        let expr = macro_expand(expr, &mut context).unwrap().unwrap();
+        assert!(expr.annotation("deprecated").is_some(), "{input}");
+        assert!(expr.range().is_some(), "{input}");
+    }
+}
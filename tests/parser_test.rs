@@ -1,5 +1,7 @@
 mod common;
 
+use std::hash::{Hash, Hasher};
+
 use assert_matches::assert_matches;
 
 use tan::{
@@ -523,3 +525,179 @@ fn parse_handles_ellipsis() {
 
     assert_matches!(&exprs[1].unpack(), Expr::Symbol(s) if s == "...");
 }
+
+#[test]
+fn parse_handles_chars() {
+    let input = r"(Array 'a' '\t' '\\' '\u{1b}')";
+    let expr = parse_string(input).unwrap();
+    let Expr::List(exprs) = expr.unpack() else {
+        panic!("assertion failed: invalid form")
+    };
+    assert_matches!(exprs[1].unpack(), Expr::Char('a'));
+    assert_matches!(exprs[2].unpack(), Expr::Char('\t'));
+
+    // The chars are formatted in the same syntax.
+    assert_eq!(expr.to_string(), input);
+    assert_eq!(format_value(&exprs[4]), r"'\u{1b}'");
+}
+
+#[cfg(feature = "bigint")]
+#[test]
+fn parse_handles_big_int_literals() {
+    let input = "(Array 123n 0xffn 9223372036854775808 1_000)";
+    let expr = parse_string(input).unwrap();
+    let Expr::List(exprs) = expr.unpack() else {
+        panic!("assertion failed: invalid form")
+    };
+    assert_matches!(exprs[1].unpack(), Expr::BigInt(n) if n.to_string() == "123");
+    assert_matches!(exprs[2].unpack(), Expr::BigInt(n) if n.to_string() == "255");
+    // Literals that overflow Int are promoted to BigInt.
+    assert_matches!(exprs[3].unpack(), Expr::BigInt(n) if n.to_string() == "9223372036854775808");
+    assert_matches!(exprs[4].unpack(), Expr::Int(1000));
+
+    assert_eq!(
+        exprs[1].dyn_type(&tan::context::Context::new()).to_string(),
+        "BigInt"
+    );
+    assert_eq!(exprs[3].to_string(), "9223372036854775808n");
+    assert_eq!(exprs[1], parse_string("123n").unwrap());
+
+    let result = parse_string("12x3n");
+    assert_matches!(
+        &result.unwrap_err()[0].variant(),
+        ErrorVariant::MalformedInt
+    );
+}
+
+#[test]
+fn parse_handles_scientific_and_named_float_literals() {
+    let input = "(Array 1e9 2.5E-3 -1e-2 +inf -inf nan 0xfe inf)";
+    let expr = parse_string(input).unwrap();
+    let Expr::List(exprs) = expr.unpack() else {
+        panic!("assertion failed: invalid form")
+    };
+    assert_matches!(exprs[1].unpack(), Expr::Float(n) if *n == 1e9);
+    assert_matches!(exprs[2].unpack(), Expr::Float(n) if *n == 2.5e-3);
+    assert_matches!(exprs[3].unpack(), Expr::Float(n) if *n == -1e-2);
+    assert_matches!(exprs[4].unpack(), Expr::Float(n) if *n == f64::INFINITY);
+    assert_matches!(exprs[5].unpack(), Expr::Float(n) if *n == f64::NEG_INFINITY);
+    assert_matches!(exprs[6].unpack(), Expr::Float(n) if n.is_nan());
+    assert_matches!(exprs[7].unpack(), Expr::Int(0xfe));
+    // #insight `inf` without a sign is a symbol.
+    assert_matches!(exprs[8].unpack(), Expr::Symbol(s) if s == "inf");
+
+    // The floats are formatted back to literals with the same value.
+    assert_eq!(
+        expr.to_string(),
+        "(Array 1000000000.0 0.0025 -0.01 +inf -inf nan 254 inf)"
+    );
+    let Expr::List(reparsed) = parse_string(expr.to_string()).unwrap().unpack().clone() else {
+        panic!("assertion failed: invalid form")
+    };
+    assert_eq!(reparsed[1..6], exprs[1..6]);
+    assert_matches!(reparsed[6].unpack(), Expr::Float(n) if n.is_nan());
+}
+
+#[test]
+fn parse_handles_ratio_literals() {
+    let input = "(Array 1/3 2/4 -6/3 1/2)";
+    let expr = parse_string(input).unwrap();
+    let Expr::List(exprs) = expr.unpack() else {
+        panic!("assertion failed: invalid form")
+    };
+    assert_matches!(exprs[1].unpack(), Expr::Ratio(n) if *n.numer() == 1 && *n.denom() == 3);
+
+    // Ratios are reduced, equality and hashing use the reduced form.
+    assert_eq!(exprs[2], exprs[4]);
+    let hash = |expr: &Expr| {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        expr.hash(&mut hasher);
+        hasher.finish()
+    };
+    assert_eq!(hash(&exprs[2]), hash(&exprs[4]));
+
+    assert_eq!(expr.to_string(), "(Array 1/3 1/2 -2/1 1/2)");
+    assert_eq!(
+        exprs[1].dyn_type(&tan::context::Context::new()).to_string(),
+        "Ratio"
+    );
+
+    let result = parse_string("1/0");
+    assert_matches!(
+        &result.unwrap_err()[0].variant(),
+        ErrorVariant::MalformedRatio
+    );
+
+    let result = parse_string("1/x");
+    assert_matches!(
+        &result.unwrap_err()[0].variant(),
+        ErrorVariant::MalformedRatio
+    );
+}
+
+#[test]
+fn parse_handles_temporal_literals() {
+    let input =
+        r#"(Array #Date "2024-01-15" #Instant "2024-01-15T10:30:00+02:00" #Duration "PT1H30M")"#;
+    let expr = parse_string(input).unwrap();
+    let Expr::List(exprs) = expr.unpack() else {
+        panic!("assertion failed: invalid form")
+    };
+    assert_matches!(exprs[1].unpack(), Expr::Date(d) if d.to_string() == "2024-01-15");
+    assert_matches!(exprs[2].unpack(), Expr::Instant(t) if t.timestamp() == 1705307400);
+    assert_matches!(exprs[3].unpack(), Expr::Duration(d) if d.num_minutes() == 90);
+
+    let context = tan::context::Context::new();
+    assert_eq!(exprs[1].dyn_type(&context).to_string(), "Date");
+    assert_eq!(exprs[2].dyn_type(&context).to_string(), "Instant");
+    assert_eq!(exprs[3].dyn_type(&context).to_string(), "Duration");
+
+    // The temporal values are formatted back to literals.
+    assert_eq!(expr.to_string(), input);
+    assert_eq!(format_value(&exprs[1]), r#"#Date "2024-01-15""#);
+
+    let earlier = parse_string(r#"#Date "2023-12-31""#).unwrap();
+    assert!(earlier < exprs[1]);
+    assert_eq!(
+        parse_string(r#"#Instant "2024-01-15T08:30:00Z""#).unwrap(),
+        exprs[2]
+    );
+
+    for (input, formatted) in [
+        ("P1DT2H0.5S", "P1DT2H0.5S"),
+        ("-PT30S", "-PT30S"),
+        ("P2W", "P14D"),
+        ("PT0S", "PT0S"),
+    ] {
+        let expr = parse_string(format!(r#"#Duration "{input}""#)).unwrap();
+        assert_eq!(expr.to_string(), format!(r#"#Duration "{formatted}""#));
+    }
+
+    for input in [
+        r#"#Date "2024-13-01""#,
+        r#"#Instant "2024-01-15""#,
+        r#"#Duration "P1Y""#,
+        r#"#Duration "PT""#,
+    ] {
+        let result = parse_string(input);
+        assert_matches!(
+            &result.unwrap_err()[0].variant(),
+            ErrorVariant::MalformedTemporal
+        );
+    }
+}
+
+#[test]
+fn parse_handles_persistent_collection_literals() {
+    let expr = parse_string("#Vector [1 2 3]").unwrap();
+    assert_eq!(expr.to_string(), "(Vector 1 2 3)");
+    assert_eq!(expr.annotation("type"), Some(&Expr::typ("Vector")));
+
+    let expr = parse_string("#PersistentMap {:a 1}").unwrap();
+    assert_eq!(expr.to_string(), "(PersistentMap :a 1)");
+
+    // The annotation is not attributed to the first item.
+    let expr = parse_string("#Point [1 2]").unwrap();
+    assert_eq!(expr.to_string(), "(Array 1 2)");
+    assert_eq!(expr.annotation("type"), Some(&Expr::typ("Point")));
+}
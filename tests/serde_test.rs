@@ -0,0 +1,82 @@
+#![cfg(feature = "serde")]
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use tan::{api::eval_string, context::ContextBuilder, de, ser};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Role {
+    Admin,
+    Guest { expires: i64 },
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct User {
+    given_name: String,
+    score: u32,
+    ratio: f64,
+    labels: Vec<String>,
+    role: Role,
+    nickname: Option<String>,
+    extra: HashMap<String, bool>,
+}
+
+#[test]
+fn de_from_str_reads_data_expressions() {
+    let input = r#"
+    {
+        :given_name "George"
+        :score 98
+        :ratio 0.5
+        :labels ["admin" "owner"]
+        :role :Admin
+        :nickname ()
+        :extra {:active true}
+    }
+    "#;
+
+    let user: User = de::from_str(input).unwrap();
+
+    assert_eq!(user.given_name, "George");
+    assert_eq!(user.score, 98);
+    assert_eq!(user.labels, vec!["admin", "owner"]);
+    assert_eq!(user.role, Role::Admin);
+    assert_eq!(user.nickname, None);
+    assert!(user.extra["active"]);
+}
+
+#[test]
+fn de_from_expr_reads_evaluated_values() {
+    let mut context = ContextBuilder::new().root_path("/tmp").try_build().unwrap();
+    let expr = eval_string("[1 2 3]", &mut context).unwrap();
+
+    let values: Vec<i64> = de::from_expr(expr).unwrap();
+    assert_eq!(values, vec![1, 2, 3]);
+}
+
+#[test]
+fn de_reports_invalid_types() {
+    let result: Result<User, _> = de::from_str(r#"{:given_name 1}"#);
+    assert!(result.is_err());
+}
+
+#[test]
+fn ser_to_string_round_trips() {
+    let user = User {
+        given_name: "Eleni \"E\"\n".to_string(),
+        score: 100,
+        ratio: 1.0,
+        labels: vec!["user".to_string()],
+        role: Role::Guest { expires: 2030 },
+        nickname: Some("el".to_string()),
+        extra: HashMap::from([("active".to_string(), false)]),
+    };
+
+    let text = ser::to_string(&user).unwrap();
+    assert!(text.starts_with("{:given_name \"Eleni \\\"E\\\"\\n\" :score 100"));
+    assert!(text.ends_with(r#":extra {"active" false}}"#));
+
+    let value: User = de::from_str(&text).unwrap();
+    assert_eq!(value, user);
+}